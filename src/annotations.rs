@@ -0,0 +1,135 @@
+use crate::config;
+use crate::process::Course;
+use crate::restrictions::CourseCode;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// What people know about a course that the catalog doesn't say, like advising
+/// notes or tags such as `being retired` or `staffing risk`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.tags.is_empty() {
+            write!(f, "[{}]", self.tags.join(", "))?;
+        }
+        match (&self.note, self.tags.is_empty()) {
+            (Some(note), true) => write!(f, "{note}"),
+            (Some(note), false) => write!(f, " {note}"),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+/// `annotations.toml`, one table per course, kept apart from the scraped data so
+/// that it survives downloading and processing again:
+///
+/// ```toml
+/// ["CSCI 0150"]
+/// note = "Advisors suggest taking it alongside CSCI 0200"
+/// tags = ["staffing risk"]
+/// ```
+#[derive(Debug, Default)]
+pub struct Annotations {
+    courses: BTreeMap<CourseCode, Annotation>,
+}
+
+impl Annotations {
+    /// Empty if there is no file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Annotations> {
+        let path = path.as_ref();
+        let tables = match config::tables(path) {
+            Ok(tables) => tables,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Annotations::default()),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: String| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{path:?}: {message}"))
+        };
+        let mut courses = BTreeMap::new();
+        for (code, table) in tables {
+            let code = CourseCode::try_from(code.as_str())
+                .map_err(|_| invalid(format!("invalid course code: {code}")))?;
+            let mut annotation = Annotation::default();
+            for (key, value) in table {
+                match key.as_str() {
+                    "note" => annotation.note = Some(value),
+                    "tags" => annotation.tags = value.split(',').map(String::from).collect(),
+                    _ => {
+                        return Err(invalid(format!(
+                            "unknown key {key} for {code}, expected note or tags"
+                        )))
+                    }
+                }
+            }
+            courses.insert(code, annotation);
+        }
+        Ok(Annotations { courses })
+    }
+
+    pub fn get(&self, code: &CourseCode) -> Option<&Annotation> {
+        self.courses.get(code)
+    }
+
+    /// Attaches each course's annotation to it
+    pub fn apply(&self, courses: &mut [Course]) {
+        for course in courses.iter_mut() {
+            if let Some(annotation) = self.get(course.code()) {
+                course.set_annotation(annotation.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Annotation, Annotations};
+    use crate::restrictions::CourseCode;
+    use std::fs;
+
+    #[test]
+    fn load() {
+        let path =
+            std::env::temp_dir().join(format!("cab-annotations-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            r#"
+            ["CSCI 0150"]
+            note = "Pair with CSCI 0200"
+            tags = ["being retired", "staffing risk"]
+
+            ["MATH 0100"]
+            tags = ["staffing risk"]
+            "#,
+        )
+        .unwrap();
+        let annotations = Annotations::load(&path).unwrap();
+        let annotation = annotations
+            .get(&CourseCode::try_from("CSCI 0150").unwrap())
+            .unwrap();
+        assert_eq!(
+            annotation.to_string(),
+            "[being retired, staffing risk] Pair with CSCI 0200"
+        );
+        let note = Annotation {
+            note: Some(String::from("Pair with CSCI 0200")),
+            tags: Vec::new(),
+        };
+        assert_eq!(note.to_string(), "Pair with CSCI 0200");
+
+        fs::write(&path, "[\"CSCI 0150\"]\nowner = \"me\"").unwrap();
+        assert!(Annotations::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(Annotations::load(&path).unwrap().courses.is_empty());
+    }
+}
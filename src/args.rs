@@ -0,0 +1,145 @@
+use crate::config::Profile;
+use std::io;
+use std::str::FromStr;
+
+/// Command line arguments, consumed as each subcommand asks for them.
+///
+/// Options and flags should be taken before positional arguments, so that their
+/// values aren't mistaken for positional arguments.
+///
+/// Options and flags missing from the command line fall back to the profile's.
+pub struct Args {
+    args: Vec<String>,
+    profile: Profile,
+}
+
+impl Args {
+    pub fn new<I: IntoIterator<Item = String>>(args: I) -> Args {
+        Args {
+            args: args.into_iter().collect(),
+            profile: Profile::new(),
+        }
+    }
+
+    pub fn with_profile(self, profile: Profile) -> Args {
+        Args { profile, ..self }
+    }
+
+    /// `--name value` or `--name=value`
+    pub fn option<T: FromStr>(&mut self, name: &str) -> io::Result<Option<T>> {
+        let flag = format!("--{name}");
+        let prefix = format!("--{name}=");
+        let value = if let Some(i) = self.args.iter().position(|arg| arg == &flag) {
+            if i + 1 >= self.args.len() {
+                return Err(invalid(format!("{flag} needs a value")));
+            }
+            self.args.remove(i);
+            self.args.remove(i)
+        } else if let Some(i) = self.args.iter().position(|arg| arg.starts_with(&prefix)) {
+            self.args.remove(i)[prefix.len()..].to_string()
+        } else if let Some(value) = self.profile.remove(name) {
+            value
+        } else {
+            return Ok(None);
+        };
+        match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(invalid(format!("invalid value for {flag}: {value}"))),
+        }
+    }
+
+    /// Every value of an option that may be given more than once, or the profile's
+    /// comma separated values if the command line has none
+    pub fn options<T: FromStr>(&mut self, name: &str) -> io::Result<Vec<T>> {
+        let profile = self.profile.remove(name);
+        let mut values = Vec::new();
+        while let Some(value) = self.option(name)? {
+            values.push(value);
+        }
+        if let (true, Some(profile)) = (values.is_empty(), profile) {
+            for value in profile.split(',') {
+                let value = value
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid value for --{name}: {value}")))?;
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+
+    pub fn flag(&mut self, name: &str) -> bool {
+        let flag = format!("--{name}");
+        let profile = self.profile.remove(name);
+        match self.args.iter().position(|arg| arg == &flag) {
+            Some(i) => {
+                self.args.remove(i);
+                true
+            }
+            None => profile.as_deref() == Some("true"),
+        }
+    }
+
+    pub fn positional(&mut self) -> Option<String> {
+        let i = self.args.iter().position(|arg| !arg.starts_with("--"))?;
+        Some(self.args.remove(i))
+    }
+
+    /// `default` is under `output/`, or the profile's `output-directory` instead
+    pub fn positional_or(&mut self, default: &str) -> String {
+        self.positional().unwrap_or_else(|| self.output(default))
+    }
+
+    /// `path` moved from `output/` to the profile's `output-directory`, if it has one
+    pub fn output(&self, path: &str) -> String {
+        match (
+            self.profile.get("output-directory"),
+            path.strip_prefix("output/"),
+        ) {
+            (Some(directory), Some(rest)) => format!("{}/{rest}", directory.trim_end_matches('/')),
+            _ => path.to_string(),
+        }
+    }
+
+    /// Errors if there are any arguments left that no one asked for.
+    pub fn finish(self) -> io::Result<()> {
+        match self.args.first() {
+            Some(arg) => Err(invalid(format!("unexpected argument: {arg}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use crate::config::Profile;
+
+    #[test]
+    fn profile() {
+        let profile = Profile::from([
+            ("subject".to_string(), "CSCI".to_string()),
+            ("terms".to_string(), "202210,202220".to_string()),
+            ("attribute".to_string(), "WRIT,DIAP".to_string()),
+            ("gatekeeper".to_string(), "true".to_string()),
+            ("force".to_string(), "false".to_string()),
+            ("output-directory".to_string(), "output/csci/".to_string()),
+        ]);
+        let args = ["--terms", "202310", "--attribute=RPP"].map(String::from);
+        let mut args = Args::new(args).with_profile(profile);
+        assert_eq!(args.option("subject").unwrap(), Some(String::from("CSCI")));
+        assert_eq!(args.option("terms").unwrap(), Some(String::from("202310")));
+        assert_eq!(args.options::<String>("attribute").unwrap(), ["RPP"]);
+        assert!(args.flag("gatekeeper"));
+        assert!(!args.flag("force"));
+        assert_eq!(
+            args.positional_or("output/cab.jsonl"),
+            "output/csci/cab.jsonl"
+        );
+        assert_eq!(args.positional_or("cache"), "cache");
+        args.finish().unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, Read, Write};
+
+/// The first bytes of a binary file of courses, which JSONL can't start with
+pub const MAGIC: &[u8] = b"\0cab";
+
+/// A compact form of the values in a file of courses, quicker to read than JSONL.
+///
+/// Each value is a tag byte and then its contents: lengths and integers are LEB128
+/// varints, floats are little-endian, and an object key is the index of an earlier key
+/// plus one, or a zero and the key itself the first time the file uses it. Like
+/// JSONL, the values follow one another with nothing between them, so a header comes
+/// first and courses are written and read one at a time.
+pub struct Writer<W> {
+    output: W,
+    keys: HashMap<String, u64>,
+}
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const UNSIGNED: u8 = 3;
+/// Stored as `-1 - n`, so that -1 takes one byte
+const NEGATIVE: u8 = 4;
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const ARRAY: u8 = 7;
+const OBJECT: u8 = 8;
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut output: W) -> io::Result<Writer<W>> {
+        output.write_all(MAGIC)?;
+        Ok(Writer {
+            output,
+            keys: Default::default(),
+        })
+    }
+
+    pub fn write<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        self.value(&serde_json::to_value(value)?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn value(&mut self, value: &Value) -> io::Result<()> {
+        match value {
+            Value::Null => self.output.write_all(&[NULL]),
+            Value::Bool(false) => self.output.write_all(&[FALSE]),
+            Value::Bool(true) => self.output.write_all(&[TRUE]),
+            Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+                (Some(n), _) => {
+                    self.output.write_all(&[UNSIGNED])?;
+                    self.varint(n)
+                }
+                (None, Some(n)) => {
+                    self.output.write_all(&[NEGATIVE])?;
+                    self.varint(!n as u64)
+                }
+                (None, None) => {
+                    self.output.write_all(&[FLOAT])?;
+                    self.output
+                        .write_all(&number.as_f64().unwrap_or_default().to_le_bytes())
+                }
+            },
+            Value::String(string) => {
+                self.output.write_all(&[STRING])?;
+                self.string(string)
+            }
+            Value::Array(array) => {
+                self.output.write_all(&[ARRAY])?;
+                self.varint(array.len() as u64)?;
+                array.iter().try_for_each(|value| self.value(value))
+            }
+            Value::Object(object) => {
+                self.output.write_all(&[OBJECT])?;
+                self.varint(object.len() as u64)?;
+                for (key, value) in object {
+                    match self.keys.get(key) {
+                        Some(&index) => self.varint(index + 1)?,
+                        None => {
+                            self.keys.insert(key.clone(), self.keys.len() as u64);
+                            self.varint(0)?;
+                            self.string(key)?;
+                        }
+                    }
+                    self.value(value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn string(&mut self, string: &str) -> io::Result<()> {
+        self.varint(string.len() as u64)?;
+        self.output.write_all(string.as_bytes())
+    }
+
+    fn varint(&mut self, mut n: u64) -> io::Result<()> {
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                return self.output.write_all(&[byte]);
+            }
+            self.output.write_all(&[byte | 0x80])?;
+        }
+    }
+}
+
+/// The values a `Writer` wrote, in order
+pub struct Reader<R> {
+    input: R,
+    keys: Vec<String>,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(mut input: R) -> io::Result<Reader<R>> {
+        let mut magic = [0; MAGIC.len()];
+        input.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a binary file of courses"));
+        }
+        Ok(Reader {
+            input,
+            keys: Vec::new(),
+        })
+    }
+
+    /// The next value, or `None` at the end of the input
+    pub fn read<T: DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+        if self.input.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_value(self.value()?)?))
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        Ok(match self.byte()? {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            UNSIGNED => Value::from(self.varint()?),
+            NEGATIVE => Value::from(!self.varint()? as i64),
+            FLOAT => {
+                let mut bytes = [0; 8];
+                self.input.read_exact(&mut bytes)?;
+                Number::from_f64(f64::from_le_bytes(bytes)).map_or(Value::Null, Value::Number)
+            }
+            STRING => Value::String(self.string()?),
+            ARRAY => {
+                let len = self.varint()?;
+                let mut array = Vec::new();
+                for _ in 0..len {
+                    array.push(self.value()?);
+                }
+                Value::Array(array)
+            }
+            OBJECT => {
+                let len = self.varint()?;
+                let mut object = Map::new();
+                for _ in 0..len {
+                    let key = match self.varint()? {
+                        0 => {
+                            let key = self.string()?;
+                            self.keys.push(key.clone());
+                            key
+                        }
+                        index => self
+                            .keys
+                            .get(index as usize - 1)
+                            .cloned()
+                            .ok_or_else(|| invalid(format!("no key numbered {index}")))?,
+                    };
+                    object.insert(key, self.value()?);
+                }
+                Value::Object(object)
+            }
+            tag => return Err(invalid(format!("unknown tag {tag}"))),
+        })
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let mut bytes = Vec::new();
+        let len = self.varint()?;
+        (&mut self.input).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(invalid)
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(invalid("varint longer than 64 bits"))
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        self.input.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<Value>;
+
+    fn next(&mut self) -> Option<io::Result<Value>> {
+        self.read().transpose()
+    }
+}
+
+/// Whether `input` starts like a binary file, without consuming anything
+pub fn is_binary<R: BufRead>(input: &mut R) -> io::Result<bool> {
+    Ok(input.fill_buf()?.starts_with(MAGIC))
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Reader, Writer};
+    use crate::process::Course;
+    use crate::restrictions::PrerequisiteTree;
+    use crate::schema;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let values = [
+            json!({"schema_version": 2}),
+            json!({"a": [null, true, false, 0, 300, -1, -70000, 1.5, "é"], "b": {"a": {}}}),
+            json!({"b": [], "a": u64::MAX, "c": i64::MIN}),
+        ];
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        for value in values.iter() {
+            writer.write(value).unwrap();
+        }
+        let bytes = writer.into_inner();
+        let read: Vec<_> = Reader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, values);
+        let jsonl: usize = values.iter().map(|value| value.to_string().len() + 1).sum();
+        assert!(bytes.len() < jsonl);
+
+        let tree = PrerequisiteTree::try_from(
+            "(CSCI 0150 or minimum score of 4 in 'AP Calculus BC') and not MATH 0100",
+        )
+        .unwrap();
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(&tree).unwrap();
+        let bytes = writer.into_inner();
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.read::<PrerequisiteTree>().unwrap(), Some(tree));
+        assert!(reader.read::<PrerequisiteTree>().unwrap().is_none());
+
+        assert!(Reader::new(&b"{}"[..]).is_err());
+        let truncated = &bytes[..bytes.len() - 1];
+        let mut reader = Reader::new(truncated).unwrap();
+        assert!(reader.read::<PrerequisiteTree>().is_err());
+    }
+
+    #[test]
+    fn courses() {
+        let mut course = Course::fixture("CSCI 0150", json!([{}]));
+        course["prerequisites"] = json!({"course": {"subject": "CSCI", "number": "0111"}});
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(&course).unwrap();
+        let (courses, version) = schema::read(writer.into_inner().as_slice()).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(
+            courses[0].offerings()[0].term().unwrap().to_string(),
+            "Fall 2022"
+        );
+
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer
+            .write(&schema::Header {
+                schema_version: schema::VERSION,
+            })
+            .unwrap();
+        writer.write(&courses[0]).unwrap();
+        let (read, version) = schema::read(writer.into_inner().as_slice()).unwrap();
+        assert_eq!(version, schema::VERSION);
+        assert_eq!(
+            serde_json::to_value(&read[0]).unwrap(),
+            serde_json::to_value(&courses[0]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::process::Course;
+use crate::restrictions::{CourseCode, PrerequisiteTree};
+use crate::subjects::Subjects;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "md" | "markdown" => Ok(Format::Markdown),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn changelog(old: &[Course], new: &[Course], format: Format, subjects: &Subjects) -> String {
+    match format {
+        Format::Markdown => markdown(old, new, subjects),
+    }
+}
+
+/// How the catalog changed between two processed outputs, as a Markdown document
+/// organized by subject
+fn markdown(old: &[Course], new: &[Course], subjects: &Subjects) -> String {
+    let old: BTreeMap<&CourseCode, &Course> = old.iter().map(|c| (c.code(), c)).collect();
+    let new: BTreeMap<&CourseCode, &Course> = new.iter().map(|c| (c.code(), c)).collect();
+    let mut codes: Vec<&CourseCode> = old.keys().chain(new.keys()).copied().collect();
+    codes.sort();
+    codes.dedup();
+
+    let mut changed: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut footnotes = String::new();
+    for code in codes {
+        let (verb, course, changes) = match (old.get(code), new.get(code)) {
+            (None, Some(course)) => ("Added", course, String::new()),
+            (Some(course), None) => ("Removed", course, String::new()),
+            (Some(old), Some(new)) => match changes(old, new) {
+                Some(changes) => ("Changed", new, changes),
+                None => continue,
+            },
+            (None, None) => unreachable!(),
+        };
+        let mut entry = format!("- {verb} **{code}** {}", course.title());
+        if let Some(annotation) = course.annotation() {
+            let label = code.to_string().replace(' ', "-");
+            write!(entry, "[^{label}]").unwrap();
+            writeln!(footnotes, "[^{label}]: {annotation}").unwrap();
+        }
+        entry.push('\n');
+        entry.push_str(&changes);
+        changed.entry(code.subject()).or_default().push(entry);
+    }
+
+    let mut markdown = String::from("# Catalog changes\n");
+    if changed.is_empty() {
+        markdown.push_str("\nNo changes.\n");
+    }
+    let mut order: Vec<&str> = changed.keys().copied().collect();
+    subjects.sort(&mut order);
+    for subject in order {
+        write!(markdown, "\n## {}\n\n", subjects.display(subject)).unwrap();
+        for entry in changed[subject].iter() {
+            markdown.push_str(entry);
+        }
+    }
+    if !footnotes.is_empty() {
+        markdown.push('\n');
+        markdown.push_str(&footnotes);
+    }
+    markdown
+}
+
+/// Nested list items describing what changed, or `None` if nothing did
+fn changes(old: &Course, new: &Course) -> Option<String> {
+    let mut changes = String::new();
+    if old.title() != new.title() {
+        writeln!(changes, "  - Title: {} → {}", old.title(), new.title()).unwrap();
+    }
+    if old.description() != new.description() {
+        changes.push_str("  - Description changed\n");
+    }
+    if old.prerequisites() != new.prerequisites() {
+        writeln!(
+            changes,
+            "  - Prerequisites: {} → {}",
+            prerequisites(old.prerequisites()),
+            prerequisites(new.prerequisites())
+        )
+        .unwrap();
+    }
+    if old.semester_range() != new.semester_range() {
+        writeln!(
+            changes,
+            "  - Open to semesters: {} → {}",
+            old.semester_range(),
+            new.semester_range()
+        )
+        .unwrap();
+    }
+    (!changes.is_empty()).then_some(changes)
+}
+
+fn prerequisites(tree: Option<&PrerequisiteTree>) -> String {
+    match tree {
+        Some(tree) => tree.to_string(),
+        None => String::from("none"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{changelog, Format};
+    use crate::process::Course;
+    use crate::subjects::Subjects;
+    use serde_json::json;
+
+    fn course(code: &str, title: &str, annotation: serde_json::Value) -> Course {
+        let mut course = Course::fixture(code, json!([]));
+        course["title"] = title.into();
+        course["annotation"] = annotation;
+        serde_json::from_value(course).unwrap()
+    }
+
+    #[test]
+    fn markdown() {
+        let old = [
+            course("CSCI 0150", "Intro", json!(null)),
+            course("CSCI 0160", "Algorithms", json!(null)),
+            course("MATH 0100", "Calculus", json!(null)),
+        ];
+        let new = [
+            course(
+                "CSCI 0150",
+                "Intro to Programming",
+                json!({"note": "Last offered in 2024", "tags": ["being retired"]}),
+            ),
+            course("CSCI 0200", "Data Structures", json!(null)),
+            course("MATH 0100", "Calculus", json!(null)),
+        ];
+        let subjects = Subjects::default();
+        let markdown = changelog(&old, &new, Format::Markdown, &subjects);
+        assert!(markdown.contains(
+            "- Changed **CSCI 0150** Intro to Programming[^CSCI-0150]\n  \
+             - Title: Intro → Intro to Programming\n"
+        ));
+        assert!(markdown.contains("- Removed **CSCI 0160** Algorithms\n"));
+        assert!(markdown.contains("- Added **CSCI 0200** Data Structures\n"));
+        assert!(!markdown.contains("MATH 0100"));
+        assert!(markdown.ends_with("\n[^CSCI-0150]: [being retired] Last offered in 2024\n"));
+
+        let unchanged = changelog(&old, &old, Format::Markdown, &subjects);
+        assert_eq!(unchanged, "# Catalog changes\n\nNo changes.\n");
+    }
+}
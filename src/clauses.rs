@@ -0,0 +1,132 @@
+use crate::logic::Tree;
+use crate::process::Course;
+use crate::restrictions::Qualification;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Formats for the implications between courses and their prerequisites, so
+/// they can be given to external reasoners.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// Soufflé facts `requires(course, clause, qualification)`: taking `course` requires
+    /// at least one qualification from each of its clauses
+    Datalog,
+    /// CNF where each variable means a qualification is held
+    Dimacs,
+}
+
+impl FromStr for Format {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "datalog" => Ok(Format::Datalog),
+            "dimacs" => Ok(Format::Dimacs),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Each course with its prerequisites in conjunctive normal form, leaving out those
+/// with prerequisites it can't express, like a negation
+fn implications(courses: &[Course]) -> Vec<(Qualification, Vec<Vec<Qualification>>)> {
+    let mut ret: Vec<_> = courses
+        .iter()
+        .filter_map(|course| {
+            let product = course.prerequisites()?.to_product()?;
+            let clauses = product
+                .clauses()
+                .map(|clause| clause.into_iter().cloned().collect())
+                .collect();
+            Some((Qualification::Course(course.code().clone()), clauses))
+        })
+        .collect();
+    ret.sort_by(|a, b| a.0.cmp(&b.0));
+    ret
+}
+
+pub fn export(courses: &[Course], format: Format) -> String {
+    let implications = implications(courses);
+    match format {
+        Format::Datalog => datalog(&implications),
+        Format::Dimacs => dimacs(&implications),
+    }
+}
+
+fn datalog(implications: &[(Qualification, Vec<Vec<Qualification>>)]) -> String {
+    let quote = |q: &Qualification| format!("{:?}", q.to_string());
+    let mut ret =
+        String::from(".decl requires(course: symbol, clause: number, qualification: symbol)\n");
+    for (course, clauses) in implications {
+        for (i, clause) in clauses.iter().enumerate() {
+            for qualification in clause {
+                writeln!(
+                    ret,
+                    "requires({}, {i}, {}).",
+                    quote(course),
+                    quote(qualification)
+                )
+                .unwrap();
+            }
+        }
+    }
+    ret
+}
+
+fn dimacs(implications: &[(Qualification, Vec<Vec<Qualification>>)]) -> String {
+    let mut variables = BTreeMap::new();
+    let mut variable = |q: &Qualification| -> usize {
+        let next = variables.len() + 1;
+        *variables.entry(q.clone()).or_insert(next)
+    };
+    let mut clauses = Vec::new();
+    for (course, prerequisites) in implications {
+        let course = variable(course);
+        for clause in prerequisites {
+            // course ⇒ (a ∨ b) === ¬course ∨ a ∨ b
+            let mut literals = vec![-(course as i64)];
+            literals.extend(clause.iter().map(|q| variable(q) as i64));
+            clauses.push(literals);
+        }
+    }
+    let mut names: Vec<_> = variables.into_iter().collect();
+    names.sort_by_key(|&(_, v)| v);
+    let mut ret = String::new();
+    for (qualification, v) in names.iter() {
+        writeln!(ret, "c {v} {qualification}").unwrap();
+    }
+    writeln!(ret, "p cnf {} {}", names.len(), clauses.len()).unwrap();
+    for clause in clauses {
+        for literal in clause {
+            write!(ret, "{literal} ").unwrap();
+        }
+        ret.push_str("0\n");
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{datalog, dimacs};
+    use crate::restrictions::{CourseCode, Qualification};
+
+    fn course(code: &str) -> Qualification {
+        Qualification::Course(CourseCode::try_from(code).unwrap())
+    }
+
+    #[test]
+    fn formats() {
+        let implications = [(
+            course("CSCI 0200"),
+            vec![
+                vec![course("CSCI 0150"), course("CSCI 0170")],
+                vec![course("MATH 0100")],
+            ],
+        )];
+        assert_eq!(
+            dimacs(&implications),
+            "c 1 CSCI 0200\nc 2 CSCI 0150\nc 3 CSCI 0170\nc 4 MATH 0100\np cnf 4 2\n-1 2 3 0\n-1 4 0\n"
+        );
+        assert!(datalog(&implications).contains("requires(\"CSCI 0200\", 1, \"MATH 0100\").\n"));
+    }
+}
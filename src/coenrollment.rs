@@ -0,0 +1,132 @@
+use crate::process::Course;
+use crate::restrictions::CourseCode;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Pairs of courses must have moved together across at least this many
+/// consecutive terms before their correlation means anything
+const MIN_CHANGES: usize = 4;
+
+/// Weaker correlations are left out of the graph
+const MIN_CORRELATION: f64 = 0.7;
+
+/// A course's enrollment by class each term, `None` for terms it wasn't offered.
+/// Courses without demographics count everyone as "others".
+type Series = Vec<Option<[f64; 6]>>;
+
+/// An experimental guess at which courses are commonly taken the same semester:
+/// courses whose enrollment, class by class, rises and falls together from one
+/// term to the next. Returns each correlated pair with its correlation.
+pub fn coenrollment(courses: &[Course]) -> Vec<(&CourseCode, &CourseCode, f64)> {
+    let terms: Vec<&str> = courses
+        .iter()
+        .flat_map(|course| course.offerings())
+        .map(|offering| offering.date())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let series: Vec<(&CourseCode, Series)> = courses
+        .iter()
+        .map(|course| (course.code(), series(course, &terms)))
+        .filter(|(_, series)| series.iter().flatten().count() > MIN_CHANGES)
+        .collect();
+    let mut edges = Vec::new();
+    for (i, (a, a_series)) in series.iter().enumerate() {
+        for (b, b_series) in series[i + 1..].iter() {
+            match correlation(a_series, b_series) {
+                Some(r) if r >= MIN_CORRELATION => edges.push((*a, *b, r)),
+                _ => {}
+            }
+        }
+    }
+    edges
+}
+
+fn series(course: &Course, terms: &[&str]) -> Series {
+    let mut series = vec![None; terms.len()];
+    for offering in course.offerings() {
+        let Ok(term) = terms.binary_search(&offering.date()) else {
+            continue;
+        };
+        let classes = match offering.demographics() {
+            Some(demographics) => demographics.classes(),
+            None => [0, 0, 0, 0, 0, u32::from(offering.enrollment().unwrap_or(0))],
+        };
+        let total = series[term].get_or_insert([0.0; 6]);
+        for (total, count) in total.iter_mut().zip(classes) {
+            *total += f64::from(count);
+        }
+    }
+    series
+}
+
+/// Pearson correlation of the term-to-term changes in each class's enrollment,
+/// over the consecutive terms that both courses were offered
+fn correlation(a: &Series, b: &Series) -> Option<f64> {
+    let mut pairs = Vec::new();
+    let mut changes = 0;
+    for (a, b) in a.windows(2).zip(b.windows(2)) {
+        let (Some(a0), Some(a1), Some(b0), Some(b1)) = (a[0], a[1], b[0], b[1]) else {
+            continue;
+        };
+        changes += 1;
+        for class in 0..6 {
+            pairs.push((a1[class] - a0[class], b1[class] - b0[class]));
+        }
+    }
+    if changes < MIN_CHANGES {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let (mean_x, mean_y) = pairs
+        .iter()
+        .fold((0.0, 0.0), |(x, y), (dx, dy)| (x + dx / n, y + dy / n));
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x) * (x - mean_x);
+        variance_y += (y - mean_y) * (y - mean_y);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// An undirected graphviz graph, thicker edges for stronger correlations
+pub fn dot(edges: &[(&CourseCode, &CourseCode, f64)]) -> String {
+    let mut ret = String::from("graph coenrollment {\n");
+    for (a, b, r) in edges {
+        writeln!(
+            ret,
+            r#"    "{a}" -- "{b}" [weight={r:.3}, penwidth={:.1}];"#,
+            1.0 + 4.0 * (r - MIN_CORRELATION) / (1.0 - MIN_CORRELATION),
+        )
+        .unwrap();
+    }
+    ret.push_str("}\n");
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{correlation, Series};
+
+    fn series(totals: &[Option<f64>]) -> Series {
+        totals
+            .iter()
+            .map(|total| total.map(|total| [total, total / 2.0, 0.0, 0.0, 0.0, 0.0]))
+            .collect()
+    }
+
+    #[test]
+    fn correlated() {
+        let a = series(&[Some(10.0), Some(20.0), Some(15.0), Some(30.0), Some(25.0)]);
+        let b = series(&[Some(40.0), Some(60.0), Some(50.0), Some(80.0), Some(70.0)]);
+        let c = series(&[Some(30.0), Some(20.0), Some(25.0), Some(10.0), Some(15.0)]);
+        assert!(correlation(&a, &b).unwrap() > 0.99);
+        assert!(correlation(&a, &c).unwrap() < -0.99);
+        let gap = series(&[Some(10.0), None, Some(15.0), Some(30.0), Some(25.0)]);
+        assert_eq!(correlation(&a, &gap), None);
+    }
+}
@@ -0,0 +1,331 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::{Child, ChildStdout, Command, ExitStatus, Stdio};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Compression of a JSONL file, chosen by its extension (`.gz`, `.zst`, or neither).
+///
+/// Like graphviz, the compressors are external programs that need to be on the `PATH`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Compression {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    fn program(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+}
+
+pub fn reader<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Read>> {
+    let program = match Compression::from_path(&path).program() {
+        Some(program) => program,
+        None => return Ok(Box::new(BufReader::new(File::open(path)?))),
+    };
+    let mut child = Command::new(program)
+        .arg("-dc")
+        .arg(path.as_ref())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    Ok(Box::new(Decompressor {
+        output: child.stdout.take().unwrap(),
+        child: Some(child),
+    }))
+}
+
+/// The output of a decompressor, which fails at the end instead of stopping early
+/// if the decompressor does, as it does on a corrupt or truncated file
+struct Decompressor {
+    output: ChildStdout,
+    /// Until it has been waited on
+    child: Option<Child>,
+}
+
+impl Read for Decompressor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.output.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            if let Some(mut child) = self.child.take() {
+                wait(child.wait()?)?;
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for Decompressor {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Every file below `directory` (except hidden ones, like lock files), in path
+/// order, decompressed and concatenated.
+///
+/// This is how per-term shards (`output/raw/<srcdb>.jsonl`) or the detail cache
+/// are read back as one input.
+pub fn directory_reader<P: AsRef<Path>>(directory: P) -> io::Result<Box<dyn Read>> {
+    let mut paths = Vec::new();
+    let mut pending = vec![directory.as_ref().to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            } else if path.is_dir() {
+                pending.push(path);
+            } else {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .try_fold(Box::new(io::empty()) as Box<dyn Read>, |combined, path| {
+            Ok(Box::new(combined.chain(reader(path)?)) as Box<dyn Read>)
+        })
+}
+
+/// Where a file is written before `finish` renames it into place: hidden, so
+/// that `directory_reader` skips it
+fn temporary(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.tmp"))
+}
+
+pub fn writer<P: AsRef<Path>>(path: P) -> io::Result<Writer> {
+    let path = path.as_ref().to_path_buf();
+    let temporary = temporary(&path);
+    let file = File::create(&temporary)?;
+    let (inner, child): (Box<dyn Write>, _) = match Compression::from_path(&path).program() {
+        None => (Box::new(file), None),
+        Some(program) => {
+            let mut child = Command::new(program)
+                .arg("-c")
+                .stdin(Stdio::piped())
+                .stdout(file)
+                .spawn()?;
+            (Box::new(child.stdin.take().unwrap()), Some(child))
+        }
+    };
+    Ok(Writer {
+        inner: Some(inner),
+        child,
+        temporary,
+        path,
+    })
+}
+
+/// Writes `contents` to `path` through `writer`
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let mut writer = writer(path)?;
+    writer.write_all(contents.as_ref())?;
+    writer.finish()
+}
+
+/// Output goes to a temporary file, which `finish` renames into place, so that
+/// nobody reads a half written file. Dropped without `finish`, the temporary
+/// file is removed.
+pub struct Writer {
+    inner: Option<Box<dyn Write>>,
+    child: Option<Child>,
+    temporary: PathBuf,
+    path: PathBuf,
+}
+
+impl Writer {
+    pub fn finish(mut self) -> io::Result<()> {
+        let mut inner = self.inner.take().unwrap();
+        inner.flush()?;
+        drop(inner);
+        if let Some(mut child) = self.child.take() {
+            wait(child.wait()?)?;
+        }
+        fs::rename(&self.temporary, &self.path)
+    }
+}
+
+fn wait(status: ExitStatus) -> io::Result<()> {
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("compressor failed: {status}"),
+        )),
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if self.inner.is_none() {
+            return;
+        }
+        drop(self.inner.take());
+        if let Some(mut child) = self.child.take() {
+            let _ = child.wait();
+        }
+        let _ = fs::remove_file(&self.temporary);
+    }
+}
+
+pub async fn async_writer<P: AsRef<Path>>(path: P) -> io::Result<AsyncCompressor> {
+    let path = path.as_ref().to_path_buf();
+    let temporary = temporary(&path);
+    let file = File::create(&temporary)?;
+    let (inner, child): (Pin<Box<dyn AsyncWrite>>, _) =
+        match Compression::from_path(&path).program() {
+            None => (Box::pin(tokio::fs::File::from_std(file)), None),
+            Some(program) => {
+                let mut child = tokio::process::Command::new(program)
+                    .arg("-c")
+                    .stdin(Stdio::piped())
+                    .stdout(file)
+                    .spawn()?;
+                (Box::pin(child.stdin.take().unwrap()), Some(child))
+            }
+        };
+    Ok(AsyncCompressor {
+        inner: Some(inner),
+        child,
+        temporary,
+        path,
+    })
+}
+
+/// Like `Writer`, the output is only in place after `finish` has been called.
+pub struct AsyncCompressor {
+    inner: Option<Pin<Box<dyn AsyncWrite>>>,
+    child: Option<tokio::process::Child>,
+    temporary: PathBuf,
+    path: PathBuf,
+}
+
+impl AsyncCompressor {
+    pub async fn finish(mut self) -> io::Result<()> {
+        let mut inner = self.inner.take().unwrap();
+        inner.shutdown().await?;
+        drop(inner);
+        if let Some(mut child) = self.child.take() {
+            wait(child.wait().await?)?;
+        }
+        tokio::fs::rename(&self.temporary, &self.path).await
+    }
+
+    fn inner(&mut self) -> Pin<&mut dyn AsyncWrite> {
+        self.inner.as_mut().unwrap().as_mut()
+    }
+}
+
+impl Drop for AsyncCompressor {
+    fn drop(&mut self) {
+        if self.inner.is_none() {
+            return;
+        }
+        drop(self.inner.take());
+        // tokio reaps the killed compressor in the background
+        if let Some(mut child) = self.child.take() {
+            let _ = child.start_kill();
+        }
+        let _ = fs::remove_file(&self.temporary);
+    }
+}
+
+impl AsyncWrite for AsyncCompressor {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.inner().poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner().poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner().poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic() {
+        let directory =
+            std::env::temp_dir().join(format!("cab-compression-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("out.jsonl");
+        let mut abandoned = writer(&path).unwrap();
+        abandoned.write_all(b"partial").unwrap();
+        assert!(!path.exists());
+        drop(abandoned);
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+        write(&path, "{}\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}\n");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn truncated() {
+        let directory = std::env::temp_dir().join(format!("cab-truncated-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("out.jsonl.gz");
+        write(&path, "{}\n".repeat(1000)).unwrap();
+        let mut contents = String::new();
+        reader(&path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents.len(), 3000);
+
+        let compressed = fs::read(&path).unwrap();
+        fs::write(&path, &compressed[..compressed.len() - 8]).unwrap();
+        let mut contents = String::new();
+        let error = reader(&path).unwrap().read_to_string(&mut contents);
+        assert_eq!(error.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
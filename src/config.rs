@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Values by key, as they would be given on the command line: lists are joined
+/// with commas, and flags are `true` or `false`
+pub type Table = BTreeMap<String, String>;
+
+/// Option values by name
+pub type Profile = Table;
+
+/// The profile called `name` in the configuration file at `path`, like `cab.toml`:
+///
+/// ```toml
+/// [profile.csci-only]
+/// subject = "CSCI"
+/// terms = ["202210", "202220"]
+/// minimize = "safe"
+/// gatekeeper = true
+/// output-directory = "output/csci"
+/// ```
+pub fn profile<P: AsRef<Path>>(path: P, name: &str) -> io::Result<Profile> {
+    let path = path.as_ref();
+    let mut profiles: BTreeMap<String, Profile> = tables(path)?
+        .into_iter()
+        .filter_map(|(header, table)| Some((header.strip_prefix("profile.")?.to_string(), table)))
+        .collect();
+    profiles.remove(name).ok_or_else(|| {
+        let names: Vec<_> = profiles.keys().map(String::as_str).collect();
+        crate::args::invalid(format!(
+            "no profile {name} in {path:?}, expected one of {}",
+            names.join(", ")
+        ))
+    })
+}
+
+/// The tables of the TOML file at `path`, by header
+pub fn tables<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, Table>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{path:?}: {e}")))
+}
+
+/// The tables of the subset of TOML that cab's files need: `[bare]` or `["quoted"]`
+/// headers, and strings, integers, floats, booleans, and one-line arrays of them
+pub fn parse(text: &str) -> Result<BTreeMap<String, Table>, String> {
+    let mut tables = BTreeMap::new();
+    let mut current: Option<&mut Table> = None;
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {message}", i + 1);
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .ok_or_else(|| error("unclosed table"))?;
+            let header = header.trim();
+            let name = match header.starts_with('"') {
+                true => scalar(header).ok_or_else(|| error("invalid table name"))?,
+                false => header.to_string(),
+            };
+            current = Some(tables.entry(name).or_default());
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected key = value"))?;
+        let table = current
+            .as_mut()
+            .ok_or_else(|| error("settings have to be in a table"))?;
+        let value = value.trim();
+        let value = match value.strip_prefix('[') {
+            Some(array) => {
+                let array = array
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("unclosed array"))?;
+                let values: Result<Vec<_>, _> = split_array(array)
+                    .into_iter()
+                    .map(|value| scalar(value).ok_or_else(|| error("invalid array element")))
+                    .collect();
+                values?.join(",")
+            }
+            None => scalar(value).ok_or_else(|| error("invalid value"))?,
+        };
+        table.insert(key.trim().to_string(), value);
+    }
+    Ok(tables)
+}
+
+/// Without a `#` comment, unless the `#` is in a string
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// At the commas that aren't in strings
+fn split_array(array: &str) -> Vec<&str> {
+    let mut ret = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in array.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                ret.push(&array[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    ret.push(&array[start..]);
+    ret.into_iter()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn scalar(value: &str) -> Option<String> {
+    let value = value.trim();
+    if let Some(string) = value.strip_prefix('"') {
+        let string = string.strip_suffix('"')?;
+        let mut ret = String::new();
+        let mut chars = string.chars();
+        while let Some(c) = chars.next() {
+            ret.push(match c {
+                '\\' => match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    c @ ('"' | '\\') => c,
+                    _ => return None,
+                },
+                c => c,
+            });
+        }
+        return Some(ret);
+    }
+    let valid = matches!(value, "true" | "false")
+        || value.parse::<i64>().is_ok()
+        || value.parse::<f64>().is_ok();
+    valid.then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn profiles() {
+        let text = r#"
+            # shared by everyone
+            [profile.quick]
+            terms = ["202210", "202220"] # the latest year
+            max-connections = 4
+            minimize = "off"
+
+            [profile.csci-only]
+            subject = "CSCI"
+            gatekeeper = true
+            output-directory = "output/csci # not a comment"
+        "#;
+        let profiles = parse(text).unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles["profile.quick"]["terms"], "202210,202220");
+        assert_eq!(profiles["profile.quick"]["max-connections"], "4");
+        assert_eq!(profiles["profile.csci-only"]["gatekeeper"], "true");
+        assert_eq!(
+            profiles["profile.csci-only"]["output-directory"],
+            "output/csci # not a comment"
+        );
+        assert!(parse("terms = 1").unwrap_err().starts_with("line 1"));
+        assert!(parse("[profile.a]\nterms = [1, bare]").is_err());
+        let quoted = parse("[\"CSCI 0150\"]\nnote = \"a\"").unwrap();
+        assert_eq!(quoted["CSCI 0150"]["note"], "a");
+    }
+}
@@ -0,0 +1,124 @@
+use crate::process::Course;
+use crate::restrictions::CourseCode;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Number of sections of each course offered in each term, with the terms in
+/// chronological order.
+pub struct Coverage<'a> {
+    terms: Vec<&'a str>,
+    rows: BTreeMap<&'a CourseCode, Vec<usize>>,
+}
+
+impl<'a> Coverage<'a> {
+    pub fn new<I: IntoIterator<Item = &'a Course>>(courses: I) -> Coverage<'a> {
+        let courses: Vec<_> = courses.into_iter().collect();
+        let terms: Vec<&str> = courses
+            .iter()
+            .flat_map(|course| course.offerings())
+            .map(|offering| offering.date())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let rows = courses
+            .iter()
+            .map(|course| {
+                let mut row = vec![0; terms.len()];
+                for offering in course.offerings() {
+                    let column = terms.binary_search(&offering.date()).unwrap();
+                    row[column] += 1;
+                }
+                (course.code(), row)
+            })
+            .collect();
+        Coverage { terms, rows }
+    }
+
+    pub fn csv(&self) -> String {
+        let mut ret = String::from("course");
+        for term in self.terms.iter() {
+            write!(ret, ",{term}").unwrap();
+        }
+        ret.push('\n');
+        for (code, row) in self.rows.iter() {
+            write!(ret, "{code}").unwrap();
+            for sections in row {
+                write!(ret, ",{sections}").unwrap();
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+
+    /// One heatmap per subject, darker cells meaning more sections that term.
+    pub fn svgs(&self) -> HashMap<&'a str, String> {
+        let mut subjects: HashMap<&str, Vec<(&CourseCode, &[usize])>> = HashMap::new();
+        for (code, row) in self.rows.iter() {
+            subjects
+                .entry(code.subject())
+                .or_default()
+                .push((code, row));
+        }
+        subjects
+            .into_iter()
+            .map(|(subject, rows)| (subject, self.svg(&rows)))
+            .collect()
+    }
+
+    fn svg(&self, rows: &[(&CourseCode, &[usize])]) -> String {
+        const CELL: usize = 16;
+        const LEFT: usize = 100;
+        const TOP: usize = 60;
+        let max = rows
+            .iter()
+            .flat_map(|(_, row)| row.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let width = LEFT + CELL * self.terms.len();
+        let height = TOP + CELL * rows.len();
+        let mut ret = String::new();
+        writeln!(
+            ret,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}">"#
+        )
+        .unwrap();
+        for (x, term) in self.terms.iter().enumerate() {
+            writeln!(
+                ret,
+                r#"<text transform="translate({},{}) rotate(-60)" style="font-family:monospace;font-size:10px">{term}</text>"#,
+                LEFT + CELL * x + CELL / 2,
+                TOP - 4,
+            )
+            .unwrap();
+        }
+        for (y, (code, row)) in rows.iter().enumerate() {
+            let top = TOP + CELL * y;
+            writeln!(
+                ret,
+                r#"<text x="0" y="{}" style="font-family:monospace;font-size:12px">{code}</text>"#,
+                top + CELL - 4,
+            )
+            .unwrap();
+            for (x, &sections) in row.iter().enumerate() {
+                let shade = 255 - 200 * sections / max;
+                let fill = if sections == 0 {
+                    String::from("#ffffff")
+                } else {
+                    format!("#{shade:02x}{shade:02x}ff")
+                };
+                writeln!(
+                    ret,
+                    r#"<rect style="fill:{fill};stroke:#cccccc;stroke-width:1" width="{CELL}" height="{CELL}" x="{}" y="{top}" />"#,
+                    LEFT + CELL * x,
+                )
+                .unwrap();
+            }
+        }
+        ret.push_str("</svg>\n");
+        ret
+    }
+}
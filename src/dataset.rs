@@ -0,0 +1,199 @@
+use crate::hash::Sha256;
+use reqwest::Client;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Where `fetch-dataset` gets a processed dataset from
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// An asset of a GitHub release: `latest` or a tag
+    Release {
+        repository: String,
+        release: String,
+        asset: String,
+    },
+    /// Any URL, with a checksum given or published at `<url>.sha256`
+    Url { url: String, sha256: Option<String> },
+}
+
+/// What the GitHub API says about a release
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+    /// Like `sha256:<hex>`, on assets uploaded since GitHub started computing them
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+impl Release {
+    /// The download URL of `name`, and where its checksum comes from
+    fn locate(&self, name: &str) -> io::Result<(&str, Checksum)> {
+        let asset = self
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = self
+                    .assets
+                    .iter()
+                    .map(|asset| asset.name.as_str())
+                    .collect();
+                invalid_data(format!(
+                    "release {} has no asset {name}, expected one of {}",
+                    self.tag_name,
+                    names.join(", ")
+                ))
+            })?;
+        let digest = asset
+            .digest
+            .as_deref()
+            .and_then(|digest| digest.strip_prefix("sha256:"));
+        let file = self
+            .assets
+            .iter()
+            .find(|file| file.name == format!("{name}.sha256"));
+        let checksum = match (digest, file) {
+            (Some(digest), _) => Checksum::Known(digest.to_string()),
+            (None, Some(file)) => Checksum::At(file.browser_download_url.clone()),
+            (None, None) => {
+                return Err(invalid_data(format!(
+                    "release {} publishes no checksum for {name}",
+                    self.tag_name
+                )))
+            }
+        };
+        Ok((&asset.browser_download_url, checksum))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Checksum {
+    Known(String),
+    /// A file like `sha256sum` writes
+    At(String),
+}
+
+/// Downloads the dataset at `source` to `output`, which is only written once the
+/// download matches its SHA-256 checksum
+pub async fn fetch<P: AsRef<Path>>(client: &Client, source: &Source, output: P) -> io::Result<()> {
+    let (url, checksum) = match source {
+        Source::Release {
+            repository,
+            release,
+            asset,
+        } => {
+            let api = match release.as_str() {
+                "latest" => format!("https://api.github.com/repos/{repository}/releases/latest"),
+                tag => format!("https://api.github.com/repos/{repository}/releases/tags/{tag}"),
+            };
+            let release: Release = serde_json::from_slice(&get(client, &api).await?)?;
+            eprintln!("Fetching {asset} from release {}", release.tag_name);
+            let (url, checksum) = release.locate(asset)?;
+            (url.to_string(), checksum)
+        }
+        Source::Url { url, sha256 } => {
+            let checksum = match sha256 {
+                Some(sha256) => Checksum::Known(sha256.clone()),
+                None => Checksum::At(format!("{url}.sha256")),
+            };
+            (url.clone(), checksum)
+        }
+    };
+    let expected = match checksum {
+        Checksum::Known(expected) => expected,
+        Checksum::At(url) => checksum_file(&get(client, &url).await?)?,
+    };
+    let dataset = get(client, &url).await?;
+    let mut hash = Sha256::new();
+    hash.update(&dataset);
+    let found = hash.hex();
+    if !found.eq_ignore_ascii_case(expected.trim()) {
+        return Err(invalid_data(format!(
+            "checksum mismatch for {url}: expected {expected}, found {found}"
+        )));
+    }
+    let output = output.as_ref();
+    if let Some(parent) = output.parent().filter(|parent| *parent != Path::new("")) {
+        fs::create_dir_all(parent)?;
+    }
+    // Already in its published compression, so not through `compression::write`
+    let partial = output.with_extension("partial");
+    fs::write(&partial, &dataset)?;
+    fs::rename(partial, output)?;
+    eprintln!(
+        "Wrote {} bytes to {output:?}, sha256 {found}",
+        dataset.len()
+    );
+    Ok(())
+}
+
+async fn get(client: &Client, url: &str) -> io::Result<bytes::Bytes> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| io::Error::other(format!("couldn't fetch {url}: {e}")))?;
+    response
+        .bytes()
+        .await
+        .map_err(|e| io::Error::other(format!("couldn't fetch {url}: {e}")))
+}
+
+/// The digest in `<hex>  <name>` or bare `<hex>` form
+fn checksum_file(contents: &[u8]) -> io::Result<String> {
+    String::from_utf8_lossy(contents)
+        .split_whitespace()
+        .next()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(String::from)
+        .ok_or_else(|| invalid_data(String::from("malformed checksum file")))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum_file, Checksum, Release};
+
+    #[test]
+    fn release_checksums() {
+        let release: Release = serde_json::from_value(serde_json::json!({
+            "tag_name": "v2024.1",
+            "assets": [
+                {"name": "minimized.jsonl.gz", "browser_download_url": "https://example.com/m",
+                    "digest": "sha256:ab12"},
+                {"name": "cab.jsonl.gz", "browser_download_url": "https://example.com/c"},
+                {"name": "cab.jsonl.gz.sha256", "browser_download_url": "https://example.com/c.sha256"},
+                {"name": "graph.svg", "browser_download_url": "https://example.com/g"},
+            ],
+        }))
+        .unwrap();
+        let (url, checksum) = release.locate("minimized.jsonl.gz").unwrap();
+        assert_eq!(url, "https://example.com/m");
+        assert_eq!(checksum, Checksum::Known(String::from("ab12")));
+        let (_, checksum) = release.locate("cab.jsonl.gz").unwrap();
+        assert_eq!(
+            checksum,
+            Checksum::At(String::from("https://example.com/c.sha256"))
+        );
+        assert!(release.locate("graph.svg").is_err());
+        assert!(release.locate("missing").is_err());
+
+        let digest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let file = format!("{digest}  cab.jsonl.gz\n");
+        assert_eq!(checksum_file(file.as_bytes()).unwrap(), digest);
+        assert!(checksum_file(b"not a digest").is_err());
+    }
+}
@@ -1,11 +1,26 @@
 use bytes::Bytes;
 use std::marker::Unpin;
 
+use crate::hash::Fnv;
+use futures::future::BoxFuture;
 use futures::prelude::*;
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, COOKIE, SET_COOKIE};
+use reqwest::{Client, Proxy};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
 use std::io::Write as IoWrite;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 
 use std::iter::IntoIterator;
 use tokio::io::AsyncWrite;
@@ -15,126 +30,1756 @@ use tokio::io::AsyncWriteExt;
 //{"group":"code:VISA 1110","key":"","srcdb":"202210","matched":"crn:17685,18097"}
 //{"group":"code:VISA 1110","key":"crn:17685","srcdb":"202210","matched":"crn:17685,18097"}
 
+/// Detail responses saved to disk as `<root>/<srcdb>/<crn>.json`, so that
+/// repeated or interrupted runs don't have to query the API again. Only successful
+/// responses that `process` can use are saved, so an error page is fetched again.
+pub struct Cache {
+    root: PathBuf,
+    /// Fetch every detail again, replacing what was saved, for seat counts that change
+    refresh: bool,
+}
+
+impl Cache {
+    pub fn new<P: AsRef<Path>>(root: P) -> Cache {
+        Cache {
+            root: root.as_ref().to_path_buf(),
+            refresh: false,
+        }
+    }
+
+    pub fn refreshing(self) -> Cache {
+        Cache {
+            refresh: true,
+            ..self
+        }
+    }
+
+    fn path(&self, stub: &Stub<'_>) -> PathBuf {
+        self.root.join(stub.term).join(format!("{}.json", stub.crn))
+    }
+
+    async fn get(&self, stub: &Stub<'_>) -> Option<Bytes> {
+        if self.refresh {
+            return None;
+        }
+        let json = tokio::fs::read(self.path(stub)).await.ok()?;
+        // from before only usable responses were saved
+        crate::process::validate(&json).ok()?;
+        Some(Bytes::from(json))
+    }
+
+    async fn put(&self, stub: &Stub<'_>, json: &Bytes) -> io::Result<()> {
+        let path = self.path(stub);
+        let temporary = path.with_file_name(format!(".{}.json.tmp", stub.crn));
+        tokio::fs::create_dir_all(path.parent().unwrap()).await?;
+        tokio::fs::write(&temporary, json).await?;
+        tokio::fs::rename(temporary, path).await
+    }
+
+    /// All cached responses concatenated, suitable as input to `process::process`.
+    pub fn reader(&self) -> io::Result<impl Read> {
+        crate::compression::directory_reader(&self.root)
+    }
+}
+
+/// The FOSE API's routes, the only endpoints `download` and `monitor` use
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Route {
+    Search,
+    Details,
+    Books,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Route::Search => "search",
+            Route::Details => "details",
+            Route::Books => "books",
+        })
+    }
+}
+
+/// A school's CourseLeaf FOSE deployment. The defaults follow Brown's, so
+/// another school usually only needs its API address and terms.
+pub trait CatalogSource: Sync {
+    /// The address of the API, which takes the page and route as query parameters
+    fn api(&self) -> &str;
+
+    /// The srcdbs of every term to download, oldest first
+    fn terms(&self) -> Vec<String>;
+
+    /// The suffixes that follow the year in a srcdb, one per season, for `probe_terms`
+    fn seasons(&self) -> &[&str] {
+        &["00", "10", "15", "20"]
+    }
+
+    /// A readable name for the term `srcdb`
+    fn term_name(&self, srcdb: &str) -> String {
+        srcdb.to_string()
+    }
+
+    /// A page to visit for session cookies before using the API, if the deployment needs it
+    fn session(&self) -> Option<&str> {
+        None
+    }
+
+    fn url(&self, route: Route) -> String {
+        format!("{}?page=fose&route={route}", self.api())
+    }
+
+    fn search_payload(&self, term: &str, criteria: &Criteria) -> Value {
+        json!({
+            "other": {"srcdb": term},
+            "criteria": criteria.to_json(),
+        })
+    }
+
+    fn details_payload(&self, term: &str, crn: &str) -> Value {
+        json!({
+            "srcdb": term,
+            "key": format!("crn:{crn}"),
+        })
+    }
+
+    fn books_payload(&self, term: &str, crn: &str) -> Value {
+        json!({
+            "srcdb": term,
+            "crn": crn,
+        })
+    }
+}
+
+/// Every term Brown's API has data for
+const BROWN_TERMS: &[&str] = &[
+    "201600", // Summer 2016
+    "201610", // Fall 2016
+    "201615", // Winter 2017
+    "201620", // Spring 2017
+    "201700", // Summer 2017
+    "201710", // Fall 2017
+    "201715", // Winter 2018
+    "201720", // Spring 2018
+    "201800", // Summer 2018
+    "201810", // Fall 2018
+    "201815", // Winter 2019
+    "201820", // Spring 2019
+    "201900", // Summer 2019
+    "201910", // Fall 2019
+    "201915", // Winter 2020
+    "201920", // Spring 2020
+    "202000", // Summer 2020
+    "202010", // Fall 2020
+    "202020", // Spring 2021
+    "202100", // Summer 2021
+    "202110", // Fall 2021
+    "202115", // Winter 2022
+    "202120", // Spring 2022
+    "202200", // Summer 2022
+    "202210", // Fall 2022
+    "202215", // Winter 2023
+    "202220", // Spring 2023
+];
+
+/// Courses@Brown
+pub struct Brown;
+
+impl CatalogSource for Brown {
+    fn api(&self) -> &str {
+        "https://cab.brown.edu/api/"
+    }
+
+    fn terms(&self) -> Vec<String> {
+        BROWN_TERMS.iter().map(|term| term.to_string()).collect()
+    }
+
+    /// Brown's srcdbs are the year the academic year starts in, then 00 for
+    /// summer, 10 for fall, 15 for winter, and 20 for spring.
+    fn term_name(&self, srcdb: &str) -> String {
+        let year: u16 = srcdb
+            .get(..4)
+            .and_then(|year| year.parse().ok())
+            .unwrap_or(0);
+        match srcdb.get(4..) {
+            Some("00") => format!("Summer {year}"),
+            Some("10") => format!("Fall {year}"),
+            Some("15") => format!("Winter {}", year + 1),
+            Some("20") => format!("Spring {}", year + 1),
+            _ => srcdb.to_string(),
+        }
+    }
+}
+
+/// Which sections the search route should return
+#[derive(Debug, Clone, Default)]
+pub struct Criteria {
+    pub subject: Option<String>,
+    pub keyword: Option<String>,
+    pub include_independent_study: bool,
+    pub include_cancelled: bool,
+}
+
+impl Criteria {
+    fn to_json(&self) -> Value {
+        let mut criteria = Vec::new();
+        if let Some(ref subject) = self.subject {
+            criteria.push(json!({"field": "subject", "value": subject}));
+        }
+        if let Some(ref keyword) = self.keyword {
+            criteria.push(json!({"field": "keyword", "value": keyword}));
+        }
+        if !self.include_independent_study {
+            criteria.push(json!({"field": "is_ind_study", "value": "N"}));
+        }
+        if !self.include_cancelled {
+            criteria.push(json!({"field": "is_canc", "value": "N"}));
+        }
+        Value::Array(criteria)
+    }
+}
+
+pub struct Settings {
+    pub source: Box<dyn CatalogSource>,
+    /// Upper bound on requests in flight; the actual number adapts to how the API is coping
+    pub max_connections: usize,
+    pub criteria: Criteria,
+    pub cache: Option<Cache>,
+    /// Also look up the textbooks of every section, stored under `books`
+    pub books: bool,
+    /// Only download sections whose fingerprints differ from these
+    pub previous: Option<Fingerprints>,
+    /// Responses that `process` couldn't use are appended here, with the reason, instead
+    /// of to the output
+    pub quarantine: Option<PathBuf>,
+    pub budget: Budget,
+    /// Where each term is written before they're merged into the output
+    pub scratch: PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            source: Box::new(Brown),
+            max_connections: 16,
+            criteria: Criteria::default(),
+            cache: None,
+            books: false,
+            previous: None,
+            quarantine: None,
+            budget: Budget::default(),
+            scratch: std::env::temp_dir(),
+        }
+    }
+}
+
+/// An extra header sent with every request, given as `Name: value`
+#[derive(Debug, Clone)]
+pub struct Header {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl FromStr for Header {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (name, value) = string.split_once(':').ok_or(())?;
+        Ok(Header {
+            name: HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| ())?,
+            value: HeaderValue::from_str(value.trim()).map_err(|_| ())?,
+        })
+    }
+}
+
+/// How to reach the API from networks that need a proxy, or when it wants
+/// particular headers or cookies
+#[derive(Debug, Clone, Default)]
+pub struct ClientSettings {
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub headers: Vec<Header>,
+    /// A page to visit before using the API, for deployments that only answer
+    /// requests carrying the session cookies it sets
+    pub session: Option<String>,
+}
+
+/// A client that keeps connections alive between the many small API requests,
+/// and sends the session cookies with every one of them
+pub async fn client(settings: &ClientSettings) -> reqwest::Result<Client> {
+    let client = build_client(settings, None)?;
+    let session = match settings.session {
+        Some(ref session) => session,
+        None => return Ok(client),
+    };
+    match session_cookies(&client, session).await? {
+        Some(cookies) => build_client(settings, Some(cookies)),
+        None => {
+            crate::summary::warn(format!("{session} didn't set any cookies"));
+            Ok(client)
+        }
+    }
+}
+
+fn build_client(
+    settings: &ClientSettings,
+    cookies: Option<HeaderValue>,
+) -> reqwest::Result<Client> {
+    let mut headers: HeaderMap = settings
+        .headers
+        .iter()
+        .map(|header| (header.name.clone(), header.value.clone()))
+        .collect();
+    if let Some(cookies) = cookies {
+        headers.insert(COOKIE, cookies);
+    }
+    let mut builder = Client::builder()
+        .tcp_keepalive(Duration::from_secs(60))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .http2_adaptive_window(true)
+        .http2_keep_alive_interval(Duration::from_secs(30))
+        .default_headers(headers);
+    if let Some(ref proxy) = settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    if let Some(ref user_agent) = settings.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    builder.build()
+}
+
+/// The cookies `url` sets, as a `Cookie` header
+async fn session_cookies(client: &Client, url: &str) -> reqwest::Result<Option<HeaderValue>> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    let cookies: Vec<&str> = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .filter_map(|cookie| cookie.split(';').next())
+        .map(str::trim)
+        .collect();
+    Ok(HeaderValue::from_str(&cookies.join("; "))
+        .ok()
+        .filter(|_| !cookies.is_empty()))
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: Bytes,
+}
+
+/// Posts a JSON body to a URL, returning the response.
+///
+/// Besides `Client`, tests implement this with canned responses, so that downloading
+/// can be tested without the API.
+pub trait Http: Sync {
+    fn post<'a>(&'a self, url: &'a str, body: &'a Value) -> BoxFuture<'a, io::Result<Response>>;
+}
+
+impl Http for Client {
+    fn post<'a>(&'a self, url: &'a str, body: &'a Value) -> BoxFuture<'a, io::Result<Response>> {
+        async move {
+            let response = Client::post(self, url)
+                .json(body)
+                .send()
+                .await
+                .map_err(io::Error::other)?;
+            let status = response.status().as_u16();
+            let body = response.bytes().await.map_err(io::Error::other)?;
+            Ok(Response { status, body })
+        }
+        .boxed()
+    }
+}
+
+/// How many of the slowest requests a report keeps
+const SLOWEST: usize = 10;
+
+/// One request, for the report
+#[derive(Debug, Clone, Serialize)]
+pub struct Request {
+    pub url: String,
+    pub body: Value,
+    pub milliseconds: u128,
+    /// The HTTP status, or `None` if there was no response
+    pub status: Option<u16>,
+}
+
+/// The outcomes of requests: how many got each status, and the slowest
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Requests {
+    /// Keyed by HTTP status, or `error` for requests that got no response
+    pub statuses: BTreeMap<String, usize>,
+    /// Slowest first
+    pub slowest: Vec<Request>,
+}
+
+impl Requests {
+    fn record(&mut self, request: Request) {
+        let status = request
+            .status
+            .map_or(String::from("error"), |status| status.to_string());
+        *self.statuses.entry(status).or_default() += 1;
+        let i = self
+            .slowest
+            .partition_point(|slow| slow.milliseconds >= request.milliseconds);
+        if i < SLOWEST {
+            self.slowest.insert(i, request);
+            self.slowest.truncate(SLOWEST);
+        }
+    }
+
+    pub fn merge(&mut self, other: Requests) {
+        for (status, count) in other.statuses {
+            *self.statuses.entry(status).or_default() += count;
+        }
+        self.slowest.extend(other.slowest);
+        self.slowest
+            .sort_by_key(|request| std::cmp::Reverse(request.milliseconds));
+        self.slowest.truncate(SLOWEST);
+    }
+}
+
+/// Times every request and notes its status
+struct Recorded<'a> {
+    inner: &'a dyn Http,
+    requests: Mutex<Requests>,
+}
+
+impl Http for Recorded<'_> {
+    fn post<'a>(&'a self, url: &'a str, body: &'a Value) -> BoxFuture<'a, io::Result<Response>> {
+        async move {
+            let start = Instant::now();
+            let response = self.inner.post(url, body).await;
+            self.requests.lock().unwrap().record(Request {
+                url: url.to_string(),
+                body: body.clone(),
+                milliseconds: start.elapsed().as_millis(),
+                status: response.as_ref().ok().map(|response| response.status),
+            });
+            response
+        }
+        .boxed()
+    }
+}
+
+/// Fails every response without a 2xx status, so that the adaptive limit backs off
+/// when the API turns requests away instead of counting its error pages as successes
+struct Successful<'a> {
+    inner: &'a dyn Http,
+}
+
+impl Http for Successful<'_> {
+    fn post<'a>(&'a self, url: &'a str, body: &'a Value) -> BoxFuture<'a, io::Result<Response>> {
+        async move {
+            let response = self.inner.post(url, body).await?;
+            match response.status {
+                200..=299 => Ok(response),
+                status => Err(io::Error::other(format!("{url} responded with {status}"))),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Limits on a whole download, after which it stops early. Details already
+/// downloaded are in the cache, so running the download again continues it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Budget {
+    pub max_requests: Option<usize>,
+    pub max_duration: Option<Duration>,
+}
+
+/// Fails every request once the budget is spent
+struct Budgeted<'a> {
+    inner: &'a dyn Http,
+    budget: Budget,
+    requests: AtomicUsize,
+    start: Instant,
+}
+
+impl<'a> Budgeted<'a> {
+    fn new(inner: &'a dyn Http, budget: Budget) -> Budgeted<'a> {
+        Budgeted {
+            inner,
+            budget,
+            requests: AtomicUsize::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    fn exhausted(&self) -> bool {
+        let requests = self.requests.load(Ordering::Relaxed);
+        self.budget.max_requests.is_some_and(|max| requests >= max)
+            || self
+                .budget
+                .max_duration
+                .is_some_and(|max| self.start.elapsed() >= max)
+    }
+}
+
+impl Http for Budgeted<'_> {
+    fn post<'a>(&'a self, url: &'a str, body: &'a Value) -> BoxFuture<'a, io::Result<Response>> {
+        if self.exhausted() {
+            let error = io::Error::new(io::ErrorKind::Interrupted, "request budget spent");
+            return future::ready(Err(error)).boxed();
+        }
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.inner.post(url, body)
+    }
+}
+
+/// Limits the number of requests in flight, additively increasing the limit
+/// while requests succeed and halving it when one fails or takes much longer
+/// than usual.
+struct Concurrency {
+    semaphore: Semaphore,
+    state: Mutex<ConcurrencyState>,
+    max: usize,
+    start: Instant,
+}
+
+struct ConcurrencyState {
+    limit: usize,
+    /// Permits to retire as requests finish, after the limit was lowered
+    debt: usize,
+    /// Successes since the limit last changed
+    successes: usize,
+    completed: usize,
+    latency: Option<Duration>,
+}
+
+impl Concurrency {
+    fn new(max: usize) -> Concurrency {
+        let limit = max.clamp(1, 4);
+        Concurrency {
+            semaphore: Semaphore::new(limit),
+            state: Mutex::new(ConcurrencyState {
+                limit,
+                debt: 0,
+                successes: 0,
+                completed: 0,
+                latency: None,
+            }),
+            max: max.max(1),
+            start: Instant::now(),
+        }
+    }
+
+    async fn run<T>(&self, request: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+        let permit = self.semaphore.acquire().await.expect("semaphore closed");
+        let start = Instant::now();
+        let result = request.await;
+        if self.record(result.is_ok(), start.elapsed()) {
+            permit.forget();
+        }
+        result
+    }
+
+    /// Adjusts the limit after a request; true if the finished request's permit should be retired
+    fn record(&self, success: bool, latency: Duration) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.completed += 1;
+        let spike = state.latency.is_some_and(|average| latency > average * 3);
+        state.latency = Some(match state.latency {
+            Some(average) => average * 7 / 8 + latency / 8,
+            None => latency,
+        });
+        if !success || spike {
+            let limit = (state.limit / 2).max(1);
+            state.debt += state.limit - limit;
+            state.limit = limit;
+            state.successes = 0;
+        } else {
+            state.successes += 1;
+            if state.successes >= state.limit && state.limit < self.max {
+                state.limit += 1;
+                state.successes = 0;
+                if state.debt > 0 {
+                    state.debt -= 1;
+                } else {
+                    self.semaphore.add_permits(1);
+                }
+            }
+        }
+        if state.debt > 0 {
+            state.debt -= 1;
+            return true;
+        }
+        false
+    }
+
+    fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Completed requests per second since the download began
+    fn throughput(&self) -> f64 {
+        let completed = self.state.lock().unwrap().completed;
+        completed as f64 / self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// Per-term counts of what a download wrote and what went wrong
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermReport {
+    pub records: usize,
+    /// Search or detail requests that failed or got an error status, so their sections
+    /// are missing
+    pub failures: usize,
+    pub quarantined: usize,
+    /// Sections left for a later run because the budget was spent
+    #[serde(default)]
+    pub unfinished: usize,
+}
+
+impl TermReport {
+    fn add(&mut self, other: &TermReport) {
+        self.records += other.records;
+        self.failures += other.failures;
+        self.quarantined += other.quarantined;
+        self.unfinished += other.unfinished;
+    }
+}
+
+/// What a download wrote: the counts per term, and a hash of the written lines.
+/// Serialized as the report written after each download.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub terms: BTreeMap<String, TermReport>,
+    #[serde(skip)]
+    pub hash: Fnv,
+    pub requests: Requests,
+}
+
+impl Report {
+    fn term(&mut self, term: &str) -> &mut TermReport {
+        self.terms.entry(term.to_string()).or_default()
+    }
+
+    /// Combines the reports of separate downloads, except their hashes
+    pub fn merge(&mut self, other: Report) {
+        self.terms.extend(other.terms);
+        self.requests.merge(other.requests);
+    }
+}
+
+/// The search fingerprint of each section in an earlier download, keyed by srcdb and CRN
+pub type Fingerprints = HashMap<(String, String), String>;
+
+/// Reads the fingerprints that `download` adds to every detail response
+pub fn fingerprints<R: Read>(previous: R) -> io::Result<Fingerprints> {
+    #[derive(Deserialize)]
+    struct Fingerprinted {
+        srcdb: String,
+        #[serde(default)]
+        crn: String,
+        #[serde(default)]
+        fingerprint: String,
+    }
+
+    let mut fingerprints = HashMap::new();
+    for record in serde_json::Deserializer::from_reader(previous).into_iter::<Fingerprinted>() {
+        let record = record?;
+        if !record.crn.is_empty() && !record.fingerprint.is_empty() {
+            fingerprints.insert((record.srcdb, record.crn), record.fingerprint);
+        }
+    }
+    Ok(fingerprints)
+}
+
+/// Only the sections that are new or whose search results changed since `previous`
+fn changed<'a>(stubs: Vec<Stub<'a>>, previous: &Fingerprints) -> Vec<Stub<'a>> {
+    let terms: HashSet<&str> = stubs.iter().map(|stub| stub.term).collect();
+    let current: HashSet<(&str, &str)> = stubs
+        .iter()
+        .map(|stub| (stub.term, stub.crn.as_str()))
+        .collect();
+    let removed = previous
+        .keys()
+        .filter(|(term, _)| terms.contains(term.as_str()))
+        .filter(|(term, crn)| !current.contains(&(term.as_str(), crn.as_str())))
+        .count();
+    let total = stubs.len();
+    let stubs: Vec<_> = stubs
+        .into_iter()
+        .filter(|stub| {
+            let key = (stub.term.to_string(), stub.crn.clone());
+            previous.get(&key) != Some(&stub.fingerprint)
+        })
+        .collect();
+    eprintln!(
+        "{} of {total} sections changed, {removed} are no longer listed",
+        stubs.len()
+    );
+    stubs
+}
+
+/// Writes the details of every section in `terms` to `destination`, sorted by
+/// term and then CRN. Terms download concurrently, each into its own scratch file.
 pub async fn download<'a, W: AsyncWrite + Unpin>(
-    client: &Client,
+    client: &dyn Http,
     terms: &'a [&'a str],
-    max_connections: usize,
-    mut destination: W,
-) {
-    let stubs = stubs(client, terms, max_connections).await;
-    let mut json_chunks = course_details(client, &stubs, max_connections)
+    settings: &Settings,
+    destination: W,
+) -> Report {
+    let (mut report, hashes) =
+        download_into(client, terms, settings, &mut [destination], |_| 0).await;
+    report.hash = hashes.into_iter().next().unwrap();
+    report
+}
+
+/// Like `download`, but each term's details go to the destination at its index in
+/// `terms`, with the same limit on requests in flight over all of them. Also gives
+/// the hash of what each destination was written.
+pub async fn download_sharded<'a, W: AsyncWrite + Unpin>(
+    client: &dyn Http,
+    terms: &'a [&'a str],
+    settings: &Settings,
+    destinations: &mut [W],
+) -> (Report, Vec<Fnv>) {
+    download_into(client, terms, settings, destinations, |i| i).await
+}
+
+/// Writes the term at each index of `terms` to the destination at index `shard(i)`
+async fn download_into<'a, W: AsyncWrite + Unpin>(
+    client: &dyn Http,
+    terms: &'a [&'a str],
+    settings: &Settings,
+    destinations: &mut [W],
+    shard: impl Fn(usize) -> usize,
+) -> (Report, Vec<Fnv>) {
+    let mut hashes = vec![Fnv::new(); destinations.len()];
+    let mut report = Report::default();
+    for term in terms {
+        report.term(term);
+    }
+    let recorded = Recorded {
+        inner: client,
+        requests: Mutex::default(),
+    };
+    let successful = Successful { inner: &recorded };
+    let client = &Budgeted::new(&successful, settings.budget);
+    let concurrency = Concurrency::new(settings.max_connections);
+    let mut stubs = stubs(client, terms, settings, &concurrency, &mut report).await;
+    if let Some(ref previous) = settings.previous {
+        stubs = changed(stubs, previous);
+    }
+    let parts = stubs.chunk_by(|a, b| a.term == b.term).map(|stubs| {
+        let scratch = settings
+            .scratch
+            .join(format!(".{}.part.jsonl", stubs[0].term));
+        download_term(client, stubs, settings, &concurrency, scratch)
+    });
+    for part in future::join_all(parts).await {
+        let term = part.term;
+        report.term(term).add(&part.report);
+        let i = shard(terms.iter().position(|t| *t == term).unwrap());
+        if let Err(e) = merge(part, &mut hashes[i], &mut destinations[i]).await {
+            eprintln!("merging {term} failed: {e:?}");
+            let term = report.term(term);
+            term.failures += term.records;
+            term.records = 0;
+        }
+    }
+    let unfinished: usize = report.terms.values().map(|term| term.unfinished).sum();
+    if unfinished > 0 {
+        match settings.cache {
+            Some(_) => eprintln!("budget spent with {unfinished} sections left; run again to continue from the cache"),
+            None => eprintln!("budget spent with {unfinished} sections left"),
+        }
+    }
+    let quarantined: usize = report.terms.values().map(|term| term.quarantined).sum();
+    if quarantined > 0 {
+        eprintln!("{quarantined} malformed responses quarantined");
+    }
+    report.requests = std::mem::take(&mut recorded.requests.lock().unwrap());
+    (report, hashes)
+}
+
+/// The records one term wrote to its scratch file, to be merged into the output
+struct Part<'a> {
+    term: &'a str,
+    report: TermReport,
+    scratch: PathBuf,
+    /// CRN, offset, and length of each record
+    records: Vec<(String, u64, usize)>,
+}
+
+/// Downloads the details of one term's sections into `scratch`, in whatever
+/// order they arrive, so that terms don't wait on each other's writes
+async fn download_term<'a>(
+    client: &Budgeted<'_>,
+    stubs: &'a [Stub<'a>],
+    settings: &Settings,
+    concurrency: &Concurrency,
+    scratch: PathBuf,
+) -> Part<'a> {
+    let mut part = Part {
+        term: stubs[0].term,
+        report: TermReport::default(),
+        scratch,
+        records: Vec::new(),
+    };
+    let mut file = match tokio::fs::File::create(&part.scratch).await {
+        Ok(file) => tokio::io::BufWriter::new(file),
+        Err(e) => {
+            eprintln!("couldn't create {}: {e:?}", part.scratch.display());
+            part.report.failures = stubs.len();
+            return part;
+        }
+    };
+    let mut offset = 0;
+    let mut json_chunks = course_details(client, stubs, settings, concurrency)
         .await
         .boxed_local();
+    while let Some((stub, json)) = json_chunks.next().await {
+        let mut json = match json {
+            Ok(json) => json,
+            Err(_) if client.exhausted() => {
+                part.report.unfinished += 1;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("course detail lookup failed: {e:?}");
+                part.report.failures += 1;
+                continue;
+            }
+        };
+        if let Err(reason) = crate::process::validate(&json) {
+            part.report.quarantined += 1;
+            if let Err(e) = quarantine(settings, stub, &json, reason).await {
+                eprintln!("quarantining {}/{} failed: {e:?}", stub.term, stub.crn);
+            }
+            continue;
+        }
+        let length = json.len();
+        if let Err(e) = file.write_all_buf(&mut json).await {
+            eprintln!("writing {}/{} failed: {e:?}", stub.term, stub.crn);
+            part.report.failures += 1;
+            continue;
+        }
+        part.records.push((stub.crn.clone(), offset, length));
+        part.report.records += 1;
+        offset += length as u64;
+    }
+    if let Err(e) = file.flush().await {
+        eprintln!("writing {} failed: {e:?}", part.scratch.display());
+    }
+    part
+}
 
-    while let Some(mut json) = json_chunks.next().await {
-        let _ = destination.write_all_buf(&mut json).await;
-        let _ = destination.write_all(b"\n").await;
+/// Appends a term's records to `destination` sorted by CRN, then removes its scratch file
+async fn merge<W: AsyncWrite + Unpin>(
+    mut part: Part<'_>,
+    hash: &mut Fnv,
+    destination: &mut W,
+) -> io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    part.records.sort();
+    let mut file = tokio::fs::File::open(&part.scratch).await?;
+    let mut json = Vec::new();
+    for (_, offset, length) in part.records.iter() {
+        json.resize(*length, 0);
+        file.seek(io::SeekFrom::Start(*offset)).await?;
+        file.read_exact(&mut json).await?;
+        hash.write_all(&json).unwrap();
+        hash.write_all(b"\n").unwrap();
+        destination.write_all(&json).await?;
+        destination.write_all(b"\n").await?;
     }
+    drop(file);
+    tokio::fs::remove_file(&part.scratch).await
+}
+
+async fn quarantine(
+    settings: &Settings,
+    stub: &Stub<'_>,
+    json: &Bytes,
+    reason: String,
+) -> io::Result<()> {
+    let path = match settings.quarantine {
+        Some(ref path) => path,
+        None => return Ok(()),
+    };
+    let mut line = serde_json::to_vec(&json!({
+        "srcdb": stub.term,
+        "crn": stub.crn,
+        "reason": reason,
+        "response": String::from_utf8_lossy(json),
+    }))?;
+    line.push(b'\n');
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?
+        .write_all(&line)
+        .await
 }
 
 struct Stub<'a> {
     crn: String,
     term: &'a str,
+    /// Of the search result, to tell whether the section changed since an earlier download
+    fingerprint: String,
+    listing: Listing,
+}
+
+/// How the search route listed a section: under which `groups`, like `code:VISA 1110`,
+/// of which cross-listed sections have more than one, and the CRNs `matched` with it
+#[derive(Debug, Clone, Default, Serialize)]
+struct Listing {
+    groups: Vec<String>,
+    matched: Vec<String>,
+}
+
+impl Listing {
+    fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.matched.is_empty()
+    }
+
+    /// Both listings of a section the search returned twice
+    fn merge(&mut self, other: Listing) {
+        for group in other.groups {
+            if !self.groups.contains(&group) {
+                self.groups.push(group);
+            }
+        }
+        self.matched.extend(other.matched);
+        self.matched.sort();
+        self.matched.dedup();
+    }
 }
 
-async fn stubs<'a>(client: &Client, terms: &'a [&'a str], max_connections: usize) -> Vec<Stub<'a>> {
-    stream::iter(terms)
+async fn stubs<'a>(
+    client: &dyn Http,
+    terms: &'a [&'a str],
+    settings: &Settings,
+    concurrency: &Concurrency,
+    report: &mut Report,
+) -> Vec<Stub<'a>> {
+    let results: Vec<_> = stream::iter(terms)
         .enumerate()
-        .map(move |(i, term)| async move {
-            eprint!("[{}/{}] requesting stub {term}\r", i + 1, terms.len());
+        .map(move |(i, &term)| async move {
+            eprint!(
+                "[{}/{}] requesting stub {}\r",
+                i + 1,
+                terms.len(),
+                settings.source.term_name(term)
+            );
             std::io::stdout().flush().unwrap();
-            let crns = crns(client, term).await?;
-            let stubs: Vec<_> = crns
-                .into_iter()
-                .map(|Crn { crn }| Stub { crn, term })
-                .collect();
-            Ok::<_, reqwest::Error>(stubs)
+            let crns = concurrency
+                .run(crns(client, &*settings.source, term, &settings.criteria))
+                .await;
+            (term, crns)
         })
-        .buffer_unordered(max_connections)
-        .filter_map(|b| async {
-            match b {
-                Ok(b) => Some(b),
-                Err(e) => {
-                    eprintln!("stub lookup failed: {e:?}");
-                    None
-                }
-            }
-        })
-        .flat_map(stream::iter)
+        .buffer_unordered(settings.max_connections)
         .collect()
-        .await
+        .await;
+    let mut stubs = Vec::new();
+    for (term, crns) in results {
+        match crns {
+            Ok(crns) => stubs.extend(crns.into_iter().map(|crn| Stub {
+                fingerprint: crn.fingerprint(),
+                listing: crn.listing(),
+                crn: crn.crn,
+                term,
+            })),
+            Err(e) => {
+                eprintln!("stub lookup failed: {e:?}");
+                report.term(term).failures += 1;
+            }
+        }
+    }
+    // in order, so that the output is the same from run to run
+    stubs.sort_by(|a, b| (a.term, &a.crn).cmp(&(b.term, &b.crn)));
+    let found = stubs.len();
+    // each section's details are requested once, however many groups it's listed in
+    stubs.dedup_by(|later, kept| {
+        let duplicate = (later.term, &later.crn) == (kept.term, &kept.crn);
+        if duplicate {
+            kept.listing.merge(std::mem::take(&mut later.listing));
+        }
+        duplicate
+    });
+    if stubs.len() < found {
+        eprintln!(
+            "merged {} listings of sections the search returned more than once",
+            found - stubs.len()
+        );
+    }
+    stubs
 }
 
 #[derive(Debug, Deserialize)]
 struct Crn {
     crn: String,
+    #[serde(default)]
+    code: String,
+    /// Everything else the search route says about the section
+    #[serde(flatten)]
+    fields: serde_json::Map<String, Value>,
 }
 
-async fn crns(client: &Client, term: &str) -> reqwest::Result<Vec<Crn>> {
-    #[derive(Debug, Deserialize)]
-    struct SearchResults {
-        results: Vec<Crn>,
+impl Crn {
+    fn fingerprint(&self) -> String {
+        let mut hash = Fnv::new();
+        hash.write_all(self.code.as_bytes()).unwrap();
+        serde_json::to_writer(&mut hash, &self.fields).unwrap();
+        hash.hex()
     }
 
-    let result = client
-        .post("https://cab.brown.edu/api/?page=fose&route=search")
-        .json(&json!({
-            "other": {"srcdb": term},
-            "criteria": [
-                {"field":"is_ind_study","value":"N"},
-                {"field":"is_canc","value":"N"}
-            ],
-        }))
-        .send()
-        .await?
-        .json::<SearchResults>()
+    /// `matched` is given like `crn:17685,18097`
+    fn listing(&self) -> Listing {
+        let field = |name| self.fields.get(name).and_then(Value::as_str);
+        Listing {
+            groups: field("group").map(String::from).into_iter().collect(),
+            matched: field("matched")
+                .map(|matched| matched.trim_start_matches("crn:"))
+                .filter(|matched| !matched.is_empty())
+                .map_or_else(Vec::new, |matched| {
+                    matched.split(',').map(String::from).collect()
+                }),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    /// How many sections matched, which can be more than the results returned
+    #[serde(default)]
+    count: Option<usize>,
+    results: Vec<Crn>,
+}
+
+impl SearchResults {
+    fn is_truncated(&self) -> bool {
+        self.count.is_some_and(|count| count > self.results.len())
+    }
+
+    /// Without a count, a round number of results is probably a cap
+    fn looks_truncated(&self) -> bool {
+        self.count.is_none() && !self.results.is_empty() && self.results.len().is_multiple_of(100)
+    }
+}
+
+/// The search route can truncate large terms, so truncated results are completed
+/// by searching again for each subject seen in them.
+async fn crns(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    term: &str,
+    criteria: &Criteria,
+) -> io::Result<Vec<Crn>> {
+    let results = search(client, source, term, criteria).await?;
+    if results.looks_truncated() {
+        crate::summary::warn(format!(
+            "{} results for {term} is suspiciously round, some may be missing",
+            results.results.len()
+        ));
+    }
+    if !results.is_truncated() {
+        return Ok(results.results);
+    }
+    let subjects: BTreeSet<String> = results
+        .results
+        .iter()
+        .filter_map(|crn| Some(crn.code.split_once(' ')?.0.to_string()))
+        .collect();
+    if criteria.subject.is_some() || subjects.is_empty() {
+        crate::summary::warn(format!(
+            "search for {term} returned {} of {} results",
+            results.results.len(),
+            results.count.unwrap_or_default()
+        ));
+        return Ok(results.results);
+    }
+    eprintln!(
+        "search for {term} was truncated, searching {} subjects separately",
+        subjects.len()
+    );
+    let mut crns = results.results;
+    for subject in subjects {
+        let criteria = Criteria {
+            subject: Some(subject),
+            ..criteria.clone()
+        };
+        crns.extend(crns_in_subject(client, source, term, &criteria).await?);
+    }
+    let mut seen = HashSet::new();
+    crns.retain(|crn| seen.insert(crn.crn.clone()));
+    if let Some(count) = results.count.filter(|&count| count > crns.len()) {
+        crate::summary::warn(format!(
+            "found {} of {count} results for {term}, subjects that were cut off entirely are missing",
+            crns.len()
+        ));
+    }
+    Ok(crns)
+}
+
+async fn crns_in_subject(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    term: &str,
+    criteria: &Criteria,
+) -> io::Result<Vec<Crn>> {
+    let results = search(client, source, term, criteria).await?;
+    if results.is_truncated() || results.looks_truncated() {
+        crate::summary::warn(format!(
+            "search for {term} {} may be truncated at {} results",
+            criteria.subject.as_deref().unwrap_or_default(),
+            results.results.len()
+        ));
+    }
+    Ok(results.results)
+}
+
+async fn search(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    term: &str,
+    criteria: &Criteria,
+) -> io::Result<SearchResults> {
+    let json = client
+        .post(
+            &source.url(Route::Search),
+            &source.search_payload(term, criteria),
+        )
         .await?
-        .results;
+        .body;
+    Ok(serde_json::from_slice(&json)?)
+}
 
-    Ok(result)
+/// Whether searching `term` finds any sections, or the error if the search failed,
+/// which says nothing either way
+async fn has_sections(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    term: &str,
+) -> io::Result<bool> {
+    let results = search(client, source, term, &Criteria::default()).await?;
+    Ok(!results.results.is_empty())
+}
+
+/// Whether any season of `year` has sections. A failed search is only an error if
+/// no other season has any.
+async fn has_year(client: &dyn Http, source: &dyn CatalogSource, year: u16) -> io::Result<bool> {
+    let terms = source
+        .seasons()
+        .iter()
+        .map(|season| format!("{year}{season}"));
+    let mut found = stream::iter(terms)
+        .map(|term| async move { has_sections(client, source, &term).await })
+        .buffer_unordered(source.seasons().len());
+    let mut error = None;
+    while let Some(result) = found.next().await {
+        match result {
+            Ok(true) => return Ok(true),
+            Ok(false) => {}
+            Err(e) => error = Some(e),
+        }
+    }
+    error.map_or(Ok(false), Err)
+}
+
+/// The srcdbs in `years` with sections, oldest first, for sources whose terms
+/// aren't listed anywhere.
+///
+/// The years with sections are assumed to be a contiguous run that includes
+/// `anchor`, so its ends are binary searched for instead of trying every year. A
+/// search that fails is an error rather than a term without sections, since it could
+/// otherwise cut whole years out of the run.
+pub async fn probe_terms(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    years: std::ops::RangeInclusive<u16>,
+    anchor: u16,
+) -> io::Result<Vec<String>> {
+    if !years.contains(&anchor) || !has_year(client, source, anchor).await? {
+        return Ok(Vec::new());
+    }
+    // the first year with sections is in first..=anchor
+    let (mut first, mut high) = (*years.start(), anchor);
+    while first < high {
+        let middle = first + (high - first) / 2;
+        match has_year(client, source, middle).await? {
+            true => high = middle,
+            false => first = middle + 1,
+        }
+    }
+    // the last year with sections is in anchor..=last
+    let (mut low, mut last) = (anchor, *years.end());
+    while low < last {
+        let middle = low + (last - low).div_ceil(2);
+        match has_year(client, source, middle).await? {
+            true => low = middle,
+            false => last = middle - 1,
+        }
+    }
+    let candidates: Vec<String> = (first..=last)
+        .flat_map(|year| {
+            source
+                .seasons()
+                .iter()
+                .map(move |season| format!("{year}{season}"))
+        })
+        .collect();
+    let found: Vec<io::Result<bool>> = stream::iter(&candidates)
+        .map(|term| has_sections(client, source, term))
+        .buffered(source.seasons().len())
+        .collect()
+        .await;
+    let found = found.into_iter().collect::<io::Result<Vec<bool>>>()?;
+    Ok(candidates
+        .into_iter()
+        .zip(found)
+        .filter_map(|(term, found)| found.then_some(term))
+        .collect())
+}
+
+/// The terms found by an earlier `probe_terms`, saved at `path`
+pub fn probed_terms<P: AsRef<Path>>(path: P) -> io::Result<Option<Vec<String>>> {
+    match fs::read(path) {
+        Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
 }
 
 async fn course_details<'a>(
-    client: &'a Client,
+    client: &'a dyn Http,
     stubs: &'a [Stub<'_>],
-    max_connections: usize,
-) -> impl Stream<Item = Bytes> + 'a
-where
-{
+    settings: &'a Settings,
+    concurrency: &'a Concurrency,
+) -> impl Stream<Item = (&'a Stub<'a>, io::Result<Bytes>)> + 'a {
     stream::iter(stubs)
         .enumerate()
         .map(move |(i, stub)| {
             eprint!(
-                "[{}/{}] requesting detail {}/{}\r",
+                "[{}/{}] requesting detail {}/{} ({:.1} requests/s, {} connections)\r",
                 i + 1,
                 stubs.len(),
                 stub.term,
-                stub.crn
+                stub.crn,
+                concurrency.throughput(),
+                concurrency.limit(),
             );
             std::io::stdout().flush().unwrap();
-            course_detail(client, stub)
+            concurrency
+                .run(annotated_course_detail(client, stub, settings))
+                .map(move |json| (stub, json))
         })
-        .buffer_unordered(max_connections)
-        .filter_map(|b| async {
-            match b {
-                Ok(b) => Some(b),
-                Err(e) => {
-                    eprintln!("course detail lookup failed: {e:?}");
-                    None
-                }
+        .buffer_unordered(settings.max_connections)
+}
+
+/// The detail response, with the section's `crn`, search `fingerprint` and listing
+/// (as `search`), and its `books` if they were asked for. Books are left out if the
+/// bookstore route fails, rather than losing the section with them.
+async fn annotated_course_detail(
+    client: &dyn Http,
+    stub: &Stub<'_>,
+    settings: &Settings,
+) -> io::Result<Bytes> {
+    let json = cached_course_detail(client, settings, stub).await?;
+    let mut detail: Value = match serde_json::from_slice(&json) {
+        Ok(Value::Object(detail)) => Value::Object(detail),
+        _ => return Ok(json),
+    };
+    let books = match settings.books {
+        true => match books(client, &*settings.source, stub).await {
+            Ok(books) => Some(books),
+            Err(e) => {
+                eprintln!("requesting books {}/{} failed: {e:?}", stub.term, stub.crn);
+                None
             }
-        })
+        },
+        false => None,
+    };
+    if let Value::Object(ref mut detail) = detail {
+        detail
+            .entry("crn")
+            .or_insert_with(|| Value::String(stub.crn.clone()));
+        detail.insert("fingerprint".to_string(), json!(stub.fingerprint));
+        if !stub.listing.is_empty() {
+            detail.insert("search".to_string(), json!(stub.listing));
+        }
+        if let Some(books) = books {
+            detail.insert("books".to_string(), books);
+        }
+    }
+    Ok(Bytes::from(detail.to_string()))
+}
+
+async fn cached_course_detail(
+    client: &dyn Http,
+    settings: &Settings,
+    stub: &Stub<'_>,
+) -> io::Result<Bytes> {
+    let source = &*settings.source;
+    let cache = match settings.cache {
+        Some(ref cache) => cache,
+        None => return Ok(course_detail(client, source, stub).await?.body),
+    };
+    if let Some(json) = cache.get(stub).await {
+        return Ok(json);
+    }
+    let response = course_detail(client, source, stub).await?;
+    let usable =
+        (200..300).contains(&response.status) && crate::process::validate(&response.body).is_ok();
+    if usable {
+        if let Err(e) = cache.put(stub, &response.body).await {
+            eprintln!("caching detail {}/{} failed: {e:?}", stub.term, stub.crn);
+        }
+    }
+    Ok(response.body)
+}
+
+/// The raw detail response for one section
+pub async fn detail(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    term: &str,
+    crn: &str,
+) -> io::Result<Bytes> {
+    let stub = Stub {
+        crn: crn.to_string(),
+        term,
+        fingerprint: String::new(),
+        listing: Listing::default(),
+    };
+    Ok(course_detail(client, source, &stub).await?.body)
+}
+
+/// The CRNs of the sections of `code`, like `CSCI 0150`, in `term`
+pub async fn sections_of(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    term: &str,
+    code: &str,
+) -> io::Result<Vec<String>> {
+    let criteria = Criteria {
+        subject: code.split(' ').next().map(String::from),
+        ..Criteria::default()
+    };
+    let results = search(client, source, term, &criteria).await?;
+    Ok(results
+        .results
+        .into_iter()
+        .filter(|crn| crn.code == code)
+        .map(|crn| crn.crn)
+        .collect())
 }
 
-async fn course_detail(client: &Client, stub: &Stub<'_>) -> reqwest::Result<Bytes> {
+async fn course_detail(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    stub: &Stub<'_>,
+) -> io::Result<Response> {
     client
-        .post("https://cab.brown.edu/api/?page=fose&route=details")
-        .json(&json!({
-            "srcdb": stub.term,
-            "key": format!("crn:{}", stub.crn),
-        }))
-        .send()
-        .await?
-        .bytes()
+        .post(
+            &source.url(Route::Details),
+            &source.details_payload(stub.term, &stub.crn),
+        )
         .await
 }
+
+async fn books(
+    client: &dyn Http,
+    source: &dyn CatalogSource,
+    stub: &Stub<'_>,
+) -> io::Result<Value> {
+    #[derive(Debug, Deserialize)]
+    struct BooksResults {
+        #[serde(default)]
+        books: Value,
+    }
+
+    let json = client
+        .post(
+            &source.url(Route::Books),
+            &source.books_payload(stub.term, &stub.crn),
+        )
+        .await?
+        .body;
+    Ok(serde_json::from_slice::<BooksResults>(&json)?.books)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers requests with `respond(route, body)`
+    struct Fixtures<F>(F);
+
+    impl<F: Fn(&str, &Value) -> io::Result<String> + Sync> Http for Fixtures<F> {
+        fn post<'a>(
+            &'a self,
+            url: &'a str,
+            body: &'a Value,
+        ) -> BoxFuture<'a, io::Result<Response>> {
+            let route = url.rsplit("route=").next().unwrap_or_default();
+            let response = (self.0)(route, body).map(|body| Response {
+                status: 200,
+                body: Bytes::from(body),
+            });
+            async move { response }.boxed()
+        }
+    }
+
+    /// A detail response for section S01 of `code`
+    fn detail(code: &str, title: &str, srcdb: &str) -> String {
+        serde_json::json!({
+            "permreq": "N", "code": code, "section": "S01", "title": title,
+            "description": "", "registration_restrictions": "", "seats": "",
+            "instructordetail_html": "", "regdemog_html": "", "regdemog_json": "",
+            "srcdb": srcdb,
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn crosslisted() {
+        let details = AtomicUsize::new(0);
+        let fixtures = Fixtures(|route: &str, _: &Value| match route {
+            "search" => Ok(serde_json::json!({"results": [
+                {"crn": "1", "group": "code:VISA 1110", "matched": "crn:1,2"},
+                {"crn": "1", "group": "code:ARTS 1110", "matched": "crn:1"},
+            ]})
+            .to_string()),
+            _ => {
+                details.fetch_add(1, Ordering::Relaxed);
+                Ok(detail("VISA 1110", "Drawing", "202210"))
+            }
+        });
+        let mut output = Vec::new();
+        let settings = Settings {
+            scratch: scratch("crosslisted"),
+            ..Settings::default()
+        };
+        download(&fixtures, &["202210"], &settings, &mut output).await;
+        assert_eq!(details.load(Ordering::Relaxed), 1);
+        let written: Value = serde_json::from_slice(&output).unwrap();
+        let search = serde_json::json!({
+            "groups": ["code:VISA 1110", "code:ARTS 1110"],
+            "matched": ["1", "2"],
+        });
+        assert_eq!(written["search"], search);
+        fs::remove_dir_all(&settings.scratch).unwrap();
+    }
+
+    #[tokio::test]
+    async fn probe() {
+        let requests = AtomicUsize::new(0);
+        let fixtures = Fixtures(|_: &str, body: &Value| {
+            requests.fetch_add(1, Ordering::Relaxed);
+            let term = body["other"]["srcdb"].as_str().unwrap();
+            let found = ("201610".."202300").contains(&term) && !term.ends_with("15");
+            let results = match found {
+                true => serde_json::json!([{"crn": "1"}]),
+                false => serde_json::json!([]),
+            };
+            Ok(serde_json::json!({ "results": results }).to_string())
+        });
+        let terms = probe_terms(&fixtures, &Brown, 2000..=2030, 2020)
+            .await
+            .unwrap();
+        assert_eq!(terms.first().map(String::as_str), Some("201610"));
+        assert_eq!(terms.last().map(String::as_str), Some("202220"));
+        assert_eq!(terms.len(), 7 * 3 - 1);
+        // 31 years of 4 seasons, most of which weren't asked about
+        assert!(requests.load(Ordering::Relaxed) < 31 * 4 / 2);
+
+        let terms = probe_terms(&fixtures, &Brown, 2000..=2030, 2010)
+            .await
+            .unwrap();
+        assert!(terms.is_empty());
+
+        // a timeout is not a year without sections
+        let flaky =
+            Fixtures(
+                |_: &str, body: &Value| match body["other"]["srcdb"].as_str().unwrap() {
+                    "201710" => Err(io::Error::from(io::ErrorKind::TimedOut)),
+                    _ => fixtures.0("search", body),
+                },
+            );
+        assert!(probe_terms(&flaky, &Brown, 2000..=2030, 2020)
+            .await
+            .is_err());
+    }
+
+    /// A scratch directory of its own for each test, since they run concurrently
+    fn scratch(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("cab-{test}-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[tokio::test]
+    async fn download_offline() {
+        let fixtures = Fixtures(|route: &str, body: &Value| match route {
+            "search" if body["other"]["srcdb"] == "202210" => Ok(serde_json::json!({
+                "results": [{"crn": "2"}, {"crn": "1"}, {"crn": "2"}],
+            })
+            .to_string()),
+            "details" if body["key"] == "crn:1" => Ok(detail("CSCI 0150", "Intro", "202210")),
+            "details" => Ok(String::from("<html>Service Unavailable</html>")),
+            _ => Err(io::Error::other("connection refused")),
+        });
+        let mut output = Vec::new();
+        let settings = Settings {
+            scratch: scratch("offline"),
+            ..Settings::default()
+        };
+        let report = download(&fixtures, &["202210", "202220"], &settings, &mut output).await;
+        let written: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(written["code"], "CSCI 0150");
+        assert_eq!(written["crn"], "1");
+        assert!(written["fingerprint"].is_string());
+        let fall = TermReport {
+            records: 1,
+            failures: 0,
+            quarantined: 1,
+            unfinished: 0,
+        };
+        assert_eq!(report.terms["202210"], fall);
+        assert_eq!(report.terms["202220"].failures, 1);
+        let statuses = &report.requests.statuses;
+        assert_eq!((statuses["200"], statuses["error"]), (3, 1));
+        assert_eq!(report.requests.slowest.len(), 4);
+
+        let settings = Settings {
+            previous: Some(fingerprints(&output[..]).unwrap()),
+            ..settings
+        };
+        let mut patch = Vec::new();
+        let report = download(&fixtures, &["202210"], &settings, &mut patch).await;
+        assert_eq!(report.terms["202210"].records, 0);
+        assert_eq!(report.terms["202210"].quarantined, 1);
+        fs::remove_dir_all(&settings.scratch).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cache() {
+        let title = Mutex::new("Intro");
+        let fixtures = Fixtures(|route: &str, body: &Value| match route {
+            "search" => {
+                Ok(serde_json::json!({"results": [{"crn": "1"}, {"crn": "2"}]}).to_string())
+            }
+            _ if body["key"] == "crn:1" => {
+                Ok(detail("CSCI 0150", &title.lock().unwrap(), "202210"))
+            }
+            _ => Ok(String::from("<html>Service Unavailable</html>")),
+        });
+        let scratch = scratch("cache");
+        let settings = Settings {
+            cache: Some(Cache::new(scratch.join("cache"))),
+            scratch: scratch.clone(),
+            ..Settings::default()
+        };
+        download(&fixtures, &["202210"], &settings, &mut Vec::new()).await;
+        assert!(scratch.join("cache/202210/1.json").exists());
+        assert!(!scratch.join("cache/202210/2.json").exists());
+
+        let titles = |output: Vec<u8>| -> Vec<Value> {
+            serde_json::Deserializer::from_slice(&output)
+                .into_iter::<Value>()
+                .map(|detail| detail.unwrap()["title"].clone())
+                .collect()
+        };
+        *title.lock().unwrap() = "Introduction";
+        let mut output = Vec::new();
+        download(&fixtures, &["202210"], &settings, &mut output).await;
+        assert_eq!(titles(output), ["Intro"]);
+        let settings = Settings {
+            cache: settings.cache.map(Cache::refreshing),
+            ..settings
+        };
+        let mut output = Vec::new();
+        download(&fixtures, &["202210"], &settings, &mut output).await;
+        assert_eq!(titles(output), ["Introduction"]);
+        fs::remove_dir_all(&scratch).unwrap();
+    }
+
+    #[tokio::test]
+    async fn books_unavailable() {
+        let fixtures = Fixtures(|route: &str, body: &Value| match route {
+            "search" => {
+                Ok(serde_json::json!({"results": [{"crn": "1"}, {"crn": "2"}]}).to_string())
+            }
+            "books" if body["crn"] == "1" => {
+                Ok(serde_json::json!({"books": [{"title": "Algorithms"}]}).to_string())
+            }
+            "books" => Err(io::Error::from(io::ErrorKind::TimedOut)),
+            _ => Ok(detail("CSCI 0150", "Intro", "202210")),
+        });
+        let settings = Settings {
+            scratch: scratch("books"),
+            books: true,
+            ..Settings::default()
+        };
+        let mut output = Vec::new();
+        let report = download(&fixtures, &["202210"], &settings, &mut output).await;
+        assert_eq!(report.terms["202210"].records, 2);
+        let mut books: Vec<(Value, Value)> = serde_json::Deserializer::from_slice(&output)
+            .into_iter::<Value>()
+            .map(|detail| {
+                let detail = detail.unwrap();
+                (detail["crn"].clone(), detail["books"].clone())
+            })
+            .collect();
+        books.sort_by_key(|(crn, _)| crn.to_string());
+        assert_eq!(
+            books,
+            [
+                (
+                    Value::from("1"),
+                    serde_json::json!([{"title": "Algorithms"}])
+                ),
+                (Value::from("2"), Value::Null)
+            ]
+        );
+        fs::remove_dir_all(&settings.scratch).unwrap();
+    }
+
+    #[tokio::test]
+    async fn sharded() {
+        let fixtures = Fixtures(|route: &str, body: &Value| match route {
+            "search" => Ok(serde_json::json!({"results": [{"crn": "1"}]}).to_string()),
+            _ => Ok(detail(
+                "CSCI 0150",
+                "Intro",
+                body["srcdb"].as_str().unwrap(),
+            )),
+        });
+        let settings = Settings {
+            scratch: scratch("sharded"),
+            ..Settings::default()
+        };
+        let terms = ["202210", "202220"];
+        let mut shards = [Vec::new(), Vec::new()];
+        let (report, hashes) = download_sharded(&fixtures, &terms, &settings, &mut shards).await;
+        assert_eq!(report.terms.len(), 2);
+        assert_eq!(report.requests.statuses["200"], 4);
+        for (shard, term) in shards.iter().zip(terms) {
+            let written: Value = serde_json::from_slice(shard).unwrap();
+            assert_eq!(written["srcdb"], term);
+        }
+        let mut whole = Vec::new();
+        let single = download(&fixtures, &terms[..1], &settings, &mut whole).await;
+        assert_eq!(whole, shards[0]);
+        assert_eq!(single.hash.hex(), hashes[0].hex());
+        fs::remove_dir_all(&settings.scratch).unwrap();
+    }
+
+    #[tokio::test]
+    async fn budget() {
+        let fixtures = Fixtures(|route: &str, _: &Value| match route {
+            "search" => Ok(serde_json::json!({
+                "results": [{"crn": "1"}, {"crn": "2"}, {"crn": "3"}],
+            })
+            .to_string()),
+            _ => Ok(detail("CSCI 0150", "Intro", "202210")),
+        });
+        let settings = Settings {
+            scratch: scratch("budget"),
+            max_connections: 1,
+            budget: Budget {
+                max_requests: Some(3),
+                max_duration: None,
+            },
+            ..Settings::default()
+        };
+        let mut output = Vec::new();
+        let report = download(&fixtures, &["202210"], &settings, &mut output).await;
+        let expected = TermReport {
+            records: 2,
+            unfinished: 1,
+            ..TermReport::default()
+        };
+        assert_eq!(report.terms["202210"], expected);
+        let crns: Vec<Value> = serde_json::Deserializer::from_slice(&output)
+            .into_iter::<Value>()
+            .map(|detail| detail.unwrap()["crn"].clone())
+            .collect();
+        assert_eq!(crns, ["1", "2"]);
+        fs::remove_dir_all(&settings.scratch).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejections() {
+        struct Status(u16);
+        impl Http for Status {
+            fn post<'a>(&'a self, _: &'a str, _: &'a Value) -> BoxFuture<'a, io::Result<Response>> {
+                let response = Response {
+                    status: self.0,
+                    body: Bytes::from("{}"),
+                };
+                async move { Ok(response) }.boxed()
+            }
+        }
+        let concurrency = Concurrency::new(6);
+        let ok = Status(200);
+        let successful = Successful { inner: &ok };
+        for _ in 0..4 {
+            assert!(concurrency
+                .run(successful.post("", &Value::Null))
+                .await
+                .is_ok());
+        }
+        assert_eq!(concurrency.limit(), 5);
+        let throttled = Status(429);
+        let successful = Successful { inner: &throttled };
+        assert!(concurrency
+            .run(successful.post("", &Value::Null))
+            .await
+            .is_err());
+        assert_eq!(concurrency.limit(), 2);
+    }
+
+    #[test]
+    fn concurrency() {
+        let concurrency = Concurrency::new(6);
+        assert_eq!(concurrency.limit(), 4);
+        for _ in 0..4 {
+            assert!(!concurrency.record(true, Duration::from_millis(100)));
+        }
+        assert_eq!(concurrency.limit(), 5);
+        assert!(concurrency.record(false, Duration::from_millis(100)));
+        assert_eq!(concurrency.limit(), 2);
+        assert!(concurrency.record(true, Duration::from_millis(100)));
+        assert!(concurrency.record(true, Duration::from_secs(1)));
+        assert_eq!(concurrency.limit(), 1);
+    }
+
+    #[test]
+    fn brown() {
+        assert_eq!(Brown.terms().len(), 27);
+        assert_eq!(Brown.term_name("201610"), "Fall 2016");
+        assert_eq!(Brown.term_name("201615"), "Winter 2017");
+        assert_eq!(Brown.term_name("202220"), "Spring 2023");
+        assert_eq!(
+            Brown.url(Route::Search),
+            "https://cab.brown.edu/api/?page=fose&route=search"
+        );
+    }
+
+    #[test]
+    fn truncation() {
+        let results = |count: Option<usize>, returned: usize| SearchResults {
+            count,
+            results: (0..returned)
+                .map(|crn| Crn {
+                    crn: crn.to_string(),
+                    code: String::from("CSCI 0150"),
+                    fields: serde_json::Map::new(),
+                })
+                .collect(),
+        };
+        assert!(results(Some(300), 200).is_truncated());
+        assert!(!results(Some(200), 200).is_truncated());
+        assert!(!results(Some(200), 200).looks_truncated());
+        assert!(results(None, 200).looks_truncated());
+        assert!(!results(None, 201).looks_truncated());
+        assert!(!results(None, 0).looks_truncated());
+    }
+}
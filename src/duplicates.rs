@@ -0,0 +1,108 @@
+use crate::process::Course;
+use crate::restrictions::CourseCode;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Descriptions shorter than this (after normalization) are placeholders like
+/// "No description available." that many unrelated courses share.
+const MIN_LENGTH: usize = 60;
+
+/// Distinct courses with the same description, which are usually cross-listings
+/// that the API doesn't report as aliases, or descriptions pasted into the wrong course.
+///
+/// Written one per line, these are candidate alias entries to review.
+#[derive(Debug, Clone, Serialize)]
+pub struct Duplicate {
+    pub codes: Vec<CourseCode>,
+    pub description: String,
+}
+
+/// Groups of courses sharing a description, except groups where every course
+/// already lists the others as aliases
+pub fn duplicates(courses: &[Course]) -> Vec<Duplicate> {
+    let mut groups: BTreeMap<String, Vec<&Course>> = BTreeMap::new();
+    for course in courses.iter() {
+        let description = normalize(course.description());
+        if description.len() < MIN_LENGTH {
+            continue;
+        }
+        groups.entry(description).or_default().push(course);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1 && !cross_listed(group))
+        .map(|group| Duplicate {
+            codes: group.iter().map(|course| course.code().clone()).collect(),
+            description: group[0].description().to_string(),
+        })
+        .collect()
+}
+
+fn cross_listed(group: &[&Course]) -> bool {
+    group.iter().all(|course| {
+        group.iter().all(|other| {
+            other.code() == course.code()
+                || course
+                    .aliases()
+                    .iter()
+                    .any(|alias| alias.code() == other.code())
+        })
+    })
+}
+
+/// Lowercase words without punctuation, separated by single spaces
+fn normalize(description: &str) -> String {
+    description
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{duplicates, normalize};
+    use crate::process::Course;
+
+    #[test]
+    fn normalization() {
+        assert_eq!(
+            normalize("An  introduction to\nAlgorithms, (and) Data-Structures."),
+            "an introduction to algorithms and data structures"
+        );
+        assert_eq!(normalize(" ... "), "");
+    }
+
+    #[test]
+    fn shared() {
+        let course = |code: &str, description: &str| {
+            let mut course = Course::fixture(code, serde_json::json!([]));
+            course["description"] = description.into();
+            serde_json::from_value::<Course>(course).unwrap()
+        };
+        let shared = "Algorithms and data structures, with an emphasis on their analysis.";
+        let courses = [
+            course("CSCI 0200", shared),
+            course(
+                "APMA 0200",
+                "algorithms and data structures with an emphasis on their analysis",
+            ),
+            course(
+                "CSCI 0300",
+                "Systems programming, from the hardware up to the operating system.",
+            ),
+            course("CSCI 0111", "No description available."),
+            course("CSCI 0112", "No description available."),
+        ];
+        let duplicates = duplicates(&courses);
+        assert_eq!(duplicates.len(), 1);
+        let codes: Vec<_> = duplicates[0]
+            .codes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(codes, ["CSCI 0200", "APMA 0200"]);
+        assert_eq!(duplicates[0].description, shared);
+    }
+}
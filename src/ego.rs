@@ -0,0 +1,181 @@
+use crate::compression;
+use crate::graph;
+use crate::hash::Fnv;
+use crate::process::Course;
+use crate::restrictions::{CourseCode, Operator, PrerequisiteTree, Qualification};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// A course's prerequisites in graphviz's DOT language, with the prerequisites
+/// of prerequisite courses expanded up to `depth` courses away
+pub fn graphviz(
+    code: &CourseCode,
+    courses: &HashMap<&CourseCode, &Course>,
+    depth: usize,
+) -> String {
+    let mut ego = Ego {
+        dot: String::from("digraph {\nrankdir=BT\nnode [shape=box]\n"),
+        next: 0,
+    };
+    let root = ego.node(&code.to_string(), "bold");
+    if let Some(tree) = courses.get(code).and_then(|course| course.prerequisites()) {
+        ego.tree(root, tree, courses, depth);
+    }
+    ego.dot.push_str("}\n");
+    ego.dot
+}
+
+struct Ego {
+    dot: String,
+    next: usize,
+}
+
+impl Ego {
+    fn node(&mut self, label: &str, style: &str) -> usize {
+        self.next += 1;
+        writeln!(self.dot, "{} [label={label:?},style={style}]", self.next).unwrap();
+        self.next
+    }
+
+    /// Adds `tree` as the prerequisites of the node `parent`
+    fn tree(
+        &mut self,
+        parent: usize,
+        tree: &PrerequisiteTree,
+        courses: &HashMap<&CourseCode, &Course>,
+        depth: usize,
+    ) {
+        let node = match tree {
+            PrerequisiteTree::Qualification(qualification) => {
+                let node = self.node(&qualification.to_string(), "solid");
+                if let Some(code) = qualification.course() {
+                    let prerequisites = courses.get(code).and_then(|course| course.prerequisites());
+                    if let Some(tree) = prerequisites.filter(|_| depth > 1) {
+                        self.tree(node, tree, courses, depth - 1);
+                    }
+                }
+                node
+            }
+            PrerequisiteTree::Operator(operator, children) => {
+                self.next += 1;
+                let node = self.next;
+                let label = match operator {
+                    Operator::All => "and",
+                    Operator::Any => "or",
+                    Operator::Not => "not",
+                };
+                writeln!(self.dot, "{node} [label={label},shape=circle]").unwrap();
+                for child in children {
+                    self.tree(node, child, courses, depth);
+                }
+                node
+            }
+        };
+        writeln!(self.dot, "{node} -> {parent}").unwrap();
+    }
+}
+
+/// Renders every course's ego graph to `<directory>/<SUBJ-NUMBER>.svg` on all cores.
+/// Graphs whose DOT source hasn't changed since the last run, according to
+/// `<directory>/.hashes.json`, aren't rendered again.
+pub fn render_all<P: AsRef<Path>>(
+    courses: &[Course],
+    depth: usize,
+    directory: P,
+) -> io::Result<()> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    let hashes_path = directory.join(".hashes.json");
+    let previous: BTreeMap<String, String> = match fs::read(&hashes_path) {
+        Ok(json) => serde_json::from_slice(&json)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e),
+    };
+    let by_code: HashMap<&CourseCode, &Course> = courses
+        .iter()
+        .map(|course| (course.code(), course))
+        .collect();
+    let hashes = Mutex::new(BTreeMap::new());
+    let rendered = AtomicUsize::new(0);
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| -> io::Result<()> {
+                    while let Some(course) = courses.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let name = format!("{}.svg", course.code().to_string().replace(' ', "-"));
+                        let dot = graphviz(course.code(), &by_code, depth);
+                        let mut hash = Fnv::new();
+                        hash.write_all(dot.as_bytes())?;
+                        let hash = hash.hex();
+                        let path = directory.join(&name);
+                        if previous.get(&name) != Some(&hash) || !path.exists() {
+                            compression::write(path, graph::graphviz_to_svg(&dot)?)?;
+                            rendered.fetch_add(1, Ordering::Relaxed);
+                        }
+                        hashes.lock().unwrap().insert(name, hash);
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("ego graph worker panicked"))
+    })?;
+    eprintln!(
+        "rendered {} of {} ego graphs",
+        rendered.into_inner(),
+        courses.len()
+    );
+    let hashes = hashes.into_inner().unwrap();
+    compression::write(hashes_path, serde_json::to_vec_pretty(&hashes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::graphviz;
+    use crate::process::Course;
+    use crate::restrictions::CourseCode;
+    use std::collections::HashMap;
+
+    fn course(code: &str, prerequisites: &str) -> Course {
+        let mut course = Course::fixture(code, serde_json::json!([]));
+        if !prerequisites.is_empty() {
+            course["prerequisites"] = serde_json::to_value(
+                crate::restrictions::PrerequisiteTree::try_from(prerequisites).unwrap(),
+            )
+            .unwrap();
+        }
+        serde_json::from_value(course).unwrap()
+    }
+
+    #[test]
+    fn depth() {
+        let courses = [
+            course("CSCI 0300", "CSCI 0200"),
+            course("CSCI 0200", "CSCI 0150 or CSCI 0170"),
+            course("CSCI 0150", "MATH 0090"),
+        ];
+        let by_code: HashMap<&CourseCode, &Course> = courses
+            .iter()
+            .map(|course| (course.code(), course))
+            .collect();
+        let code = CourseCode::try_from("CSCI 0300").unwrap();
+        let shallow = graphviz(&code, &by_code, 1);
+        assert!(shallow.contains("\"CSCI 0200\""));
+        assert!(!shallow.contains("\"CSCI 0150\""));
+        let deep = graphviz(&code, &by_code, 2);
+        assert!(deep.contains("\"CSCI 0150\"") && deep.contains("label=or"));
+        assert!(!deep.contains("\"MATH 0090\""));
+        assert!(graphviz(&code, &by_code, 3).contains("\"MATH 0090\""));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::config;
+use crate::restrictions::{ExamScore, PrerequisiteTree, Qualification, StudentProfile};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// An exam's entry in `resources/exams.toml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exam {
+    /// The name prerequisites are rewritten to use
+    name: String,
+    label: Option<String>,
+    scale: Option<RangeInclusive<u32>>,
+    /// Exams that a score on this one counts as the same score on
+    implies: Vec<String>,
+}
+
+impl Exam {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What to call the exam in reports and graphs, its name if it has no label
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The scores the exam can give
+    pub fn scale(&self) -> Option<&RangeInclusive<u32>> {
+        self.scale.as_ref()
+    }
+
+    pub fn implies(&self) -> &[String] {
+        &self.implies
+    }
+}
+
+/// The registry that ranking exam scores against each other consults
+static REGISTRY: Lazy<Exams> = Lazy::new(Exams::default);
+
+/// Whether a score on `stronger` counts as the same score on `weaker`, by the
+/// equivalences in `resources/exams.toml`
+pub fn implies(stronger: &str, weaker: &str) -> bool {
+    REGISTRY.implies(stronger, weaker)
+}
+
+/// The exam registry, keyed by every name each exam goes by
+#[derive(Debug, Clone)]
+pub struct Exams {
+    exams: Vec<Exam>,
+    /// `key` of each name or alias, to its index in `exams`
+    names: HashMap<String, usize>,
+}
+
+impl Default for Exams {
+    fn default() -> Exams {
+        Exams::parse(include_str!("../resources/exams.toml")).unwrap()
+    }
+}
+
+impl Exams {
+    fn parse(text: &str) -> Result<Exams, String> {
+        let mut exams = Vec::new();
+        let mut names = HashMap::new();
+        for (name, table) in config::parse(text)? {
+            let mut exam = Exam {
+                name,
+                label: None,
+                scale: None,
+                implies: Vec::new(),
+            };
+            let mut aliases = Vec::new();
+            for (key, value) in table {
+                match key.as_str() {
+                    "label" => exam.label = Some(value),
+                    "scale" => {
+                        exam.scale = Some(scale(&value).ok_or_else(|| {
+                            format!(
+                                "invalid scale {value} for {}, expected like 1..5",
+                                exam.name
+                            )
+                        })?)
+                    }
+                    "aliases" => aliases = value.split(',').map(String::from).collect(),
+                    "implies" => exam.implies = value.split(',').map(String::from).collect(),
+                    _ => {
+                        return Err(format!(
+                            "unknown key {key} for {}, expected label, scale, aliases, or implies",
+                            exam.name
+                        ))
+                    }
+                }
+            }
+            for alias in aliases.iter().chain([&exam.name]) {
+                if names
+                    .insert(key(alias), exams.len())
+                    .is_some_and(|i| i != exams.len())
+                {
+                    return Err(format!("{alias} names more than one exam"));
+                }
+            }
+            exams.push(exam);
+        }
+        let exams = Exams { exams, names };
+        for exam in exams.exams.iter() {
+            if let Some(implied) = exam.implies.iter().find(|name| exams.get(name).is_none()) {
+                return Err(format!(
+                    "{} implies {implied}, which is not an exam",
+                    exam.name
+                ));
+            }
+        }
+        // or scores on two exams would each rank above the other
+        if let Some(exam) = exams
+            .exams
+            .iter()
+            .find(|exam| exams.implies(&exam.name, &exam.name))
+        {
+            return Err(format!(
+                "{} implies itself through the exams it implies",
+                exam.name
+            ));
+        }
+        Ok(exams)
+    }
+
+    /// The exam that `name` or something like it refers to
+    pub fn get(&self, name: &str) -> Option<&Exam> {
+        self.names.get(&key(name)).map(|&i| &self.exams[i])
+    }
+
+    /// `name` as the registry spells it, or unchanged if it isn't registered
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str {
+        self.get(name).map_or(name, Exam::name)
+    }
+
+    /// Whether a score on `stronger` counts as the same score on `weaker` through a
+    /// chain of `implies`. Names are looked up like `get`, but an exam doesn't imply
+    /// itself under another name, since that is what `normalize` is for
+    pub fn implies(&self, stronger: &str, weaker: &str) -> bool {
+        let (Some(stronger), Some(weaker)) = (self.get(stronger), self.get(weaker)) else {
+            return false;
+        };
+        let mut seen = vec![stronger.name()];
+        let mut stack = vec![stronger];
+        while let Some(exam) = stack.pop() {
+            for implied in exam.implies.iter().filter_map(|name| self.get(name)) {
+                if implied == weaker {
+                    return true;
+                }
+                if !seen.contains(&implied.name()) {
+                    seen.push(implied.name());
+                    stack.push(implied);
+                }
+            }
+        }
+        false
+    }
+
+    /// Like "AP Calc BC 4/5", with the top of the scale if it is known
+    pub fn describe(&self, score: &ExamScore) -> String {
+        match self.get(&score.exam) {
+            Some(exam) => match exam.scale() {
+                Some(scale) => format!("{} {}/{}", exam.label(), score.score, scale.end()),
+                None => format!("{} {}", exam.label(), score.score),
+            },
+            None => format!("{} {}", score.exam, score.score),
+        }
+    }
+
+    /// With a registered exam's name in canonical form, so that it ranks against
+    /// the same exam spelled differently
+    pub fn normalize(&self, qualification: &Qualification) -> Qualification {
+        match qualification {
+            Qualification::ExamScore(ExamScore { exam, score }) => {
+                Qualification::ExamScore(ExamScore {
+                    exam: self.canonical(exam).to_string(),
+                    score: *score,
+                })
+            }
+            qualification => qualification.clone(),
+        }
+    }
+
+    pub fn normalize_tree(&self, tree: &PrerequisiteTree) -> PrerequisiteTree {
+        tree.map_qualifications(|qualification| self.normalize(qualification))
+    }
+
+    /// So that a transcript's exam names match the ones in prerequisites
+    pub fn normalize_profile(&self, profile: &mut StudentProfile) {
+        profile.qualifications = profile
+            .qualifications
+            .iter()
+            .map(|qualification| self.normalize(qualification))
+            .collect();
+    }
+}
+
+/// Lowercase letters and digits, so that spacing and punctuation don't matter
+fn key(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn scale(range: &str) -> Option<RangeInclusive<u32>> {
+    let (low, high) = range.split_once("..")?;
+    Some(low.trim().parse().ok()?..=high.trim().parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Exams;
+    use crate::restrictions::{ExamScore, PrerequisiteTree, Qualification, StudentProfile};
+
+    #[test]
+    fn registry() {
+        let exams = Exams::default();
+        assert_eq!(exams.canonical("AP Calc BC"), "AP Calculus BC");
+        assert_eq!(exams.canonical("ap  calculus-bc"), "AP Calculus BC");
+        assert_eq!(
+            exams.canonical("BIOL Placement Test Min. Score"),
+            "BIOL Placement Test Min.Score"
+        );
+        assert_eq!(exams.canonical("Unknown Exam"), "Unknown Exam");
+        let score = |exam: &str, score| ExamScore {
+            exam: exam.to_string(),
+            score,
+        };
+        assert_eq!(
+            exams.describe(&score("AP Calculus BC", 4)),
+            "AP Calc BC 4/5"
+        );
+        assert_eq!(
+            exams.describe(&score("Spanish Placement", 491)),
+            "Spanish placement 491"
+        );
+
+        let tree =
+            PrerequisiteTree::try_from("MATH 0090 or minimum score of 4 in 'AP Calc BC'").unwrap();
+        let tree = exams.normalize_tree(&tree);
+        assert_eq!(
+            tree,
+            PrerequisiteTree::try_from("MATH 0090 or minimum score of 4 in 'AP Calculus BC'")
+                .unwrap()
+        );
+        let mut profile = StudentProfile {
+            qualifications: [Qualification::ExamScore(score(
+                "Advanced Placement Calculus BC",
+                5,
+            ))]
+            .into_iter()
+            .collect(),
+            semester_level: "01".parse().unwrap(),
+        };
+        assert!(!tree.evaluate(&profile));
+        exams.normalize_profile(&mut profile);
+        assert!(tree.evaluate(&profile));
+
+        assert!(exams.implies("AP Calc BC", "AP Calculus AB"));
+        assert!(!exams.implies("AP Calculus AB", "AP Calculus BC"));
+        let bc = Qualification::ExamScore(score("AP Calculus BC", 5));
+        let ab =
+            PrerequisiteTree::Qualification(Qualification::ExamScore(score("AP Calculus AB", 4)));
+        profile.qualifications = [bc].into_iter().collect();
+        assert!(ab.evaluate(&profile));
+        profile.qualifications = [Qualification::ExamScore(score("AP Calculus BC", 3))]
+            .into_iter()
+            .collect();
+        assert!(!ab.evaluate(&profile));
+
+        assert!(Exams::parse("[\"A\"]\nscale = \"high\"").is_err());
+        assert!(Exams::parse("[\"A\"]\nimplies = [\"B\"]").is_err());
+        assert!(Exams::parse("[\"A\"]\n[\"B\"]\naliases = [\"a\"]").is_err());
+        assert!(Exams::parse("[\"A\"]\nimplies = [\"A\"]").is_err());
+        let mutual = "[\"A\"]\nimplies = [\"B\"]\n[\"B\"]\nimplies = [\"A\"]";
+        assert!(Exams::parse(mutual).is_err());
+        let cycle =
+            "[\"A\"]\nimplies = [\"B\"]\n[\"B\"]\nimplies = [\"C\"]\n[\"C\"]\nimplies = [\"A\"]";
+        assert!(Exams::parse(cycle).is_err());
+        assert!(Exams::parse("[\"A\"]\nimplies = [\"B\"]\n[\"B\"]").is_ok());
+    }
+}
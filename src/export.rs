@@ -0,0 +1,205 @@
+use crate::clauses;
+use crate::coverage::Coverage;
+use crate::exams::Exams;
+use crate::gatekeeper;
+use crate::graph;
+use crate::process::Course;
+use crate::schema;
+use crate::subjects::Subjects;
+use std::io;
+use std::io::Write;
+
+/// An output format for `export`
+pub trait Exporter: Sync {
+    /// What `--format` calls it
+    fn name(&self) -> &str;
+    /// Of the files it writes, without the dot
+    fn extension(&self) -> &str;
+    fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()>;
+}
+
+/// The exporters `export --format` can choose from
+pub struct Registry {
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Default for Registry {
+    /// The built in exporters
+    fn default() -> Registry {
+        let mut registry = Registry::empty();
+        registry.register(Box::new(Jsonl));
+        registry.register(Box::new(Csv));
+        registry.register(Box::new(Dot));
+        registry.register(Box::new(Gatekeeper));
+        registry.register(Box::new(Clauses(clauses::Format::Datalog)));
+        registry.register(Box::new(Clauses(clauses::Format::Dimacs)));
+        registry
+    }
+}
+
+impl Registry {
+    pub fn empty() -> Registry {
+        Registry {
+            exporters: Vec::new(),
+        }
+    }
+
+    /// Replaces any exporter with the same name
+    pub fn register(&mut self, exporter: Box<dyn Exporter>) {
+        self.exporters
+            .retain(|existing| existing.name() != exporter.name());
+        self.exporters.push(exporter);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.exporters
+            .iter()
+            .find(|exporter| exporter.name() == name)
+            .map(Box::as_ref)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.exporters.iter().map(|exporter| exporter.name())
+    }
+}
+
+/// One course per line after the schema header, as `process` writes them
+pub struct Jsonl;
+
+impl Exporter for Jsonl {
+    fn name(&self) -> &str {
+        "jsonl"
+    }
+
+    fn extension(&self) -> &str {
+        "jsonl"
+    }
+
+    fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()> {
+        schema::write_header(output)?;
+        for course in courses {
+            serde_json::to_writer(&mut *output, course)?;
+            output.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of sections of each course each term
+pub struct Csv;
+
+impl Exporter for Csv {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extension(&self) -> &str {
+        "csv"
+    }
+
+    fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()> {
+        output.write_all(Coverage::new(courses).csv().as_bytes())
+    }
+}
+
+/// The prerequisite graph, for graphviz
+pub struct Dot;
+
+impl Exporter for Dot {
+    fn name(&self) -> &str {
+        "dot"
+    }
+
+    fn extension(&self) -> &str {
+        "dot"
+    }
+
+    fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()> {
+        output
+            .write_all(graph::graphviz(courses, &Subjects::default(), &Exams::default()).as_bytes())
+    }
+}
+
+/// `gatekeeper::metrics` of each course
+pub struct Gatekeeper;
+
+impl Exporter for Gatekeeper {
+    fn name(&self) -> &str {
+        "gatekeeper"
+    }
+
+    fn extension(&self) -> &str {
+        "csv"
+    }
+
+    fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()> {
+        output.write_all(gatekeeper::csv(&gatekeeper::metrics(courses)).as_bytes())
+    }
+}
+
+/// Prerequisites as clauses for a solver
+pub struct Clauses(pub clauses::Format);
+
+impl Exporter for Clauses {
+    fn name(&self) -> &str {
+        match self.0 {
+            clauses::Format::Datalog => "datalog",
+            clauses::Format::Dimacs => "dimacs",
+        }
+    }
+
+    fn extension(&self) -> &str {
+        match self.0 {
+            clauses::Format::Datalog => "dl",
+            clauses::Format::Dimacs => "cnf",
+        }
+    }
+
+    fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()> {
+        output.write_all(clauses::export(courses, self.0).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Exporter, Jsonl, Registry};
+    use crate::process::Course;
+    use std::io;
+    use std::io::Write;
+
+    struct Count;
+
+    impl Exporter for Count {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn write(&self, courses: &[Course], output: &mut dyn Write) -> io::Result<()> {
+            writeln!(output, "{}", courses.len())
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = Registry::default();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["jsonl", "csv", "dot", "gatekeeper", "datalog", "dimacs"]
+        );
+        assert_eq!(registry.get("dimacs").unwrap().extension(), "cnf");
+        registry.register(Box::new(Count));
+        let mut output = Vec::new();
+        registry
+            .get("count")
+            .unwrap()
+            .write(&[], &mut output)
+            .unwrap();
+        assert_eq!(output, b"0\n");
+        registry.register(Box::new(Jsonl));
+        assert_eq!(registry.names().count(), 7);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::process::Course;
+use crate::restrictions::{CourseCode, PrerequisiteTree, Qualification};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Proxies for how much a course holds students back from the rest of the catalog
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Metrics {
+    pub code: CourseCode,
+    /// The longest chain of prerequisites below the course
+    pub depth: usize,
+    /// Courses that need it, directly or through their own prerequisites
+    pub dependents: usize,
+    /// Ever offered
+    pub sections: usize,
+    /// Mean enrollment over capacity, of the sections that have both
+    pub fill: Option<f32>,
+    /// The other metrics combined, from 0 to 1
+    pub score: f32,
+}
+
+/// The prerequisites of each course
+fn edges(courses: &[Course]) -> HashMap<&CourseCode, Vec<&CourseCode>> {
+    courses
+        .iter()
+        .map(|course| {
+            let prerequisites = course
+                .prerequisites()
+                .into_iter()
+                .flat_map(PrerequisiteTree::qualifications)
+                .filter_map(Qualification::course)
+                .collect();
+            (course.code(), prerequisites)
+        })
+        .collect()
+}
+
+/// Memoized, and a course on a prerequisite cycle counts as a leaf the second time
+fn depth<'a>(
+    code: &'a CourseCode,
+    prerequisites: &HashMap<&'a CourseCode, Vec<&'a CourseCode>>,
+    visiting: &mut HashSet<&'a CourseCode>,
+    memo: &mut HashMap<&'a CourseCode, usize>,
+) -> usize {
+    if let Some(&depth) = memo.get(code) {
+        return depth;
+    }
+    if !visiting.insert(code) {
+        return 0;
+    }
+    let below = prerequisites.get(code).map_or(&[][..], Vec::as_slice);
+    let ret = below
+        .iter()
+        .map(|prerequisite| 1 + depth(prerequisite, prerequisites, visiting, memo))
+        .max()
+        .unwrap_or(0);
+    visiting.remove(code);
+    memo.insert(code, ret);
+    ret
+}
+
+fn fill(course: &Course) -> Option<f32> {
+    let fills: Vec<f32> = course
+        .offerings()
+        .iter()
+        .filter_map(|offering| Some((offering.enrollment()?, offering.capacity()?)))
+        .filter(|&(_, capacity)| capacity > 0)
+        .map(|(enrollment, capacity)| enrollment as f32 / capacity as f32)
+        .collect();
+    (!fills.is_empty()).then(|| fills.iter().sum::<f32>() / fills.len() as f32)
+}
+
+/// The metrics of every course, in the order of `courses`.
+///
+/// The score weighs dependents most, since a course many others need gates the most,
+/// then depth, section count, and fill equally, each scaled by its largest value.
+pub fn metrics(courses: &[Course]) -> Vec<Metrics> {
+    let prerequisites = edges(courses);
+    let mut dependents: HashMap<&CourseCode, Vec<&CourseCode>> = HashMap::new();
+    for (&course, below) in prerequisites.iter() {
+        for &prerequisite in below {
+            dependents.entry(prerequisite).or_default().push(course);
+        }
+    }
+    let mut memo = HashMap::new();
+    let mut ret: Vec<Metrics> = courses
+        .iter()
+        .map(|course| {
+            let code = course.code();
+            let mut reached = HashSet::from([code]);
+            let mut pending = vec![code];
+            while let Some(code) = pending.pop() {
+                for &dependent in dependents.get(code).into_iter().flatten() {
+                    if reached.insert(dependent) {
+                        pending.push(dependent);
+                    }
+                }
+            }
+            Metrics {
+                code: code.clone(),
+                depth: depth(code, &prerequisites, &mut HashSet::new(), &mut memo),
+                dependents: reached.len() - 1,
+                sections: course.offerings().len(),
+                fill: fill(course),
+                score: 0.0,
+            }
+        })
+        .collect();
+    let max = |metric: fn(&Metrics) -> f32| {
+        ret.iter()
+            .map(metric)
+            .fold(0.0, f32::max)
+            .max(f32::MIN_POSITIVE)
+    };
+    let max_dependents = max(|m| m.dependents as f32);
+    let max_depth = max(|m| m.depth as f32);
+    let max_sections = max(|m| m.sections as f32);
+    for metrics in ret.iter_mut() {
+        metrics.score = 0.4 * metrics.dependents as f32 / max_dependents
+            + 0.2 * metrics.depth as f32 / max_depth
+            + 0.2 * metrics.sections as f32 / max_sections
+            + 0.2 * metrics.fill.unwrap_or(0.0).min(1.0);
+    }
+    ret
+}
+
+pub fn csv(metrics: &[Metrics]) -> String {
+    let mut ret = String::from("code,depth,dependents,sections,fill,score\n");
+    for m in metrics {
+        let fill = m.fill.map_or(String::new(), |fill| format!("{fill:.3}"));
+        writeln!(
+            ret,
+            "{},{},{},{},{fill},{:.3}",
+            m.code, m.depth, m.dependents, m.sections, m.score
+        )
+        .unwrap();
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::metrics;
+    use crate::process::Course;
+    use crate::restrictions::{CourseCode, PrerequisiteTree};
+
+    fn course(code: &str, prerequisites: Option<&str>) -> Course {
+        let prerequisites = prerequisites.map(|p| PrerequisiteTree::try_from(p).unwrap());
+        let mut course = Course::fixture(
+            code,
+            serde_json::json!([{"enrollment": 30, "capacity": 40}]),
+        );
+        course["prerequisites"] = serde_json::to_value(prerequisites).unwrap();
+        serde_json::from_value(course).unwrap()
+    }
+
+    #[test]
+    fn chain() {
+        let courses = [
+            course("CSCI 0150", None),
+            course("CSCI 0160", Some("CSCI 0150")),
+            course("CSCI 0330", Some("CSCI 0160 or CSCI 0150")),
+        ];
+        let metrics = metrics(&courses);
+        let depths: Vec<_> = metrics.iter().map(|m| m.depth).collect();
+        let dependents: Vec<_> = metrics.iter().map(|m| m.dependents).collect();
+        assert_eq!(depths, [0, 1, 2]);
+        assert_eq!(dependents, [2, 1, 0]);
+        assert_eq!(metrics[0].fill, Some(0.75));
+        assert!(metrics[0].score > metrics[1].score);
+    }
+}
@@ -1,5 +1,9 @@
+use crate::exams::Exams;
+use crate::gatekeeper;
+use crate::html;
 use crate::process::Course;
 use crate::restrictions::{CourseCode, Operator, PrerequisiteTree, Qualification};
+use crate::subjects::Subjects;
 use once_cell::sync::Lazy;
 use rand::{thread_rng, Rng};
 use regex::{Regex, RegexBuilder};
@@ -11,7 +15,7 @@ use std::io::{Read, Write as _};
 use std::ops::{Index, IndexMut};
 use std::process::{Command, Stdio};
 
-fn graphviz_to_svg(graphviz: &str) -> io::Result<String> {
+pub fn graphviz_to_svg(graphviz: &str) -> io::Result<String> {
     let mut dotted = Command::new("dot")
         .arg("-Tsvg")
         .arg("/dev/stdin")
@@ -29,10 +33,33 @@ fn graphviz_to_svg(graphviz: &str) -> io::Result<String> {
     Ok(svg)
 }
 
-fn svg_box(code: &CourseCode, course: Option<&Course>, x: f32, y: f32) -> String {
+/// `score`, from 0 to 1, shades the box from white to red. An annotated course is
+/// marked with a dagger, and its annotation is the box's tooltip. A first year or
+/// sophomore seminar gets a dashed border, with its enrollment cap in the corner.
+fn svg_box(
+    code: &CourseCode,
+    course: Option<&Course>,
+    score: Option<f32>,
+    x: f32,
+    y: f32,
+) -> String {
     let mut ret = String::new();
     let x = x - 102.0;
-    writeln!(ret, r#"<rect style="fill:#ffffff;stroke:#000000;stroke-width:3" width="102" height="44" x="{}" y="{}" />"#, x, y).unwrap();
+    let shade = 255 - (200.0 * score.unwrap_or(0.0).clamp(0.0, 1.0)) as u8;
+    let fill = format!("#ff{shade:02x}{shade:02x}");
+    let annotation = course.and_then(Course::annotation);
+    ret.push_str("<g>");
+    if let Some(annotation) = annotation {
+        let title = html::escape(&annotation.to_string());
+        writeln!(ret, "<title>{code}: {title}</title>").unwrap();
+    }
+    let seminar = course
+        .is_some_and(|course| course.is_first_year_seminar() || course.is_sophomore_seminar());
+    let dash = match seminar {
+        true => ";stroke-dasharray:6,3",
+        false => "",
+    };
+    writeln!(ret, r#"<rect style="fill:{fill};stroke:#000000;stroke-width:3{dash}" width="102" height="44" x="{}" y="{}" />"#, x, y).unwrap();
     writeln!(
         ret,
         r#"<text x="{}" y="{}" style="font-family:monospace;font-size:16px">{}</text>"#,
@@ -53,10 +80,33 @@ fn svg_box(code: &CourseCode, course: Option<&Course>, x: f32, y: f32) -> String
             .unwrap();
         }
     }
+    if let Some(cap) = course.filter(|_| seminar).and_then(Course::enrollment_cap) {
+        writeln!(
+            ret,
+            r#"<text x="{}" y="{}" style="font-family:monospace;font-size:8px">cap {cap}</text>"#,
+            x + 72.0,
+            y + 40.0
+        )
+        .unwrap();
+    }
+    if annotation.is_some() {
+        writeln!(
+            ret,
+            r#"<text x="{}" y="{}" style="font-family:monospace;font-size:12px">†</text>"#,
+            x + 92.0,
+            y + 12.0
+        )
+        .unwrap();
+    }
+    ret.push_str("</g>");
     ret
 }
 
-fn svg_filter(svg: &mut String, courses: &HashMap<CourseCode, Course>) {
+fn svg_filter(svg: &mut String, courses: &[Course], scores: &HashMap<&CourseCode, f32>) {
+    let courses: HashMap<&CourseCode, &Course> = courses
+        .iter()
+        .map(|course| (course.code(), course))
+        .collect();
     // static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<g id=".*?" class="node qual_(.*?)">.*?points="(.*?),(.*?) .*?</g>"#).unwrap());
     static REGEX: Lazy<Regex> = Lazy::new(|| {
         RegexBuilder::new(
@@ -66,33 +116,63 @@ fn svg_filter(svg: &mut String, courses: &HashMap<CourseCode, Course>) {
         .build()
         .unwrap()
     });
-    while let Some(location) = REGEX.captures(&svg) {
+    while let Some(location) = REGEX.captures(svg) {
         let entire_range = location.get(0).unwrap().range();
         let code = location[1].try_into().unwrap();
         let top_left_x = location[2].parse::<f32>().unwrap();
         let top_left_y = location[3].parse().unwrap();
-        let new_svg = svg_box(&code, courses.get(&code), top_left_x, top_left_y);
+        let score = scores.get(&code).copied();
+        let new_svg = svg_box(
+            &code,
+            courses.get(&code).copied(),
+            score,
+            top_left_x,
+            top_left_y,
+        );
         svg.replace_range(entire_range, &new_svg);
     }
 }
 
-pub fn svg(courses: &HashMap<CourseCode, Course>) -> io::Result<String> {
+/// The prerequisite graph in graphviz's DOT language, one cluster per subject.
+/// Clusters are laid out in the natural order of the subjects' display names.
+pub fn graphviz(courses: &[Course], subjects: &Subjects, exams: &Exams) -> String {
     let mut id_generator = IdGenerator::default();
-    let subjects: HashSet<&str> = courses.keys().map(|code| code.subject()).collect();
-    let subject_graphs: Vec<_> = subjects
+    let mut codes: Vec<&str> = courses
+        .iter()
+        .map(|course| course.code().subject())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    subjects.sort(&mut codes);
+    let subject_graphs: Vec<_> = codes
         .iter()
         .map(|subject| SubjectGraph::new(subject, courses, &mut id_generator))
         .collect();
     let mut graphviz = String::from("digraph {\npackmode=\"graph\"\n");
     for subject_graph in subject_graphs.iter() {
-        subject_graph.graphviz_cluster(&mut graphviz);
+        subject_graph.graphviz_cluster(&mut graphviz, subjects, exams);
     }
-    graphviz.push_str("}");
+    graphviz.push('}');
+    graphviz
+}
 
+/// With each course shaded by its gatekeeper score if `gatekeeper` is set
+pub fn svg(
+    courses: &[Course],
+    subjects: &Subjects,
+    exams: &Exams,
+    gatekeeper: bool,
+) -> io::Result<String> {
+    let graphviz = graphviz(courses, subjects, exams);
     eprintln!("Filtering through graphviz");
     let mut svg = graphviz_to_svg(&graphviz)?;
     eprintln!("Fixup svg");
-    svg_filter(&mut svg, courses);
+    let metrics = match gatekeeper {
+        true => gatekeeper::metrics(courses),
+        false => Vec::new(),
+    };
+    let scores = metrics.iter().map(|m| (&m.code, m.score)).collect();
+    svg_filter(&mut svg, courses, &scores);
     Ok(svg)
 }
 
@@ -102,23 +182,26 @@ struct SubjectGraph {
 }
 
 impl SubjectGraph {
-    fn new(
-        subject: &str,
-        restrictions: &HashMap<CourseCode, Course>,
-        id_generator: &mut IdGenerator,
-    ) -> SubjectGraph {
+    fn new(subject: &str, courses: &[Course], id_generator: &mut IdGenerator) -> SubjectGraph {
         let mut ret = SubjectGraph {
             nodes: Vec::new(),
             subject: subject.to_string(),
         };
-        for (code, course) in restrictions
+        for course in courses
             .iter()
-            .filter(|(code, _)| code.subject() == subject)
+            .filter(|course| course.code().subject() == subject)
         {
+            let code = course.code();
             let node_index =
                 ret.insert_qualification(&Qualification::Course(code.clone()), id_generator);
-            if let Some(prereq_tree) = course.prerequisites() {
-                ret.insert(node_index, prereq_tree, id_generator);
+            if let Some(prereq_tree) = course.requirements() {
+                // the graph draws a course once, however it may be taken
+                let prereq_tree =
+                    prereq_tree.map_qualifications(|qualification| match qualification.course() {
+                        Some(code) => Qualification::Course(code.clone()),
+                        None => qualification.clone(),
+                    });
+                ret.insert(node_index, &prereq_tree, id_generator);
             }
         }
         ret
@@ -208,11 +291,11 @@ impl SubjectGraph {
                 .all(|o| !o.dependencies.contains(&node_index))
     }
 
-    fn graphviz_cluster(&self, string: &mut String) {
+    fn graphviz_cluster(&self, string: &mut String, subjects: &Subjects, exams: &Exams) {
         let abbreviation = self.subject.to_string();
         writeln!(string, "subgraph cluster_{} {{", abbreviation).unwrap();
         writeln!(string, "packmode=\"graph\"").unwrap();
-        writeln!(string, "label=\"{}\"", self.subject).unwrap();
+        writeln!(string, "label=\"{}\"", subjects.display(&self.subject)).unwrap();
 
         let color = "808000";
         writeln!(string, "bgcolor=\"#{}\"", color).unwrap();
@@ -220,9 +303,46 @@ impl SubjectGraph {
         for node in self.nodes.iter() {
             match node.kind() {
                 NodeKind::Qualification(Qualification::ExamScore(q)) => {
-                    writeln!(string, "{} [label=\"{}\",shape=box,color=blue]", node.id, q).unwrap();
+                    writeln!(
+                        string,
+                        "{} [label={:?},shape=box,color=blue]",
+                        node.id,
+                        exams.describe(q)
+                    )
+                    .unwrap();
                 }
-                NodeKind::Qualification(Qualification::Course(code)) => {
+                NodeKind::Qualification(
+                    qualification @ (Qualification::Program(_) | Qualification::Concentration(_)),
+                ) => {
+                    writeln!(
+                        string,
+                        "{} [label={:?},shape=box,color=purple]",
+                        node.id,
+                        qualification.to_string()
+                    )
+                    .unwrap();
+                }
+                NodeKind::Qualification(Qualification::InstructorPermission) => {
+                    writeln!(
+                        string,
+                        "{} [label=\"instructor permission\",shape=box,style=dashed,color=red]",
+                        node.id
+                    )
+                    .unwrap();
+                }
+                NodeKind::Qualification(Qualification::SemesterLevel(r)) => {
+                    writeln!(
+                        string,
+                        "{} [label=\"semester {}\",shape=box,color=green]",
+                        node.id, r
+                    )
+                    .unwrap();
+                }
+                NodeKind::Qualification(
+                    Qualification::Course(code)
+                    | Qualification::Concurrent(code)
+                    | Qualification::CourseGrade { code, .. },
+                ) => {
                     writeln!(string, "{} [label=\"\",shape=box, fixedsize=true, width=1.4, height=0.6, class=\"qual_{}\"]", node.id, code).unwrap();
                 }
                 NodeKind::Operator(conjunctive) => {
@@ -347,12 +467,11 @@ fn integer_square_root(n: u64) -> u64 {
         return 0;
     }
     let mut x = n;
-    let result = loop {
+    loop {
         let x_prev = x;
         x = (x + n / x) / 2;
         if x_prev == x || x_prev + 1 == x {
             break x_prev;
         }
-    };
-    result
+    }
 }
@@ -0,0 +1,789 @@
+use std::borrow::Cow;
+
+/// Decodes character references: numeric ones like `&#8217;` and `&#x2019;`, and
+/// the named ones of HTML 4. Unknown or malformed references are left as they are.
+pub fn decode_entities(string: &str) -> Cow<'_, str> {
+    if !string.contains('&') {
+        return Cow::Borrowed(string);
+    }
+    let mut ret = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(start) = rest.find('&') {
+        ret.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 32)
+            .and_then(|end| Some((entity(&rest[1..=end])?, end + 2)));
+        match decoded {
+            Some((c, length)) => {
+                ret.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                ret.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    ret.push_str(rest);
+    Cow::Owned(ret)
+}
+
+/// The character a reference like `#8217` or `rsquo` (without `&` and `;`) stands for
+fn entity(name: &str) -> Option<char> {
+    let number = match name.strip_prefix('#') {
+        Some(number) => number,
+        None => {
+            let i = ENTITIES
+                .binary_search_by(|(entity, _)| entity.cmp(&name))
+                .ok()?;
+            return Some(ENTITIES[i].1);
+        }
+    };
+    let code = match number.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => number.parse().ok()?,
+    };
+    char::from_u32(code)
+}
+
+/// Composes letters followed by combining accents into the precomposed letters
+/// of Latin-1 and Latin Extended-A, which is as much of NFC as course text needs:
+/// `Cafe\u{301}` and `Café` then match.
+pub fn compose(string: &str) -> Cow<'_, str> {
+    if string
+        .chars()
+        .all(|c| !('\u{300}'..='\u{36f}').contains(&c))
+    {
+        return Cow::Borrowed(string);
+    }
+    let mut ret = String::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
+    while let Some(mut c) = chars.next() {
+        while let Some(&mark) = chars.peek() {
+            match COMPOSITIONS
+                .iter()
+                .find(|&&(base, combining, _)| base == c && combining == mark)
+            {
+                Some(&(_, _, composed)) => {
+                    c = composed;
+                    chars.next();
+                }
+                None => break,
+            }
+        }
+        ret.push(c);
+    }
+    Cow::Owned(ret)
+}
+
+/// A node of a parsed HTML fragment, with character references in text decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    /// Lowercase
+    pub name: String,
+    attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.attribute("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+
+    pub fn text(&self) -> String {
+        text(&self.children)
+    }
+}
+
+/// Elements that never have children or end tags
+const VOID: &[&str] = &[
+    "area", "br", "col", "hr", "img", "input", "link", "meta", "wbr",
+];
+
+/// Parses an HTML fragment as leniently as browsers do, for the markup the catalog
+/// uses: end tags close the nearest matching open element, stray ones are ignored,
+/// and whatever is still open at the end is closed. Comments are dropped.
+pub fn parse(fragment: &str) -> Vec<Node> {
+    // the elements being built, the innermost last; the root's name is empty
+    let mut open = vec![Element {
+        name: String::new(),
+        attributes: Vec::new(),
+        children: Vec::new(),
+    }];
+    let mut rest = fragment;
+    while !rest.is_empty() {
+        let tag = rest
+            .find('<')
+            .filter(|&start| {
+                rest[start + 1..]
+                    .starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!')
+            })
+            .unwrap_or(rest.len());
+        if tag > 0 {
+            let text = decode_entities(&rest[..tag]).into_owned();
+            open.last_mut().unwrap().children.push(Node::Text(text));
+            rest = &rest[tag..];
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => {
+                let text = decode_entities(rest).into_owned();
+                open.last_mut().unwrap().children.push(Node::Text(text));
+                break;
+            }
+        };
+        let inner = &rest[1..end];
+        rest = &rest[end + 1..];
+        if let Some(name) = inner.strip_prefix('/') {
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(depth) = open.iter().rposition(|element| element.name == name) {
+                while open.len() > depth.max(1) {
+                    let element = open.pop().unwrap();
+                    open.last_mut()
+                        .unwrap()
+                        .children
+                        .push(Node::Element(element));
+                }
+            }
+            continue;
+        }
+        if inner.starts_with('!') {
+            continue;
+        }
+        let self_closing = inner.ends_with('/');
+        let inner = inner.trim_end_matches('/');
+        let name_end = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+        let element = Element {
+            name: inner[..name_end].to_ascii_lowercase(),
+            attributes: attributes(&inner[name_end..]),
+            children: Vec::new(),
+        };
+        if self_closing || VOID.contains(&element.name.as_str()) {
+            open.last_mut()
+                .unwrap()
+                .children
+                .push(Node::Element(element));
+        } else {
+            open.push(element);
+        }
+    }
+    while open.len() > 1 {
+        let element = open.pop().unwrap();
+        open.last_mut()
+            .unwrap()
+            .children
+            .push(Node::Element(element));
+    }
+    open.pop().unwrap().children
+}
+
+/// Like `class="a b" id=x disabled`, with names lowercased and values decoded
+fn attributes(mut string: &str) -> Vec<(String, String)> {
+    let mut ret = Vec::new();
+    loop {
+        string = string.trim_start();
+        let name_end = string
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(string.len());
+        if name_end == 0 {
+            return ret;
+        }
+        let name = string[..name_end].to_ascii_lowercase();
+        string = string[name_end..].trim_start();
+        let value = match string.strip_prefix('=') {
+            None => "",
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, after) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value
+                            .find(|c: char| c.is_whitespace())
+                            .unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                string = after;
+                value
+            }
+        };
+        ret.push((name, decode_entities(value).into_owned()));
+    }
+}
+
+/// Every element in `nodes` and below, in document order
+pub fn elements(nodes: &[Node]) -> Vec<&Element> {
+    let mut ret = Vec::new();
+    for node in nodes {
+        if let Node::Element(element) = node {
+            ret.push(element);
+            ret.extend(elements(&element.children));
+        }
+    }
+    ret
+}
+
+/// The elements named `name` with the class `class`, in document order
+pub fn select<'a>(nodes: &'a [Node], name: &str, class: &str) -> Vec<&'a Element> {
+    elements(nodes)
+        .into_iter()
+        .filter(|element| element.name == name && element.has_class(class))
+        .collect()
+}
+
+/// All the text in `nodes`, without any markup
+pub fn text(nodes: &[Node]) -> String {
+    let mut ret = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(text) => ret.push_str(text),
+            Node::Element(element) => ret.push_str(&element.text()),
+        }
+    }
+    ret
+}
+
+/// Markup for `nodes` in one canonical form, for matching against patterns: tags are
+/// lowercase with only their `class` attribute, void elements are written like `<br/>`,
+/// whitespace between top level elements is dropped, and only `&`, `<`, and `>` are escaped.
+pub fn to_html(nodes: &[Node]) -> String {
+    let mut ret = String::new();
+    write_html(nodes, true, &mut ret);
+    ret
+}
+
+/// `text` with `&`, `<`, and `>` escaped, to go in markup
+pub fn escape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+fn write_html(nodes: &[Node], top: bool, ret: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) if top && text.trim().is_empty() => {}
+            Node::Text(text) => ret.push_str(&escape(text)),
+            Node::Element(element) => {
+                ret.push('<');
+                ret.push_str(&element.name);
+                if let Some(class) = element.attribute("class") {
+                    ret.push_str(&format!(" class=\"{class}\""));
+                }
+                if VOID.contains(&element.name.as_str()) && element.children.is_empty() {
+                    ret.push_str("/>");
+                    continue;
+                }
+                ret.push('>');
+                write_html(&element.children, false, ret);
+                ret.push_str(&format!("</{}>", element.name));
+            }
+        }
+    }
+}
+
+/// HTML 4's named character references, sorted by name
+static ENTITIES: &[(&str, char)] = &[
+    ("AElig", '\u{c6}'),
+    ("Aacute", '\u{c1}'),
+    ("Acirc", '\u{c2}'),
+    ("Agrave", '\u{c0}'),
+    ("Alpha", '\u{391}'),
+    ("Aring", '\u{c5}'),
+    ("Atilde", '\u{c3}'),
+    ("Auml", '\u{c4}'),
+    ("Beta", '\u{392}'),
+    ("Ccedil", '\u{c7}'),
+    ("Chi", '\u{3a7}'),
+    ("Dagger", '\u{2021}'),
+    ("Delta", '\u{394}'),
+    ("ETH", '\u{d0}'),
+    ("Eacute", '\u{c9}'),
+    ("Ecirc", '\u{ca}'),
+    ("Egrave", '\u{c8}'),
+    ("Epsilon", '\u{395}'),
+    ("Eta", '\u{397}'),
+    ("Euml", '\u{cb}'),
+    ("Gamma", '\u{393}'),
+    ("Iacute", '\u{cd}'),
+    ("Icirc", '\u{ce}'),
+    ("Igrave", '\u{cc}'),
+    ("Iota", '\u{399}'),
+    ("Iuml", '\u{cf}'),
+    ("Kappa", '\u{39a}'),
+    ("Lambda", '\u{39b}'),
+    ("Mu", '\u{39c}'),
+    ("Ntilde", '\u{d1}'),
+    ("Nu", '\u{39d}'),
+    ("OElig", '\u{152}'),
+    ("Oacute", '\u{d3}'),
+    ("Ocirc", '\u{d4}'),
+    ("Ograve", '\u{d2}'),
+    ("Omega", '\u{3a9}'),
+    ("Omicron", '\u{39f}'),
+    ("Oslash", '\u{d8}'),
+    ("Otilde", '\u{d5}'),
+    ("Ouml", '\u{d6}'),
+    ("Phi", '\u{3a6}'),
+    ("Pi", '\u{3a0}'),
+    ("Prime", '\u{2033}'),
+    ("Psi", '\u{3a8}'),
+    ("Rho", '\u{3a1}'),
+    ("Scaron", '\u{160}'),
+    ("Sigma", '\u{3a3}'),
+    ("THORN", '\u{de}'),
+    ("Tau", '\u{3a4}'),
+    ("Theta", '\u{398}'),
+    ("Uacute", '\u{da}'),
+    ("Ucirc", '\u{db}'),
+    ("Ugrave", '\u{d9}'),
+    ("Upsilon", '\u{3a5}'),
+    ("Uuml", '\u{dc}'),
+    ("Xi", '\u{39e}'),
+    ("Yacute", '\u{dd}'),
+    ("Yuml", '\u{178}'),
+    ("Zeta", '\u{396}'),
+    ("aacute", '\u{e1}'),
+    ("acirc", '\u{e2}'),
+    ("acute", '\u{b4}'),
+    ("aelig", '\u{e6}'),
+    ("agrave", '\u{e0}'),
+    ("alefsym", '\u{2135}'),
+    ("alpha", '\u{3b1}'),
+    ("amp", '\u{26}'),
+    ("and", '\u{2227}'),
+    ("ang", '\u{2220}'),
+    ("aring", '\u{e5}'),
+    ("asymp", '\u{2248}'),
+    ("atilde", '\u{e3}'),
+    ("auml", '\u{e4}'),
+    ("bdquo", '\u{201e}'),
+    ("beta", '\u{3b2}'),
+    ("brvbar", '\u{a6}'),
+    ("bull", '\u{2022}'),
+    ("cap", '\u{2229}'),
+    ("ccedil", '\u{e7}'),
+    ("cedil", '\u{b8}'),
+    ("cent", '\u{a2}'),
+    ("chi", '\u{3c7}'),
+    ("circ", '\u{2c6}'),
+    ("clubs", '\u{2663}'),
+    ("cong", '\u{2245}'),
+    ("copy", '\u{a9}'),
+    ("crarr", '\u{21b5}'),
+    ("cup", '\u{222a}'),
+    ("curren", '\u{a4}'),
+    ("dArr", '\u{21d3}'),
+    ("dagger", '\u{2020}'),
+    ("darr", '\u{2193}'),
+    ("deg", '\u{b0}'),
+    ("delta", '\u{3b4}'),
+    ("diams", '\u{2666}'),
+    ("divide", '\u{f7}'),
+    ("eacute", '\u{e9}'),
+    ("ecirc", '\u{ea}'),
+    ("egrave", '\u{e8}'),
+    ("empty", '\u{2205}'),
+    ("emsp", '\u{2003}'),
+    ("ensp", '\u{2002}'),
+    ("epsilon", '\u{3b5}'),
+    ("equiv", '\u{2261}'),
+    ("eta", '\u{3b7}'),
+    ("eth", '\u{f0}'),
+    ("euml", '\u{eb}'),
+    ("euro", '\u{20ac}'),
+    ("exist", '\u{2203}'),
+    ("fnof", '\u{192}'),
+    ("forall", '\u{2200}'),
+    ("frac12", '\u{bd}'),
+    ("frac14", '\u{bc}'),
+    ("frac34", '\u{be}'),
+    ("frasl", '\u{2044}'),
+    ("gamma", '\u{3b3}'),
+    ("ge", '\u{2265}'),
+    ("gt", '\u{3e}'),
+    ("hArr", '\u{21d4}'),
+    ("harr", '\u{2194}'),
+    ("hearts", '\u{2665}'),
+    ("hellip", '\u{2026}'),
+    ("iacute", '\u{ed}'),
+    ("icirc", '\u{ee}'),
+    ("iexcl", '\u{a1}'),
+    ("igrave", '\u{ec}'),
+    ("image", '\u{2111}'),
+    ("infin", '\u{221e}'),
+    ("int", '\u{222b}'),
+    ("iota", '\u{3b9}'),
+    ("iquest", '\u{bf}'),
+    ("isin", '\u{2208}'),
+    ("iuml", '\u{ef}'),
+    ("kappa", '\u{3ba}'),
+    ("lArr", '\u{21d0}'),
+    ("lambda", '\u{3bb}'),
+    ("lang", '\u{2329}'),
+    ("laquo", '\u{ab}'),
+    ("larr", '\u{2190}'),
+    ("lceil", '\u{2308}'),
+    ("ldquo", '\u{201c}'),
+    ("le", '\u{2264}'),
+    ("lfloor", '\u{230a}'),
+    ("lowast", '\u{2217}'),
+    ("loz", '\u{25ca}'),
+    ("lrm", '\u{200e}'),
+    ("lsaquo", '\u{2039}'),
+    ("lsquo", '\u{2018}'),
+    ("lt", '\u{3c}'),
+    ("macr", '\u{af}'),
+    ("mdash", '\u{2014}'),
+    ("micro", '\u{b5}'),
+    ("middot", '\u{b7}'),
+    ("minus", '\u{2212}'),
+    ("mu", '\u{3bc}'),
+    ("nabla", '\u{2207}'),
+    ("nbsp", '\u{a0}'),
+    ("ndash", '\u{2013}'),
+    ("ne", '\u{2260}'),
+    ("ni", '\u{220b}'),
+    ("not", '\u{ac}'),
+    ("notin", '\u{2209}'),
+    ("nsub", '\u{2284}'),
+    ("ntilde", '\u{f1}'),
+    ("nu", '\u{3bd}'),
+    ("oacute", '\u{f3}'),
+    ("ocirc", '\u{f4}'),
+    ("oelig", '\u{153}'),
+    ("ograve", '\u{f2}'),
+    ("oline", '\u{203e}'),
+    ("omega", '\u{3c9}'),
+    ("omicron", '\u{3bf}'),
+    ("oplus", '\u{2295}'),
+    ("or", '\u{2228}'),
+    ("ordf", '\u{aa}'),
+    ("ordm", '\u{ba}'),
+    ("oslash", '\u{f8}'),
+    ("otilde", '\u{f5}'),
+    ("otimes", '\u{2297}'),
+    ("ouml", '\u{f6}'),
+    ("para", '\u{b6}'),
+    ("part", '\u{2202}'),
+    ("permil", '\u{2030}'),
+    ("perp", '\u{22a5}'),
+    ("phi", '\u{3c6}'),
+    ("pi", '\u{3c0}'),
+    ("piv", '\u{3d6}'),
+    ("plusmn", '\u{b1}'),
+    ("pound", '\u{a3}'),
+    ("prime", '\u{2032}'),
+    ("prod", '\u{220f}'),
+    ("prop", '\u{221d}'),
+    ("psi", '\u{3c8}'),
+    ("quot", '\u{22}'),
+    ("rArr", '\u{21d2}'),
+    ("radic", '\u{221a}'),
+    ("rang", '\u{232a}'),
+    ("raquo", '\u{bb}'),
+    ("rarr", '\u{2192}'),
+    ("rceil", '\u{2309}'),
+    ("rdquo", '\u{201d}'),
+    ("real", '\u{211c}'),
+    ("reg", '\u{ae}'),
+    ("rfloor", '\u{230b}'),
+    ("rho", '\u{3c1}'),
+    ("rlm", '\u{200f}'),
+    ("rsaquo", '\u{203a}'),
+    ("rsquo", '\u{2019}'),
+    ("sbquo", '\u{201a}'),
+    ("scaron", '\u{161}'),
+    ("sdot", '\u{22c5}'),
+    ("sect", '\u{a7}'),
+    ("shy", '\u{ad}'),
+    ("sigma", '\u{3c3}'),
+    ("sigmaf", '\u{3c2}'),
+    ("sim", '\u{223c}'),
+    ("spades", '\u{2660}'),
+    ("sub", '\u{2282}'),
+    ("sube", '\u{2286}'),
+    ("sum", '\u{2211}'),
+    ("sup", '\u{2283}'),
+    ("sup1", '\u{b9}'),
+    ("sup2", '\u{b2}'),
+    ("sup3", '\u{b3}'),
+    ("supe", '\u{2287}'),
+    ("szlig", '\u{df}'),
+    ("tau", '\u{3c4}'),
+    ("there4", '\u{2234}'),
+    ("theta", '\u{3b8}'),
+    ("thetasym", '\u{3d1}'),
+    ("thinsp", '\u{2009}'),
+    ("thorn", '\u{fe}'),
+    ("tilde", '\u{2dc}'),
+    ("times", '\u{d7}'),
+    ("trade", '\u{2122}'),
+    ("uArr", '\u{21d1}'),
+    ("uacute", '\u{fa}'),
+    ("uarr", '\u{2191}'),
+    ("ucirc", '\u{fb}'),
+    ("ugrave", '\u{f9}'),
+    ("uml", '\u{a8}'),
+    ("upsih", '\u{3d2}'),
+    ("upsilon", '\u{3c5}'),
+    ("uuml", '\u{fc}'),
+    ("weierp", '\u{2118}'),
+    ("xi", '\u{3be}'),
+    ("yacute", '\u{fd}'),
+    ("yen", '\u{a5}'),
+    ("yuml", '\u{ff}'),
+    ("zeta", '\u{3b6}'),
+    ("zwj", '\u{200d}'),
+    ("zwnj", '\u{200c}'),
+];
+
+/// Base letter, combining mark, and the precomposed letter they make
+static COMPOSITIONS: &[(char, char, char)] = &[
+    ('A', '\u{300}', 'À'),
+    ('A', '\u{301}', 'Á'),
+    ('A', '\u{302}', 'Â'),
+    ('A', '\u{303}', 'Ã'),
+    ('A', '\u{308}', 'Ä'),
+    ('A', '\u{30a}', 'Å'),
+    ('C', '\u{327}', 'Ç'),
+    ('E', '\u{300}', 'È'),
+    ('E', '\u{301}', 'É'),
+    ('E', '\u{302}', 'Ê'),
+    ('E', '\u{308}', 'Ë'),
+    ('I', '\u{300}', 'Ì'),
+    ('I', '\u{301}', 'Í'),
+    ('I', '\u{302}', 'Î'),
+    ('I', '\u{308}', 'Ï'),
+    ('N', '\u{303}', 'Ñ'),
+    ('O', '\u{300}', 'Ò'),
+    ('O', '\u{301}', 'Ó'),
+    ('O', '\u{302}', 'Ô'),
+    ('O', '\u{303}', 'Õ'),
+    ('O', '\u{308}', 'Ö'),
+    ('U', '\u{300}', 'Ù'),
+    ('U', '\u{301}', 'Ú'),
+    ('U', '\u{302}', 'Û'),
+    ('U', '\u{308}', 'Ü'),
+    ('Y', '\u{301}', 'Ý'),
+    ('a', '\u{300}', 'à'),
+    ('a', '\u{301}', 'á'),
+    ('a', '\u{302}', 'â'),
+    ('a', '\u{303}', 'ã'),
+    ('a', '\u{308}', 'ä'),
+    ('a', '\u{30a}', 'å'),
+    ('c', '\u{327}', 'ç'),
+    ('e', '\u{300}', 'è'),
+    ('e', '\u{301}', 'é'),
+    ('e', '\u{302}', 'ê'),
+    ('e', '\u{308}', 'ë'),
+    ('i', '\u{300}', 'ì'),
+    ('i', '\u{301}', 'í'),
+    ('i', '\u{302}', 'î'),
+    ('i', '\u{308}', 'ï'),
+    ('n', '\u{303}', 'ñ'),
+    ('o', '\u{300}', 'ò'),
+    ('o', '\u{301}', 'ó'),
+    ('o', '\u{302}', 'ô'),
+    ('o', '\u{303}', 'õ'),
+    ('o', '\u{308}', 'ö'),
+    ('u', '\u{300}', 'ù'),
+    ('u', '\u{301}', 'ú'),
+    ('u', '\u{302}', 'û'),
+    ('u', '\u{308}', 'ü'),
+    ('y', '\u{301}', 'ý'),
+    ('y', '\u{308}', 'ÿ'),
+    ('A', '\u{304}', 'Ā'),
+    ('a', '\u{304}', 'ā'),
+    ('A', '\u{306}', 'Ă'),
+    ('a', '\u{306}', 'ă'),
+    ('A', '\u{328}', 'Ą'),
+    ('a', '\u{328}', 'ą'),
+    ('C', '\u{301}', 'Ć'),
+    ('c', '\u{301}', 'ć'),
+    ('C', '\u{302}', 'Ĉ'),
+    ('c', '\u{302}', 'ĉ'),
+    ('C', '\u{307}', 'Ċ'),
+    ('c', '\u{307}', 'ċ'),
+    ('C', '\u{30c}', 'Č'),
+    ('c', '\u{30c}', 'č'),
+    ('D', '\u{30c}', 'Ď'),
+    ('d', '\u{30c}', 'ď'),
+    ('E', '\u{304}', 'Ē'),
+    ('e', '\u{304}', 'ē'),
+    ('E', '\u{306}', 'Ĕ'),
+    ('e', '\u{306}', 'ĕ'),
+    ('E', '\u{307}', 'Ė'),
+    ('e', '\u{307}', 'ė'),
+    ('E', '\u{328}', 'Ę'),
+    ('e', '\u{328}', 'ę'),
+    ('E', '\u{30c}', 'Ě'),
+    ('e', '\u{30c}', 'ě'),
+    ('G', '\u{302}', 'Ĝ'),
+    ('g', '\u{302}', 'ĝ'),
+    ('G', '\u{306}', 'Ğ'),
+    ('g', '\u{306}', 'ğ'),
+    ('G', '\u{307}', 'Ġ'),
+    ('g', '\u{307}', 'ġ'),
+    ('G', '\u{327}', 'Ģ'),
+    ('g', '\u{327}', 'ģ'),
+    ('H', '\u{302}', 'Ĥ'),
+    ('h', '\u{302}', 'ĥ'),
+    ('I', '\u{303}', 'Ĩ'),
+    ('i', '\u{303}', 'ĩ'),
+    ('I', '\u{304}', 'Ī'),
+    ('i', '\u{304}', 'ī'),
+    ('I', '\u{306}', 'Ĭ'),
+    ('i', '\u{306}', 'ĭ'),
+    ('I', '\u{328}', 'Į'),
+    ('i', '\u{328}', 'į'),
+    ('I', '\u{307}', 'İ'),
+    ('J', '\u{302}', 'Ĵ'),
+    ('j', '\u{302}', 'ĵ'),
+    ('K', '\u{327}', 'Ķ'),
+    ('k', '\u{327}', 'ķ'),
+    ('L', '\u{301}', 'Ĺ'),
+    ('l', '\u{301}', 'ĺ'),
+    ('L', '\u{327}', 'Ļ'),
+    ('l', '\u{327}', 'ļ'),
+    ('L', '\u{30c}', 'Ľ'),
+    ('l', '\u{30c}', 'ľ'),
+    ('N', '\u{301}', 'Ń'),
+    ('n', '\u{301}', 'ń'),
+    ('N', '\u{327}', 'Ņ'),
+    ('n', '\u{327}', 'ņ'),
+    ('N', '\u{30c}', 'Ň'),
+    ('n', '\u{30c}', 'ň'),
+    ('O', '\u{304}', 'Ō'),
+    ('o', '\u{304}', 'ō'),
+    ('O', '\u{306}', 'Ŏ'),
+    ('o', '\u{306}', 'ŏ'),
+    ('O', '\u{30b}', 'Ő'),
+    ('o', '\u{30b}', 'ő'),
+    ('R', '\u{301}', 'Ŕ'),
+    ('r', '\u{301}', 'ŕ'),
+    ('R', '\u{327}', 'Ŗ'),
+    ('r', '\u{327}', 'ŗ'),
+    ('R', '\u{30c}', 'Ř'),
+    ('r', '\u{30c}', 'ř'),
+    ('S', '\u{301}', 'Ś'),
+    ('s', '\u{301}', 'ś'),
+    ('S', '\u{302}', 'Ŝ'),
+    ('s', '\u{302}', 'ŝ'),
+    ('S', '\u{327}', 'Ş'),
+    ('s', '\u{327}', 'ş'),
+    ('S', '\u{30c}', 'Š'),
+    ('s', '\u{30c}', 'š'),
+    ('T', '\u{327}', 'Ţ'),
+    ('t', '\u{327}', 'ţ'),
+    ('T', '\u{30c}', 'Ť'),
+    ('t', '\u{30c}', 'ť'),
+    ('U', '\u{303}', 'Ũ'),
+    ('u', '\u{303}', 'ũ'),
+    ('U', '\u{304}', 'Ū'),
+    ('u', '\u{304}', 'ū'),
+    ('U', '\u{306}', 'Ŭ'),
+    ('u', '\u{306}', 'ŭ'),
+    ('U', '\u{30a}', 'Ů'),
+    ('u', '\u{30a}', 'ů'),
+    ('U', '\u{30b}', 'Ű'),
+    ('u', '\u{30b}', 'ű'),
+    ('U', '\u{328}', 'Ų'),
+    ('u', '\u{328}', 'ų'),
+    ('W', '\u{302}', 'Ŵ'),
+    ('w', '\u{302}', 'ŵ'),
+    ('Y', '\u{302}', 'Ŷ'),
+    ('y', '\u{302}', 'ŷ'),
+    ('Y', '\u{308}', 'Ÿ'),
+    ('Z', '\u{301}', 'Ź'),
+    ('z', '\u{301}', 'ź'),
+    ('Z', '\u{307}', 'Ż'),
+    ('z', '\u{307}', 'ż'),
+    ('Z', '\u{30c}', 'Ž'),
+    ('z', '\u{30c}', 'ž'),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{compose, decode_entities, parse, select, text, to_html, ENTITIES};
+
+    #[test]
+    fn entities() {
+        assert!(ENTITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(
+            decode_entities("Students&#8217; work in the Renaissance&nbsp;&amp; Baroque"),
+            "Students\u{2019} work in the Renaissance\u{a0}& Baroque"
+        );
+        assert_eq!(
+            decode_entities("&ldquo;Modern&rdquo; art &#x2014; 1900&ndash;1950"),
+            "\u{201c}Modern\u{201d} art \u{2014} 1900\u{2013}1950"
+        );
+        assert_eq!(
+            decode_entities("AT&T & R&D; &bogus; &#xZZ;"),
+            "AT&T & R&D; &bogus; &#xZZ;"
+        );
+    }
+
+    #[test]
+    fn composition() {
+        assert_eq!(
+            compose("Cafe\u{301} and Franc\u{327}ais"),
+            "Café and Français"
+        );
+        assert_eq!(compose("Dvor\u{30c}a\u{301}k"), "Dvořák");
+        assert_eq!(compose("q\u{301}"), "q\u{301}");
+    }
+
+    #[test]
+    fn parsing() {
+        let fragment = r#"<P CLASS='prereq extra' id=x>Prerequisite: CSCI 0150<br>
+            <sup>*</sup> May be &amp; taken</p>
+            <!-- note --><span class="seats_max">40</span></div>stray"#;
+        let nodes = parse(fragment);
+        let prereq = select(&nodes, "p", "prereq");
+        assert_eq!(prereq.len(), 1);
+        assert_eq!(prereq[0].attribute("id"), Some("x"));
+        assert_eq!(select(&nodes, "span", "seats_max")[0].text(), "40");
+        assert!(text(&nodes).starts_with("Prerequisite: CSCI 0150\n"));
+        assert!(text(&nodes).ends_with("40stray"));
+        assert_eq!(
+            to_html(&parse(
+                r#"<p class="a" title="t">A&amp;B<BR> <b>C</b></p> <ul><li>x</ul>"#
+            )),
+            r#"<p class="a">A&amp;B<br/> <b>C</b></p><ul><li>x</li></ul>"#
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::process::Course;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// An instructor's name split into family name and given names, lowercased and
+/// without punctuation, so that "Doe, J." and "John Doe" can be compared.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Name {
+    family: String,
+    given: Vec<String>,
+}
+
+impl Name {
+    pub fn new(name: &str) -> Name {
+        let words = |string: &str| -> Vec<String> {
+            string
+                .split(|c: char| c.is_whitespace() || c == '.')
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect()
+        };
+        match name.split_once(',') {
+            Some((family, given)) => Name {
+                family: words(family).join(" "),
+                given: words(given),
+            },
+            None => {
+                let mut given = words(name);
+                let family = given.pop().unwrap_or_default();
+                Name { family, given }
+            }
+        }
+    }
+
+    /// The family name and first initial, which "Doe, J." and "John Doe" share
+    pub fn key(&self) -> String {
+        match self.given.first().and_then(|given| given.chars().next()) {
+            Some(initial) => format!("{} {initial}", self.family),
+            None => self.family.clone(),
+        }
+    }
+
+    /// Family names have to agree up to a typo, and each given name in `self` has to be
+    /// a prefix of the corresponding one in `other`, so initials match full names.
+    pub fn matches(&self, other: &Name) -> bool {
+        let family_matches = self.family == other.family
+            || (self.family.len() >= 4 && edit_distance(&self.family, &other.family) <= 1);
+        family_matches
+            && self
+                .given
+                .iter()
+                .zip(other.given.iter())
+                .all(|(a, b)| b.starts_with(a.as_str()))
+    }
+}
+
+/// The same for every offering an instructor teaches: the username of their email
+/// address if the details have one, otherwise their name's `Name::key`
+pub fn identifier(name: &str, email: Option<&str>) -> String {
+    let username = email
+        .and_then(|email| email.split('@').next())
+        .filter(|username| !username.is_empty());
+    match username {
+        Some(username) => format!("email:{}", username.to_lowercase()),
+        None => format!("name:{}", Name::new(name).key()),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + (ca != cb) as usize);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Every offering taught by someone matching `query`, then the distinct courses,
+/// with their annotations as numbered notes.
+pub fn report(courses: &[Course], query: &str) -> String {
+    let query = Name::new(query);
+    let mut taught = Vec::new();
+    for course in courses {
+        for offering in course.offerings() {
+            let instructors = offering.instructors();
+            if let Some(name) = instructors
+                .iter()
+                .find(|name| query.matches(&Name::new(name)))
+            {
+                taught.push((offering.date(), course.code(), offering, name));
+            }
+        }
+    }
+    taught.sort_by(|a, b| (a.0, a.1, a.2.section()).cmp(&(b.0, b.1, b.2.section())));
+    let mut ret = String::new();
+    for (srcdb, code, offering, name) in taught.iter() {
+        let term = offering
+            .term()
+            .map_or(srcdb.to_string(), |term| term.to_string());
+        let enrollment = offering
+            .enrollment()
+            .map_or(String::from("?"), |e| e.to_string());
+        writeln!(
+            ret,
+            "{term} {code} S{:02} enrollment {enrollment} ({name})",
+            offering.section()
+        )
+        .unwrap();
+    }
+    let distinct: BTreeSet<_> = taught.iter().map(|(_, code, _, _)| code).collect();
+    writeln!(
+        ret,
+        "{} offerings of {} courses:",
+        taught.len(),
+        distinct.len()
+    )
+    .unwrap();
+    let mut footnotes = Vec::new();
+    for code in distinct {
+        let annotation = courses
+            .iter()
+            .find(|course| course.code() == *code)
+            .and_then(Course::annotation);
+        match annotation {
+            Some(annotation) => {
+                footnotes.push(annotation);
+                writeln!(ret, "{code} [{}]", footnotes.len()).unwrap();
+            }
+            None => writeln!(ret, "{code}").unwrap(),
+        }
+    }
+    for (i, annotation) in footnotes.iter().enumerate() {
+        writeln!(ret, "[{}] {annotation}", i + 1).unwrap();
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Name;
+
+    #[test]
+    fn matching() {
+        let query = Name::new("Smith, J.");
+        assert!(query.matches(&Name::new("John Smith")));
+        assert!(query.matches(&Name::new("Smith, Jane")));
+        assert!(query.matches(&Name::new("John Smyth")));
+        assert!(!query.matches(&Name::new("Alice Smith")));
+        assert!(!query.matches(&Name::new("John Doe")));
+        assert!(Name::new("doe").matches(&Name::new("Jane Doe")));
+        assert_eq!(Name::new("Doe, J.").key(), Name::new("John Doe").key());
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// An advisory `.lock` file in a directory that a run writes to, so that two
+/// runs don't interleave their output. Removed when dropped.
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Fails if another run holds the lock, unless `force`, which takes it over
+    /// (for lock files left behind by a run that crashed).
+    pub fn acquire<P: AsRef<Path>>(directory: P, force: bool) -> io::Result<Lock> {
+        fs::create_dir_all(&directory)?;
+        let path = directory.as_ref().join(".lock");
+        let file = fs::File::options()
+            .create_new(!force)
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&path);
+        let mut file = match file {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let owner = fs::read_to_string(&path).unwrap_or_default();
+                let message = format!(
+                    "another run is in progress in {} (process {}); pass --force if it is not",
+                    directory.as_ref().display(),
+                    owner.trim(),
+                );
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, message));
+            }
+            file => file?,
+        };
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Lock { path })
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lock;
+
+    #[test]
+    fn exclusive() {
+        let directory = std::env::temp_dir().join(format!("cab-lock-{}", std::process::id()));
+        let lock = Lock::acquire(&directory, false).unwrap();
+        assert!(Lock::acquire(&directory, false).is_err());
+        drop(lock);
+        let lock = Lock::acquire(&directory, false).unwrap();
+        let forced = Lock::acquire(&directory, true).unwrap();
+        std::mem::forget(lock);
+        drop(forced);
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -6,12 +7,13 @@ use std::fmt;
 use std::hash::Hash;
 use std::ops::BitAnd;
 use std::ops::BitOr;
+use std::str::FromStr;
 
 pub trait Symbol: Ord + Eq + Hash + Clone {
     fn cmp_rank(&self, other: &Self) -> Option<Ordering>;
 
     fn ge(&self, other: &Self) -> bool {
-        self.cmp_rank(other).map(Ordering::is_ge).unwrap_or(false)
+        self == other || self.cmp_rank(other).map(Ordering::is_ge).unwrap_or(false)
     }
 }
 
@@ -39,7 +41,7 @@ impl<S: Symbol> Sum<S> {
         self.inner.contains(symbol)
     }
 
-    fn difference<'a>(&'a self, other: &'a Sum<S>) -> impl Iterator<Item = &S> {
+    fn difference<'a>(&'a self, other: &'a Sum<S>) -> impl Iterator<Item = &'a S> {
         self.inner.difference(&other.inner)
     }
 
@@ -98,6 +100,11 @@ impl<S: Symbol> Product<S> {
     fn into_iter(self) -> impl Iterator<Item = Sum<S>> {
         self.0.into_iter()
     }
+
+    /// The disjunctions this product is the conjunction of
+    pub fn clauses(&self) -> impl Iterator<Item = Vec<&'_ S>> {
+        self.iter().map(|sum| sum.iter().collect())
+    }
 }
 
 impl<const N: usize, S: Symbol> From<[Sum<S>; N]> for Product<S> {
@@ -120,8 +127,7 @@ impl<'a, S: Symbol> BitOr for &'a Product<S> {
         Product(
             self.0
                 .iter()
-                .map(move |a| other.0.iter().map(move |b| a | b))
-                .flatten()
+                .flat_map(move |a| other.0.iter().map(move |b| a | b))
                 .collect(),
         )
     }
@@ -149,7 +155,7 @@ impl<S: Symbol> Products<S> {
 
     fn find_redundant(&self) -> Option<(S, usize, S)> {
         self.iter().find_map(|(lhs, product)| {
-            product.iter().enumerate().find_map(|(sum_index, ref sum)| {
+            product.iter().enumerate().find_map(|(sum_index, sum)| {
                 sum.iter()
                     .find(|&s| {
                         let sum = sum.without(s);
@@ -165,7 +171,7 @@ impl<S: Symbol> Products<S> {
             product
                 .iter()
                 .enumerate()
-                .find(|&(b, ref sum)| self.implies(&Sum::from([lhs.clone()]), sum, Some((&lhs, b))))
+                .find(|&(b, sum)| self.implies(&Sum::from([lhs.clone()]), sum, Some((lhs, b))))
                 .map(|(b, _)| (lhs.clone(), b))
         })
     }
@@ -181,9 +187,27 @@ impl<S: Symbol> Products<S> {
             self.products.get_mut(&a).unwrap().0.remove(b);
         }
 
+        self.normalize();
+    }
+
+    /// Simplifications that only look at one product at a time
+    fn normalize(&mut self) {
         for product in self.products.values_mut() {
+            for sum in product.0.iter_mut() {
+                // a ≥ b ⇒ a ∨ b === b
+                let symbols: Vec<_> = sum.iter().cloned().collect();
+                sum.inner
+                    .retain(|s| !symbols.iter().any(|t| s != t && Symbol::ge(s, t)));
+            }
             product.0.sort();
             product.0.dedup();
+            // (a ∨ b) ∧ a === a, and a ≥ b ⇒ b ∧ a === a
+            let sums = product.0.clone();
+            product.0.retain(|sum| {
+                !sums.iter().any(|other| {
+                    other != sum && other.iter().all(|t| sum.iter().any(|s| Symbol::ge(t, s)))
+                })
+            });
         }
     }
 
@@ -198,10 +222,9 @@ impl<S: Symbol> Products<S> {
         let mut seen = HashSet::from([lhs.clone()]);
         let mut heap = Vec::from([lhs.clone()]);
         while let Some(lhs) = heap.pop() {
-            let is_subset = lhs.difference(&rhs).all(|l| {
-                rhs.iter()
-                    .any(|r| l.cmp_rank(r).map(Ordering::is_ge).unwrap_or(false))
-            });
+            let is_subset = lhs
+                .difference(rhs)
+                .all(|l| rhs.iter().any(|r| Symbol::ge(l, r)));
             if is_subset {
                 return true;
             }
@@ -214,8 +237,7 @@ impl<S: Symbol> Products<S> {
                         let child_valid = disallow != Some((sym, i))
                             && !seen.contains(&child)
                             && !child.iter().any(|s| {
-                                !rhs.iter()
-                                    .any(|r| s.cmp_rank(r).map(Ordering::is_ge).unwrap_or(false))
+                                !rhs.iter().any(|r| Symbol::ge(s, r))
                                     && self.get(s).map(Product::is_empty).unwrap_or(true)
                             });
                         if child_valid {
@@ -242,31 +264,33 @@ pub fn visit_symbol<S: Symbol>(symbol: S) -> Product<S> {
     Product::from([Sum::from([symbol])])
 }
 
-pub fn visit_all<'b, S, T, I>(iter: I) -> Product<S>
+pub fn visit_all<'b, S, T, I>(iter: I) -> Option<Product<S>>
 where
     T: Tree<Symbol = S> + 'b,
     S: Symbol,
     I: IntoIterator<Item = &'b T>,
 {
     iter.into_iter()
-        .map(|tree| tree.into_product())
-        .fold(Product::and_identity(), BitAnd::bitand)
+        .map(|tree| tree.to_product())
+        .try_fold(Product::and_identity(), |accum, elem| Some(accum & elem?))
 }
 
-pub fn visit_any<'b, S, T, I>(iter: I) -> Product<S>
+pub fn visit_any<'b, S, T, I>(iter: I) -> Option<Product<S>>
 where
     T: Tree<Symbol = S> + 'b,
     S: Symbol,
     I: IntoIterator<Item = &'b T>,
 {
     iter.into_iter()
-        .map(|tree| tree.into_product())
-        .fold(Product::or_identity(), |accum, elem| &accum | &elem)
+        .map(|tree| tree.to_product())
+        .try_fold(Product::or_identity(), |accum, elem| Some(&accum | &elem?))
 }
 
 pub trait Tree: Sized {
     type Symbol: Symbol;
-    fn into_product(&self) -> Product<Self::Symbol>;
+    /// `None` if the tree says something a product of sums of symbols can't, like
+    /// a negation
+    fn to_product(&self) -> Option<Product<Self::Symbol>>;
     fn symbol(symbol: Self::Symbol) -> Self;
     fn all(trees: Vec<Self>) -> Self;
     fn any(trees: Vec<Self>) -> Self;
@@ -303,36 +327,323 @@ where
     }
 }
 
-pub fn minimize<'a, 'b, T, S, M>(trees: M) -> impl Iterator<Item = (S, Option<T>)>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Minimization {
+    /// Trees are left as they are
+    Off,
+    /// Only simplifications within each tree, like deduplication and absorption
+    Safe,
+    /// Also removes requirements implied by the other trees
+    Full,
+}
+
+impl FromStr for Minimization {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "off" => Ok(Minimization::Off),
+            "safe" => Ok(Minimization::Safe),
+            "full" => Ok(Minimization::Full),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Minimized trees, with the number of symbols in their product forms
+/// before and after minimization. Trees without a product form are passed through
+/// as they are, and aren't counted or used to minimize the others.
+pub struct Minimized<S, T> {
+    pub trees: Vec<(S, Option<T>)>,
+    pub before: usize,
+    pub after: usize,
+}
+
+pub fn minimize<'a, 'b, T, S, M>(trees: M, minimization: Minimization) -> Minimized<S, T>
 where
     'b: 'a,
-    T: Tree<Symbol = S> + 'b,
+    T: Tree<Symbol = S> + Clone + 'b,
     S: Symbol,
     M: IntoIterator<Item = (S, &'a T)>,
 {
+    let trees: Vec<_> = trees.into_iter().collect();
     let products = trees
-        .into_iter()
-        .map(|(symbol, tree)| (symbol, tree.into_product()))
+        .iter()
+        .filter_map(|(symbol, tree)| Some((symbol.clone(), tree.to_product()?)))
         .collect();
     let mut products = Products { products };
-    let len_before = products.len();
-    products.minimize();
-    eprintln!("Before: {}, After: {}", len_before, products.len());
-    products
+    let before = products.len();
+    if minimization == Minimization::Off {
+        let trees = trees
+            .into_iter()
+            .map(|(symbol, tree)| (symbol, Some(tree.clone())))
+            .collect();
+        return Minimized {
+            trees,
+            before,
+            after: before,
+        };
+    }
+    let opaque: Vec<_> = trees
+        .into_iter()
+        .filter(|(symbol, _)| products.get(symbol).is_none())
+        .map(|(symbol, tree)| (symbol, Some(tree.clone())))
+        .collect();
+    match minimization {
+        Minimization::Safe => products.normalize(),
+        _ => products.minimize(),
+    }
+    let after = products.len();
+    let trees = products
         .products
         .into_iter()
         .map(move |(symbol, product)| (symbol, product_into_tree(product)))
+        .chain(opaque)
+        .collect();
+    Minimized {
+        trees,
+        before,
+        after,
+    }
+}
+
+/// Minimizes each group of trees on its own, so that implications only apply
+/// within a group
+pub fn minimize_grouped<'a, 'b, T, S, M, K, F>(
+    trees: M,
+    minimization: Minimization,
+    group: F,
+) -> Minimized<S, T>
+where
+    'b: 'a,
+    T: Tree<Symbol = S> + Clone + 'b,
+    S: Symbol,
+    M: IntoIterator<Item = (S, &'a T)>,
+    K: Ord,
+    F: Fn(&S) -> K,
+{
+    let mut groups: BTreeMap<K, Vec<(S, &'a T)>> = BTreeMap::new();
+    for (symbol, tree) in trees {
+        groups
+            .entry(group(&symbol))
+            .or_default()
+            .push((symbol, tree));
+    }
+    let mut minimized = Minimized {
+        trees: Vec::new(),
+        before: 0,
+        after: 0,
+    };
+    for trees in groups.into_values() {
+        let group = minimize(trees, minimization);
+        minimized.trees.extend(group.trees);
+        minimized.before += group.before;
+        minimized.after += group.after;
+    }
+    minimized
+}
+
+/// Which way a minimized tree disagrees with the original
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// The minimized tree rejects a student the original accepts
+    Strengthened,
+    /// The minimized tree accepts a student the original rejects
+    Weakened,
+}
+
+/// A minimized tree that isn't equivalent to the original, with the symbols that are
+/// true in an assignment on which they disagree
+#[derive(Debug, Clone)]
+pub struct Violation<S> {
+    pub symbol: S,
+    pub direction: Direction,
+    pub counterexample: Vec<S>,
+}
+
+/// Checks every minimized tree against the original in both directions: the original
+/// has to imply the minimized tree, and the minimized tree has to imply the original,
+/// given that each symbol implies its own tree.
+///
+/// Strengthening is checked in the context of the original trees. Weakening is checked
+/// in the context of the minimized trees, so that two trees can't each have dropped a
+/// requirement on the strength of the other's.
+pub fn audit<'a, 'b, T, S, M>(original: M, minimized: &[(S, Option<T>)]) -> Vec<Violation<S>>
+where
+    'b: 'a,
+    T: Tree<Symbol = S> + 'b,
+    S: Symbol,
+    M: IntoIterator<Item = (S, &'a T)>,
+{
+    let original = Products {
+        products: original
+            .into_iter()
+            .filter_map(|(symbol, tree)| Some((symbol, tree.to_product()?)))
+            .collect(),
+    };
+    let minimized = Products {
+        products: minimized
+            .iter()
+            .filter_map(|(symbol, tree)| {
+                let product = match tree {
+                    Some(tree) => tree.to_product()?,
+                    None => Product::or_identity(),
+                };
+                Some((symbol.clone(), product))
+            })
+            .collect(),
+    };
+    audit_products(&original, &minimized)
+}
+
+fn audit_products<S: Symbol>(original: &Products<S>, minimized: &Products<S>) -> Vec<Violation<S>> {
+    let mut symbols: Vec<_> = original.iter().map(|(symbol, _)| symbol).collect();
+    symbols.sort();
+    let mut ret = Vec::new();
+    for symbol in symbols {
+        let Some(after) = minimized.get(symbol) else {
+            continue;
+        };
+        let before = original.get(symbol).unwrap();
+        let checks = [
+            (Direction::Strengthened, original, before, after),
+            (Direction::Weakened, minimized, after, before),
+        ];
+        for (direction, context, premise, conclusion) in checks {
+            if let Some(counterexample) = counterexample(context, premise, conclusion) {
+                ret.push(Violation {
+                    symbol: symbol.clone(),
+                    direction,
+                    counterexample,
+                });
+            }
+        }
+    }
+    ret
+}
+
+/// The true symbols of an assignment satisfying `premise` but not `conclusion`, given
+/// that each symbol in `context` implies its product and each symbol implies the ones
+/// it ranks above, or `None` if `premise` implies `conclusion`.
+///
+/// Only the symbols reachable from `premise` and `conclusion` through `context` are
+/// considered, which is where minimization looks for implications.
+fn counterexample<S: Symbol>(
+    context: &Products<S>,
+    premise: &Product<S>,
+    conclusion: &Product<S>,
+) -> Option<Vec<S>> {
+    let mut symbols: Vec<&S> = Vec::new();
+    let mut index = HashMap::new();
+    let mut pending: Vec<&S> = premise
+        .iter()
+        .chain(conclusion.iter())
+        .flat_map(Sum::iter)
+        .collect();
+    while let Some(symbol) = pending.pop() {
+        if index.contains_key(symbol) {
+            continue;
+        }
+        index.insert(symbol, symbols.len());
+        symbols.push(symbol);
+        pending.extend(
+            context
+                .get(symbol)
+                .into_iter()
+                .flat_map(Product::iter)
+                .flat_map(Sum::iter),
+        );
+    }
+    let literals = |sum: &Sum<S>| -> Vec<(usize, bool)> {
+        sum.iter().map(|symbol| (index[symbol], true)).collect()
+    };
+    let mut clauses: Vec<Vec<(usize, bool)>> = premise.iter().map(literals).collect();
+    for (&symbol, &i) in index.iter() {
+        for sum in context.get(symbol).into_iter().flat_map(Product::iter) {
+            let mut clause = literals(sum);
+            clause.push((i, false));
+            clauses.push(clause);
+        }
+        for (&other, &j) in index.iter() {
+            if i != j && Symbol::ge(symbol, other) {
+                clauses.push(vec![(i, false), (j, true)]);
+            }
+        }
+    }
+    for sum in conclusion.iter() {
+        let mut clauses = clauses.clone();
+        clauses.extend(sum.iter().map(|symbol| vec![(index[symbol], false)]));
+        let mut assignment = vec![None; symbols.len()];
+        if satisfy(&clauses, &mut assignment) {
+            let mut ret: Vec<S> = symbols
+                .iter()
+                .zip(assignment)
+                .filter(|&(_, value)| value == Some(true))
+                .map(|(&symbol, _)| symbol.clone())
+                .collect();
+            ret.sort();
+            return Some(ret);
+        }
+    }
+    None
+}
+
+/// DPLL: whether some extension of `assignment` satisfies every clause of
+/// `(variable, value)` literals, leaving the first such extension in `assignment`.
+/// Variables still unassigned can take either value.
+fn satisfy(clauses: &[Vec<(usize, bool)>], assignment: &mut Vec<Option<bool>>) -> bool {
+    let mut propagated = Vec::new();
+    let mut branch = None;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        branch = None;
+        for clause in clauses {
+            if clause
+                .iter()
+                .any(|&(v, value)| assignment[v] == Some(value))
+            {
+                continue;
+            }
+            let mut unassigned = clause.iter().filter(|&&(v, _)| assignment[v].is_none());
+            match (unassigned.next(), unassigned.next()) {
+                (None, _) => {
+                    propagated.into_iter().for_each(|v| assignment[v] = None);
+                    return false;
+                }
+                (Some(&(v, value)), None) => {
+                    assignment[v] = Some(value);
+                    propagated.push(v);
+                    changed = true;
+                }
+                (Some(&literal), Some(_)) => branch = branch.or(Some(literal)),
+            }
+        }
+    }
+    let Some((v, value)) = branch else {
+        return true;
+    };
+    for value in [value, !value] {
+        assignment[v] = Some(value);
+        if satisfy(clauses, assignment) {
+            return true;
+        }
+    }
+    assignment[v] = None;
+    propagated.into_iter().for_each(|v| assignment[v] = None);
+    false
 }
 
 #[cfg(test)]
 mod implications {
+    use super::audit_products;
+    use super::Direction;
     use super::Product;
     use super::Products;
     use super::Sum;
     use super::Symbol;
     use std::cmp::Ordering;
 
-    #[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+    #[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Debug)]
     pub struct TestSymbol(u32);
 
     impl Symbol for TestSymbol {
@@ -341,6 +652,25 @@ mod implications {
         }
     }
 
+    #[test]
+    fn normalize() {
+        let mut products = Products::from([(
+            TestSymbol(0),
+            Product::from([
+                Sum::from([TestSymbol(1), TestSymbol(2)]),
+                Sum::from([TestSymbol(1)]),
+                Sum::from([TestSymbol(1)]),
+                Sum::from([TestSymbol(3)]),
+            ]),
+        )]);
+        products.normalize();
+        let product = products.get(&TestSymbol(0)).unwrap();
+        assert_eq!(
+            product.iter().cloned().collect::<Vec<_>>(),
+            [Sum::from([TestSymbol(1)]), Sum::from([TestSymbol(3)])]
+        );
+    }
+
     #[test]
     fn foo() {
         let implications =
@@ -350,6 +680,21 @@ mod implications {
         assert!(implications.implies_test(&Sum::from([TestSymbol(1)]), &Sum::from([TestSymbol(1)])));
     }
 
+    #[test]
+    fn ge_reflexive() {
+        assert!(Symbol::ge(&TestSymbol(0), &TestSymbol(0)));
+        assert!(!Symbol::ge(&TestSymbol(0), &TestSymbol(1)));
+        assert!(!Symbol::ge(&TestSymbol(1), &TestSymbol(0)));
+
+        // 1 has no implications of its own, so reaching it only counts if 1 satisfies 1
+        let implications =
+            Products::from([(TestSymbol(0), Product::from([Sum::from([TestSymbol(1)])]))]);
+        assert!(implications.implies_test(&Sum::from([TestSymbol(0)]), &Sum::from([TestSymbol(1)])));
+        assert!(
+            !implications.implies_test(&Sum::from([TestSymbol(1)]), &Sum::from([TestSymbol(0)]))
+        );
+    }
+
     #[test]
     fn bar() {
         let implications = Products::from([
@@ -465,4 +810,48 @@ mod implications {
             !implications.implies_test(&Sum::from([TestSymbol(3)]), &Sum::from([TestSymbol(0)]))
         );
     }
+
+    #[test]
+    fn audit() {
+        let original = Products::from([
+            (
+                TestSymbol(0),
+                Product::from([Sum::from([TestSymbol(1)]), Sum::from([TestSymbol(2)])]),
+            ),
+            (TestSymbol(1), Product::from([Sum::from([TestSymbol(2)])])),
+        ]);
+        let mut minimized = original.clone();
+        minimized.minimize();
+        assert!(audit_products(&original, &minimized).is_empty());
+
+        // Dropping 1's requirement leaves nothing to imply 0's
+        let minimized = Products::from([
+            (TestSymbol(0), Product::from([Sum::from([TestSymbol(1)])])),
+            (TestSymbol(1), Product::and_identity()),
+        ]);
+        let violations = audit_products(&original, &minimized);
+        let violations: Vec<_> = violations
+            .into_iter()
+            .map(|v| (v.symbol, v.direction, v.counterexample))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                (TestSymbol(0), Direction::Weakened, vec![TestSymbol(1)]),
+                (TestSymbol(1), Direction::Weakened, vec![]),
+            ]
+        );
+
+        let minimized = Products::from([
+            (
+                TestSymbol(0),
+                Product::from([Sum::from([TestSymbol(1)]), Sum::from([TestSymbol(3)])]),
+            ),
+            (TestSymbol(1), Product::from([Sum::from([TestSymbol(2)])])),
+        ]);
+        let violations = audit_products(&original, &minimized);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].direction, Direction::Strengthened);
+        assert_eq!(violations[0].counterexample, [TestSymbol(1), TestSymbol(2)]);
+    }
 }
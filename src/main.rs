@@ -1,111 +1,1042 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
+#![allow(clippy::result_large_err)]
 
+mod annotations;
+mod args;
+#[cfg(feature = "binary")]
+mod binary;
+mod changelog;
+mod clauses;
+mod coenrollment;
+mod compression;
+mod config;
+mod coverage;
+mod dataset;
 mod download;
+mod duplicates;
+mod ego;
+mod exams;
+mod export;
+mod gatekeeper;
 mod graph;
+mod hash;
+mod html;
+mod instructor;
+mod lock;
 mod logic;
+mod manifest;
+mod memory;
+mod monitor;
 mod parse_prerequisite_string;
 mod process;
+mod registration;
 mod restrictions;
+mod schema;
+mod stats;
+mod subjects;
+mod summary;
+mod tags;
+mod term;
+mod tree;
+mod trends;
 
-use crate::process::Course;
-use crate::restrictions::Qualification;
+use crate::args::Args;
+use crate::download::CatalogSource;
+use crate::logic::Minimization;
+use crate::process::{Course, Level};
+use crate::restrictions::{MinimizationScope, Qualification};
 use reqwest::Client;
 use serde_json::de::IoRead;
 use serde_json::StreamDeserializer;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::io::AsyncWriteExt;
 
+const USAGE: &str = "usage:
+    cab [--profile NAME [--config cab.toml]] [--annotations annotations.toml]
+        [--summary-json FILE] SUBCOMMAND ...
+    cab download [OUTPUT] [--books] [--subject SUBJECT] [--keyword KEYWORD]
+        [--include-independent-study] [--include-cancelled] [--sharded]
+        [--terms SRCDB,...|probe|reprobe] [--delta PREVIOUS] [--max-requests N]
+        [--max-duration SECONDS] [--max-connections N] [--no-cache|--refresh] [--force]
+        [CLIENT_OPTIONS]
+    cab process [INPUT] [OUTPUT] [--minimize off|safe|full]
+        [--minimize-scope subject|global] [--audit-minimization] [--trends DATABASE]
+        [--include-cancelled] [--as-of SRCDB] [--since SRCDB] [--subject SUBJECT]...
+        [--numbers 0000..1999] [--level undergrad|grad] [--exclude-restricted]
+        [--strict-coverage BASELINE] [--tag-rules tags.toml] [--partitions N] [--force]
+    cab export [INPUT] [OUTPUT] [--level undergrad|grad] [--as-of SRCDB]
+        [--format jsonl|csv|dot|gatekeeper|datalog|dimacs] [--attribute WRIT]... [--tag TAG]...
+        [--snc]
+    cab graph [INPUT] [--level undergrad|grad] [--as-of SRCDB] [--per-course [--depth N]]
+        [--gatekeeper] [--tag TAG]...
+    cab coenrollment [INPUT] [OUTPUT]
+    cab coverage [INPUT] [OUTPUT_DIRECTORY]
+    cab clauses [INPUT] [OUTPUT] [--format datalog|dimacs]
+    cab changelog OLD NEW [OUTPUT] [--format md]
+    cab audit [INPUT]
+    cab duplicates [INPUT] [OUTPUT]
+    cab stats [INPUT]
+    cab trends [DATABASE]
+    cab migrate [INPUT] [OUTPUT]
+    cab convert INPUT OUTPUT [--to binary|jsonl]
+    cab tree CODE [INPUT] [--format text|latex]
+    cab instructor NAME [INPUT] [--as-of SRCDB]
+    cab monitor SRCDB:CRN... [--interval SECONDS] [--rounds N] [--output FILE]
+        [CLIENT_OPTIONS]
+    cab fetch-dataset [OUTPUT] [--release latest|TAG] [--repository OWNER/NAME]
+        [--asset NAME] [--url URL [--sha256 HEX]] [--proxy URL] [--user-agent AGENT]
+    cab watch SRCDB --course CODE... [--interval SECONDS] [--rounds N] [--output FILE]
+        [--charts DIRECTORY] [CLIENT_OPTIONS]
+
+CLIENT_OPTIONS: [--proxy URL] [--user-agent AGENT] [--header 'NAME: VALUE']...
+    [--session URL]
+
+A profile in the config file supplies options and flags missing from the command
+line, and moves the default paths under output/ to its output-directory.
+Annotations, notes kept about courses apart from the catalog, are merged into
+exports, graphs, changelogs, and instructor reports. Processing tags courses by the
+rules in the tag rules file, and --tag keeps only the courses with every tag given.
+Download keeps each usable detail response under cache/ and reads it from there on
+later runs; --refresh fetches every detail again and --no-cache skips the cache.
+Export --snc keeps only the courses that can be taken S/NC. Process also writes each
+section on its own line to sections.jsonl beside its output, each term's registration
+deadlines to terms.json, and the course each alias code stands for to aliases.json,
+rewriting prerequisites that name an alias to name its course. Process --partitions N
+holds only one of N parts of the records in memory at a time, spilling them to a
+partitions directory beside its output first.
+Files of courses start with their schema version; reading an older one upgrades it
+in memory, and migrate upgrades the file itself, in place unless given an OUTPUT.
+Built with the binary feature, cab reads a compact binary form of these files as
+well, and convert turns one form into the other, binary unless --to says otherwise
+or the INPUT is already binary.
+--summary-json writes the inputs, outputs with their SHA-256 hashes, counts,
+warnings, and duration of the run, whether or not it succeeds.";
+
 #[tokio::main]
 async fn main() -> io::Result<()> {
-    //    stage2("output/cab.jsonl", "output/minimized.jsonl")?;
-    courses_to_svg("output/minimized.jsonl")?;
-    //    stage1("output/cab.jsonl").await?;
+    let mut args = Args::new(std::env::args().skip(1));
+    let config: String = args
+        .option("config")?
+        .unwrap_or_else(|| String::from("cab.toml"));
+    if let Some(profile) = args.option::<String>("profile")? {
+        args = args.with_profile(config::profile(config, &profile)?);
+    }
+    let annotations: String = args
+        .option("annotations")?
+        .unwrap_or_else(|| String::from("annotations.toml"));
+    let annotations = annotations::Annotations::load(annotations)?;
+    let summary_json: Option<String> = args.option("summary-json")?;
+    let subcommand = args.positional_or("graph");
+    let started = std::time::SystemTime::now();
+    let result = run(&subcommand, args, &annotations).await;
+    if let Some(path) = summary_json {
+        summary::Summary::new(&subcommand, started, &result).write(path)?;
+    }
+    result
+}
+
+async fn run(
+    subcommand: &str,
+    mut args: Args,
+    annotations: &annotations::Annotations,
+) -> io::Result<()> {
+    match subcommand {
+        "download" => {
+            let criteria = download::Criteria {
+                subject: args.option("subject")?,
+                keyword: args.option("keyword")?,
+                include_independent_study: args.flag("include-independent-study"),
+                include_cancelled: args.flag("include-cancelled"),
+            };
+            let cache = download::Cache::new("cache");
+            let cache = match (args.flag("no-cache"), args.flag("refresh")) {
+                (true, true) => {
+                    return Err(args::invalid(String::from(
+                        "--no-cache and --refresh can't be used together",
+                    )))
+                }
+                (true, false) => None,
+                (false, true) => Some(cache.refreshing()),
+                (false, false) => Some(cache),
+            };
+            let mut settings = download::Settings {
+                cache,
+                books: args.flag("books"),
+                criteria,
+                ..download::Settings::default()
+            };
+            if let Some(max_connections) = args.option("max-connections")? {
+                settings.max_connections = max_connections;
+            }
+            let client = client(&mut args).await?;
+            let force = args.flag("force");
+            let sharded = args.flag("sharded");
+            let delta: Option<String> = args.option("delta")?;
+            settings.budget = download::Budget {
+                max_requests: args.option("max-requests")?,
+                max_duration: args
+                    .option("max-duration")?
+                    .map(std::time::Duration::from_secs),
+            };
+            let terms: Option<String> = args.option("terms")?;
+            let output = args.positional_or(match (sharded, &delta) {
+                (true, _) => "output/raw",
+                (false, Some(_)) => "output/patch.jsonl",
+                (false, None) => "output/cab.jsonl",
+            });
+            args.finish()?;
+            summary::output(&output);
+            if let Some(previous) = delta {
+                summary::input(&previous);
+                let previous = if Path::new(&previous).is_dir() {
+                    compression::directory_reader(previous)?
+                } else {
+                    compression::reader(previous)?
+                };
+                settings.previous = Some(download::fingerprints(previous)?);
+                // Changed sections are exactly the ones whose cached details are stale
+                settings.cache = None;
+            }
+            let _output_lock = match sharded {
+                true => lock::Lock::acquire(&output, force)?,
+                false => lock_parent(&output, force)?,
+            };
+            let _cache_lock = lock::Lock::acquire("cache", force)?;
+            let terms: Vec<String> = match terms.as_deref() {
+                Some("probe") => probe_terms(&client, &settings, false).await?,
+                Some("reprobe") => probe_terms(&client, &settings, true).await?,
+                Some(terms) => terms.split(',').map(String::from).collect(),
+                None => settings.source.terms(),
+            };
+            let terms: Vec<&str> = terms.iter().map(String::as_str).collect();
+            settings.scratch = match sharded {
+                true => PathBuf::from(&output),
+                false => Path::new(&output)
+                    .parent()
+                    .filter(|parent| *parent != Path::new(""))
+                    .map_or_else(|| PathBuf::from("."), Path::to_path_buf),
+            };
+            let quarantine = Path::new(&output).with_file_name("quarantine.jsonl");
+            File::create(&quarantine)?;
+            summary::output(&quarantine);
+            settings.quarantine = Some(quarantine);
+            let report_path = Path::new(&output).with_file_name("report.json");
+            let report = if sharded {
+                stage1_sharded(&client, output, &terms, settings).await?
+            } else {
+                let (entry, report) = stage1(&client, &output, &terms, &settings).await?;
+                manifest::record(&output, entry)?;
+                report
+            };
+            summary::count("terms", report.terms.len());
+            summary::count(
+                "records",
+                report.terms.values().map(|term| term.records).sum(),
+            );
+            summary::output(&report_path);
+            compression::write(report_path, serde_json::to_vec_pretty(&report)?)?;
+        }
+        "process" => {
+            let minimization = args.option("minimize")?.unwrap_or(Minimization::Full);
+            let scope = args
+                .option("minimize-scope")?
+                .unwrap_or(MinimizationScope::Global);
+            let audit = args.flag("audit-minimization");
+            let trends: Option<String> = args.option("trends")?;
+            let strict_coverage: Option<String> = args.option("strict-coverage")?;
+            let tag_rules: String = args
+                .option("tag-rules")?
+                .unwrap_or_else(|| String::from("tags.toml"));
+            let tag_rules = tags::Rules::load(tag_rules)?;
+            let force = args.flag("force");
+            let partitions: Option<usize> = args.option("partitions")?;
+            if partitions == Some(0) {
+                return Err(args::invalid(String::from(
+                    "--partitions needs to be at least 1",
+                )));
+            }
+            let numbers = match args.option::<String>("numbers")? {
+                Some(numbers) => Some(tags::numbers(&numbers).ok_or_else(|| {
+                    args::invalid(format!(
+                        "invalid --numbers {numbers}, expected like 0000..1999"
+                    ))
+                })?),
+                None => None,
+            };
+            let filters = process::Filters {
+                include_cancelled: args.flag("include-cancelled"),
+                as_of: args.option("as-of")?,
+                since: args.option("since")?,
+                subjects: args.options("subject")?,
+                numbers,
+                level: args.option("level")?,
+                exclude_restricted: args.flag("exclude-restricted"),
+            };
+            let input = args.positional_or("output/cab.jsonl");
+            let output = args.positional_or("output/minimized.jsonl");
+            args.finish()?;
+            let _lock = lock_parent(&output, force)?;
+            summary::input(&input);
+            summary::output(&output);
+            manifest::verify(&input)?;
+            let open = || match Path::new(&input).is_dir() {
+                true => compression::directory_reader(&input),
+                false => compression::reader(&input),
+            };
+            if let Some(baseline) = strict_coverage {
+                check_coverage(open()?, baseline)?;
+            }
+            let input = open()?;
+            let read = || match partitions {
+                Some(partitions) => {
+                    let directory = Path::new(&output).with_file_name("partitions");
+                    process::process_partitioned(
+                        IoRead::new(input),
+                        &filters,
+                        &directory,
+                        partitions,
+                    )
+                }
+                None => Ok(process::process(IoRead::new(input), &filters)),
+            };
+            let summary = stage2(read, &output, &tag_rules, minimization, scope, audit)?;
+            summary::count("courses", summary.courses);
+            summary::count("with_prerequisites", summary.with_prerequisites);
+            summary::count("rejected", summary.rejected);
+            summary::count("minimization_before", summary.minimization_before);
+            summary::count("minimization_after", summary.minimization_after);
+            if let Some(trends) = trends {
+                summary::output(&trends);
+                trends::record(trends, &summary)?;
+            }
+        }
+        "export" => {
+            let level = args.option("level")?;
+            let as_of: Option<String> = args.option("as-of")?;
+            let format: String = args
+                .option("format")?
+                .unwrap_or_else(|| String::from("jsonl"));
+            let attributes: Vec<process::Attribute> = args.options("attribute")?;
+            let tags: Vec<String> = args.options("tag")?;
+            let snc = args.flag("snc");
+            let registry = export::Registry::default();
+            let exporter = registry.get(&format).ok_or_else(|| {
+                let names: Vec<_> = registry.names().collect();
+                args::invalid(format!(
+                    "unknown format {format}, expected one of {}",
+                    names.join(", ")
+                ))
+            })?;
+            let input = args.positional_or("output/minimized.jsonl");
+            let name = match level {
+                Some(Level::Undergraduate) => "undergrad",
+                Some(Level::Graduate) => "grad",
+                None => "export",
+            };
+            let output = args.positional_or(&format!("output/{name}.{}", exporter.extension()));
+            args.finish()?;
+            let courses = read_annotated(input, annotations)?;
+            let courses = filter_level(as_of_term(courses, as_of.as_deref()), level);
+            let mut courses = filter_tags(courses, &tags);
+            courses.retain(|course| {
+                attributes
+                    .iter()
+                    .all(|attribute| course.attributes().contains(attribute))
+                    && (!snc || course.grading().satisfactory)
+            });
+            summary::count("courses", courses.len());
+            summary::output(&output);
+            let mut output = compression::writer(output)?;
+            exporter.write(&courses, &mut output)?;
+            output.finish()?;
+        }
+        "graph" => {
+            let level = args.option("level")?;
+            let as_of: Option<String> = args.option("as-of")?;
+            let per_course = args.flag("per-course");
+            let gatekeeper = args.flag("gatekeeper");
+            let tags: Vec<String> = args.options("tag")?;
+            let depth = args.option("depth")?.unwrap_or(2);
+            let input = args.positional_or("output/minimized.jsonl");
+            let output = args.output(match per_course {
+                true => "output/graphs/by-course",
+                false => "output/graphs/graph",
+            });
+            args.finish()?;
+            if per_course {
+                let courses =
+                    filter_level(as_of_term(read_courses(input)?, as_of.as_deref()), level);
+                let courses = filter_tags(courses, &tags);
+                summary::count("courses", courses.len());
+                summary::output(&output);
+                ego::render_all(&courses, depth, output)?;
+            } else {
+                let courses = read_annotated(input, annotations)?;
+                let courses = filter_level(as_of_term(courses, as_of.as_deref()), level);
+                let courses = filter_tags(courses, &tags);
+                summary::count("courses", courses.len());
+                courses_to_svg(&courses, &output, gatekeeper)?;
+            }
+        }
+        "coenrollment" => {
+            let input = args.positional_or("output/minimized.jsonl");
+            let output = args.positional_or("output/coenrollment.dot");
+            args.finish()?;
+            let courses = read_courses(input)?;
+            let edges = coenrollment::coenrollment(&courses);
+            eprintln!("{} correlated pairs", edges.len());
+            summary::count("pairs", edges.len());
+            summary::output(&output);
+            compression::write(output, coenrollment::dot(&edges))?;
+        }
+        "coverage" => {
+            let input = args.positional_or("output/minimized.jsonl");
+            let output = args.positional_or("output/coverage");
+            args.finish()?;
+            summary::output(&output);
+            courses_to_coverage(input, output)?;
+        }
+        "tree" => {
+            let format = args.option("format")?.unwrap_or(tree::Format::Text);
+            let code = args
+                .positional()
+                .ok_or_else(|| args::invalid(String::from("missing course code")))?;
+            let code = restrictions::CourseCode::try_from(code.as_str())
+                .map_err(|_| args::invalid(format!("invalid course code: {code}")))?;
+            let input = args.positional_or("output/minimized.jsonl");
+            args.finish()?;
+            let courses = read_courses(input)?;
+            let course = courses
+                .iter()
+                .find(|course| *course.code() == code)
+                .ok_or_else(|| args::invalid(format!("no course {code}")))?;
+            print!("{}", tree::render(&code, course.prerequisites(), format));
+        }
+        "instructor" => {
+            let as_of: Option<String> = args.option("as-of")?;
+            let name = args
+                .positional()
+                .ok_or_else(|| args::invalid(String::from("missing instructor name")))?;
+            let input = args.positional_or("output/minimized.jsonl");
+            args.finish()?;
+            let courses = as_of_term(read_annotated(input, annotations)?, as_of.as_deref());
+            print!("{}", instructor::report(&courses, &name));
+        }
+        "monitor" => {
+            let client = client(&mut args).await?;
+            let interval = args.option("interval")?.unwrap_or(300);
+            let rounds = args.option("rounds")?;
+            let output: String = args
+                .option("output")?
+                .unwrap_or_else(|| args.output("output/seats.jsonl"));
+            let mut sections = Vec::new();
+            while let Some(section) = args.positional() {
+                let section = section
+                    .parse()
+                    .map_err(|_| args::invalid(format!("expected SRCDB:CRN, found {section}")))?;
+                sections.push(section);
+            }
+            args.finish()?;
+            let interval = std::time::Duration::from_secs(interval);
+            summary::output(&output);
+            monitor::monitor(
+                &client,
+                &download::Brown,
+                &sections,
+                interval,
+                rounds,
+                output,
+            )
+            .await?;
+        }
+        "fetch-dataset" => {
+            let release: String = args
+                .option("release")?
+                .unwrap_or_else(|| String::from("latest"));
+            let repository: String = args
+                .option("repository")?
+                .unwrap_or_else(|| String::from("EtomicBomb/cab"));
+            let asset: String = args
+                .option("asset")?
+                .unwrap_or_else(|| String::from("minimized.jsonl.gz"));
+            let url: Option<String> = args.option("url")?;
+            let sha256: Option<String> = args.option("sha256")?;
+            let settings = download::ClientSettings {
+                proxy: args.option("proxy")?,
+                // The GitHub API turns away requests without one
+                user_agent: Some(
+                    args.option("user-agent")?
+                        .unwrap_or_else(|| String::from("cab")),
+                ),
+                headers: Vec::new(),
+                session: None,
+            };
+            let name = match url {
+                Some(ref url) => url.rsplit('/').next().unwrap_or_default().to_string(),
+                None => asset.clone(),
+            };
+            let output = args.positional_or(&format!("output/{name}"));
+            args.finish()?;
+            let source = match url {
+                Some(url) => dataset::Source::Url { url, sha256 },
+                None => dataset::Source::Release {
+                    repository,
+                    release,
+                    asset,
+                },
+            };
+            let client = download::client(&settings)
+                .await
+                .map_err(|e| io::Error::other(format!("couldn't set up the client: {e}")))?;
+            summary::output(&output);
+            dataset::fetch(&client, &source, output).await?;
+        }
+        "watch" => {
+            let client = client(&mut args).await?;
+            let interval = args.option("interval")?.unwrap_or(300);
+            let rounds = args.option("rounds")?;
+            let courses: Vec<String> = args.options("course")?;
+            let output: String = args
+                .option("output")?
+                .unwrap_or_else(|| args.output("output/watch.jsonl"));
+            let charts: String = args
+                .option("charts")?
+                .unwrap_or_else(|| args.output("output/watch"));
+            let term = args
+                .positional()
+                .ok_or_else(|| args::invalid(String::from("missing SRCDB")))?;
+            args.finish()?;
+            if courses.is_empty() {
+                return Err(args::invalid(String::from("watch needs a --course")));
+            }
+            let interval = std::time::Duration::from_secs(interval);
+            summary::output(&output);
+            summary::output(&charts);
+            monitor::watch(
+                &client,
+                &download::Brown,
+                &term,
+                &courses,
+                interval,
+                rounds,
+                output,
+                charts,
+            )
+            .await?;
+        }
+        "clauses" => {
+            let format = args.option("format")?.unwrap_or(clauses::Format::Datalog);
+            let input = args.positional_or("output/minimized.jsonl");
+            let output = args.positional_or(match format {
+                clauses::Format::Datalog => "output/clauses.dl",
+                clauses::Format::Dimacs => "output/clauses.cnf",
+            });
+            args.finish()?;
+            summary::output(&output);
+            compression::write(output, clauses::export(&read_courses(input)?, format))?;
+        }
+        "changelog" => {
+            let format = args
+                .option("format")?
+                .unwrap_or(changelog::Format::Markdown);
+            let missing = || args::invalid(String::from("changelog needs OLD and NEW"));
+            let old = args.positional().ok_or_else(missing)?;
+            let new = args.positional().ok_or_else(missing)?;
+            let output = args.positional_or("output/changelog.md");
+            args.finish()?;
+            let changelog = changelog::changelog(
+                &read_annotated(old, annotations)?,
+                &read_annotated(new, annotations)?,
+                format,
+                &subjects::Subjects::default(),
+            );
+            summary::output(&output);
+            compression::write(output, changelog)?;
+        }
+        "audit" => {
+            let input = args.positional_or("output/cab.jsonl");
+            args.finish()?;
+            summary::input(&input);
+            let (inconsistencies, skipped) =
+                process::audit_demographics(IoRead::new(compression::reader(input)?));
+            for inconsistency in inconsistencies.iter() {
+                println!("{inconsistency}");
+            }
+            eprintln!("{} inconsistencies", inconsistencies.len());
+            summary::count("inconsistencies", inconsistencies.len());
+            if skipped > 0 {
+                summary::warn(format!("{skipped} records couldn't be audited"));
+            }
+            summary::count("skipped", skipped);
+        }
+        "duplicates" => {
+            let input = args.positional_or("output/minimized.jsonl");
+            let output = args.positional_or("output/duplicates.jsonl");
+            args.finish()?;
+            let duplicates = duplicates::duplicates(&read_courses(input)?);
+            summary::count("groups", duplicates.len());
+            summary::output(&output);
+            let mut output = compression::writer(output)?;
+            for duplicate in duplicates.iter() {
+                serde_json::to_writer(&mut output, duplicate)?;
+                output.write_all(b"\n")?;
+            }
+            output.finish()?;
+            eprintln!("{} groups of courses share a description", duplicates.len());
+        }
+        "stats" => {
+            let input = args.positional_or("output/minimized.jsonl");
+            args.finish()?;
+            let stats = stats::stats(&read_courses(input)?);
+            summary::count("courses", stats.courses);
+            print!("{}", stats.report());
+        }
+        "migrate" => {
+            let input = args.positional_or("output/minimized.jsonl");
+            let output = args.positional().unwrap_or_else(|| input.clone());
+            args.finish()?;
+            summary::input(&input);
+            let (courses, version) = schema::read(compression::reader(&input)?)?;
+            summary::output(&output);
+            let mut writer = compression::writer(&output)?;
+            schema::write_header(&mut writer)?;
+            for course in courses.iter() {
+                serde_json::to_writer(&mut writer, course)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.finish()?;
+            summary::count("courses", courses.len());
+            eprintln!(
+                "Migrated {} courses from schema version {version} to {}",
+                courses.len(),
+                schema::VERSION
+            );
+        }
+        #[cfg(feature = "binary")]
+        "convert" => {
+            let input = args
+                .positional()
+                .ok_or_else(|| args::invalid(String::from("missing INPUT")))?;
+            let output = args
+                .positional()
+                .ok_or_else(|| args::invalid(String::from("missing OUTPUT")))?;
+            let to: Option<String> = args.option("to")?;
+            args.finish()?;
+            summary::input(&input);
+            let mut reader = io::BufReader::new(compression::reader(&input)?);
+            let to_binary = match to.as_deref() {
+                Some("binary") => true,
+                Some("jsonl") => false,
+                None => !binary::is_binary(&mut reader)?,
+                Some(to) => {
+                    return Err(args::invalid(format!(
+                        "unknown form {to}, expected binary or jsonl"
+                    )))
+                }
+            };
+            let (courses, _) = schema::read(reader)?;
+            summary::output(&output);
+            let writer = if to_binary {
+                let mut writer = binary::Writer::new(compression::writer(&output)?)?;
+                writer.write(&schema::Header {
+                    schema_version: schema::VERSION,
+                })?;
+                for course in courses.iter() {
+                    writer.write(course)?;
+                }
+                writer.into_inner()
+            } else {
+                let mut writer = compression::writer(&output)?;
+                schema::write_header(&mut writer)?;
+                for course in courses.iter() {
+                    serde_json::to_writer(&mut writer, course)?;
+                    writer.write_all(b"\n")?;
+                }
+                writer
+            };
+            writer.finish()?;
+            summary::count("courses", courses.len());
+        }
+        "trends" => {
+            let database = args.positional_or("output/trends.jsonl");
+            args.finish()?;
+            summary::input(&database);
+            print!("{}", trends::chart(&trends::load(database)?));
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return Err(args::invalid(format!("unknown subcommand: {subcommand}")));
+        }
+    }
     Ok(())
 }
 
-fn courses_to_svg<I: AsRef<Path>>(input: I) -> io::Result<()> {
-    let input = File::open(input)?;
-    let courses: Vec<Course> = StreamDeserializer::new(IoRead::new(&input))
+/// Takes the `CLIENT_OPTIONS` shared by the subcommands that talk to the API
+async fn client(args: &mut Args) -> io::Result<Client> {
+    let settings = download::ClientSettings {
+        proxy: args.option("proxy")?,
+        user_agent: args.option("user-agent")?,
+        headers: args.options("header")?,
+        session: args
+            .option("session")?
+            .or_else(|| download::Brown.session().map(String::from)),
+    };
+    download::client(&settings)
+        .await
+        .map_err(|e| io::Error::other(format!("couldn't set up the client: {e}")))
+}
+
+/// Locks the directory `path` is written into
+fn lock_parent<P: AsRef<Path>>(path: P, force: bool) -> io::Result<lock::Lock> {
+    match path.as_ref().parent() {
+        Some(parent) if parent != Path::new("") => lock::Lock::acquire(parent, force),
+        _ => lock::Lock::acquire(".", force),
+    }
+}
+
+/// Upgraded in memory if an older version of the schema wrote them
+fn read_courses<I: AsRef<Path>>(input: I) -> io::Result<Vec<Course>> {
+    summary::input(&input);
+    let path = input.as_ref().to_path_buf();
+    let (courses, version) = schema::read(compression::reader(input)?)?;
+    if version < schema::VERSION {
+        summary::warn(format!(
+            "{path:?} has schema version {version}, run cab migrate to upgrade it to {}",
+            schema::VERSION
+        ));
+    }
+    Ok(courses)
+}
+
+/// With each course's annotation attached
+fn read_annotated<I: AsRef<Path>>(
+    input: I,
+    annotations: &annotations::Annotations,
+) -> io::Result<Vec<Course>> {
+    let mut courses = read_courses(input)?;
+    annotations.apply(&mut courses);
+    Ok(courses)
+}
+
+/// The courses with every one of `tags`
+fn filter_tags(courses: Vec<Course>, tags: &[String]) -> Vec<Course> {
+    courses
         .into_iter()
-        .collect::<serde_json::Result<_>>()?;
-    let courses = courses
+        .filter(|course| tags.iter().all(|tag| course.tags().contains(tag)))
+        .collect()
+}
+
+fn filter_level(courses: Vec<Course>, level: Option<Level>) -> Vec<Course> {
+    courses
         .into_iter()
-        .map(|course| (course.code().clone(), course))
-        .collect();
-    let svg = crate::graph::svg(&courses)?;
-    let mut output = file_at("output/graphs/graph", ".svg").unwrap();
+        .filter(|course| level.is_none_or(|level| course.level() == level))
+        .collect()
+}
+
+/// Courses as they stood in `term`, without the offerings after it
+fn as_of_term(courses: Vec<Course>, term: Option<&str>) -> Vec<Course> {
+    match term {
+        Some(term) => courses
+            .into_iter()
+            .filter_map(|course| course.until(term))
+            .collect(),
+        None => courses,
+    }
+}
+
+/// Writes the graph to the first free `<output><number>.svg`
+fn courses_to_svg(courses: &[Course], output: &str, gatekeeper: bool) -> io::Result<()> {
+    let svg = crate::graph::svg(
+        courses,
+        &subjects::Subjects::default(),
+        &exams::Exams::default(),
+        gatekeeper,
+    )?;
+    let mut output = file_at(output, ".svg").unwrap();
     output.write_all(svg.as_bytes()).unwrap();
     Ok(())
 }
 
-/// Input is cab.jsonl, output is courses
-fn stage2<I: AsRef<Path>, O: AsRef<Path>>(input: I, output: O) -> io::Result<()> {
-    let input = File::open(input)?;
+/// Errors if any minimized tree is stronger or weaker than its original, after
+/// printing a counterexample for each
+fn audit_minimization<'a, I>(
+    original: I,
+    minimized: &[(Qualification, Option<restrictions::PrerequisiteTree>)],
+) -> io::Result<()>
+where
+    I: IntoIterator<Item = (Qualification, &'a restrictions::PrerequisiteTree)>,
+{
+    let violations = logic::audit(original, minimized);
+    for violation in violations.iter() {
+        let taken: Vec<_> = violation
+            .counterexample
+            .iter()
+            .map(Qualification::to_string)
+            .collect();
+        let taken = match taken.is_empty() {
+            true => String::from("nothing"),
+            false => taken.join(", "),
+        };
+        let direction = match violation.direction {
+            logic::Direction::Strengthened => "rejects",
+            logic::Direction::Weakened => "accepts",
+        };
+        eprintln!(
+            "{}: minimized prerequisites {direction} a student with {taken}, unlike the original",
+            violation.symbol
+        );
+    }
+    match violations.len() {
+        0 => {
+            eprintln!("Minimization audit found no violations");
+            Ok(())
+        }
+        n => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("minimization changed {n} prerequisite trees"),
+        )),
+    }
+}
+
+/// Writes `coverage.csv` and one `<subject>.svg` heatmap per subject into `output`
+fn courses_to_coverage<I: AsRef<Path>, O: AsRef<Path>>(input: I, output: O) -> io::Result<()> {
+    let courses = read_courses(input)?;
+    let coverage = coverage::Coverage::new(&courses);
+    let output = output.as_ref();
+    std::fs::create_dir_all(output)?;
+    compression::write(output.join("coverage.csv"), coverage.csv())?;
+    for (subject, svg) in coverage.svgs() {
+        compression::write(output.join(format!("{subject}.svg")), svg)?;
+    }
+    Ok(())
+}
+
+/// Errors if more of `input` fails to parse than the failure counts in `baseline`
+/// allow, recording the current counts as the baseline if there are none yet
+fn check_coverage<I: io::Read, B: AsRef<Path>>(input: I, baseline: B) -> io::Result<()> {
+    let failures = process::ParseFailures::new(IoRead::new(input));
+    eprintln!("Parse failures: {failures:?}");
+    let baseline_failures: process::ParseFailures = match std::fs::read(&baseline) {
+        Ok(json) => serde_json::from_slice(&json)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            eprintln!("no coverage baseline yet, writing one");
+            return compression::write(baseline, serde_json::to_vec_pretty(&failures)?);
+        }
+        Err(e) => return Err(e),
+    };
+    let regressions = failures.regressions(&baseline_failures);
+    if !regressions.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("parse coverage regressed: {}", regressions.join("; ")),
+        ));
+    }
+    Ok(())
+}
+
+/// `read` processes cab.jsonl (or a directory of term shards or cached details), and
+/// output is courses, with each of their sections in `sections.jsonl` beside it, and
+/// the course each alias stands for in `aliases.json`, and each term's registration
+/// deadlines in `terms.json`
+fn stage2<O: AsRef<Path>>(
+    read: impl FnOnce() -> io::Result<(Vec<process::Course>, Vec<process::Rejected>)>,
+    output: O,
+    tag_rules: &tags::Rules,
+    minimization: Minimization,
+    scope: MinimizationScope,
+    audit: bool,
+) -> io::Result<trends::Summary> {
+    let mut memory = memory::Stages::start();
     eprintln!("Reading from file");
-    let mut courses = process::process(IoRead::new(&input));
+    let (mut courses, rejected) = read()?;
     eprintln!("Read {}", courses.len());
-    let minimized = courses.iter().filter_map(|course| {
-        Some((
-            Qualification::Course(course.code().clone()),
-            course.prerequisites()?,
-        ))
-    });
+    tag_rules.apply(&mut courses);
+    let aliases = output.as_ref().with_file_name("aliases.json");
+    summary::output(&aliases);
+    let entries = process::AliasMap::new(courses.iter()).entries();
+    compression::write(aliases, serde_json::to_vec_pretty(&entries)?)?;
+    process::canonicalize_aliases(&mut courses);
+    let exams = exams::Exams::default();
+    for course in courses.iter_mut() {
+        if let Some(tree) = course.prerequisites_mut() {
+            *tree = exams.normalize_tree(tree);
+        }
+    }
+    let terms = output.as_ref().with_file_name("terms.json");
+    summary::output(&terms);
+    let term_dates = process::term_dates(&courses);
+    compression::write(terms, serde_json::to_vec_pretty(&term_dates)?)?;
+    let sections = output.as_ref().with_file_name("sections.jsonl");
+    let errors = output.as_ref().with_file_name("process-errors.jsonl");
+    summary::output(&sections);
+    summary::output(&errors);
+    let mut errors = compression::writer(errors)?;
+    for rejected in rejected.iter() {
+        serde_json::to_writer(&mut errors, rejected)?;
+        errors.write_all(b"\n")?;
+    }
+    errors.finish()?;
+    if !rejected.is_empty() {
+        let mut fields: BTreeMap<&str, usize> = BTreeMap::new();
+        for rejected in rejected.iter() {
+            *fields
+                .entry(rejected.field.unwrap_or("record"))
+                .or_default() += 1;
+        }
+        let fields: Vec<_> = fields
+            .into_iter()
+            .map(|(field, count)| format!("{count} in {field}"))
+            .collect();
+        summary::warn(format!(
+            "skipped {} malformed records ({}), see process-errors.jsonl",
+            rejected.len(),
+            fields.join(", ")
+        ));
+    }
+    memory.finish("process");
+    let trees = || {
+        courses.iter().filter_map(|course| {
+            Some((
+                Qualification::Course(course.code().clone()),
+                course.prerequisites()?,
+            ))
+        })
+    };
     eprintln!("Minimizing");
-    let minimized: HashMap<_, _> = logic::minimize(minimized).collect();
+    let minimized = logic::minimize_grouped(trees(), minimization, |qualification| {
+        scope.group(qualification).map(String::from)
+    });
+    let (minimization_before, minimization_after) = (minimized.before, minimized.after);
+    eprintln!("Before: {minimization_before}, After: {minimization_after}");
+    if audit {
+        audit_minimization(trees(), &minimized.trees)?;
+    }
+    let minimized: HashMap<_, _> = minimized.trees.into_iter().collect();
     for course in courses.iter_mut() {
         if let Some(new_tree) = minimized.get(&Qualification::Course(course.code().clone())) {
             *course.prerequisites_mut() = new_tree.clone();
         }
     }
+    memory.finish("minimize");
     eprintln!("Writing");
-    let mut output = File::create(output)?;
+    let mut output = compression::writer(output)?;
+    schema::write_header(&mut output)?;
+    let mut hash = hash::Fnv::new();
+    let mut subjects = BTreeMap::new();
     for result in courses.iter() {
-        serde_json::to_writer(&mut output, result)?;
+        let line = serde_json::to_vec(result)?;
+        output.write_all(&line)?;
         output.write_all(b"\n")?;
+        hash.write_all(&line)?;
+        *subjects
+            .entry(result.code().subject().to_string())
+            .or_default() += 1;
     }
-    Ok(())
+    output.finish()?;
+    let mut sections = compression::writer(sections)?;
+    for course in courses.iter() {
+        for section in course.sections() {
+            serde_json::to_writer(&mut sections, &section)?;
+            sections.write_all(b"\n")?;
+        }
+    }
+    sections.finish()?;
+    memory.finish("write");
+    Ok(trends::Summary {
+        time: trends::Summary::now(),
+        hash: hash.hex(),
+        courses: courses.len(),
+        with_prerequisites: courses
+            .iter()
+            .filter(|course| course.prerequisites().is_some())
+            .count(),
+        minimization_before,
+        minimization_after,
+        subjects,
+        memory: memory.into_vec(),
+        rejected: rejected.len(),
+    })
 }
 
-async fn stage1<P: AsRef<Path>>(output: P) -> io::Result<()> {
-    let terms = [
-        "201600", // Summer 2016
-        "201610", // Fall 2016
-        "201615", // Winter 2017
-        "201620", // Spring 2017
-        "201700", // Summer 2017
-        "201710", // Fall 2017
-        "201715", // Winter 2018
-        "201720", // Spring 2018
-        "201800", // Summer 2018
-        "201810", // Fall 2018
-        "201815", // Winter 2019
-        "201820", // Spring 2019
-        "201900", // Summer 2019
-        "201910", // Fall 2019
-        "201915", // Winter 2020
-        "201920", // Spring 2020
-        "202000", // Summer 2020
-        "202010", // Fall 2020
-        "202020", // Spring 2021
-        "202100", // Summer 2021
-        "202110", // Fall 2021
-        "202115", // Winter 2022
-        "202120", // Spring 2022
-        "202200", // Summer 2022
-        "202210", // Fall 2022
-        "202215", // Winter 2023
-        "202220", // Spring 2023
-    ];
-    let client = Client::builder().build().expect("client not available");
-    let mut output = tokio::fs::File::create(output).await.unwrap();
-    download::download(&client, &terms, 10, &mut output).await;
-    output.shutdown().await.unwrap();
-    Ok(())
+/// Where the terms found by probing are kept between downloads
+const PROBED_TERMS: &str = "cache/.terms.json";
+
+/// The terms the API has sections for, as probed before unless `reprobe`
+async fn probe_terms(
+    client: &Client,
+    settings: &download::Settings,
+    reprobe: bool,
+) -> io::Result<Vec<String>> {
+    if let Some(terms) = download::probed_terms(PROBED_TERMS)?.filter(|_| !reprobe) {
+        return Ok(terms);
+    }
+    // srcdb years are when the academic year starts, so last year's always has sections
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let year = (1970 + seconds / 31_556_952) as u16;
+    eprintln!("probing terms from 1990 to {}", year + 1);
+    let terms = download::probe_terms(client, &*settings.source, 1990..=year + 1, year - 1).await?;
+    eprintln!("found {} terms", terms.len());
+    std::fs::create_dir_all("cache")?;
+    compression::write(PROBED_TERMS, serde_json::to_vec(&terms)?)?;
+    Ok(terms)
+}
+
+async fn stage1<P: AsRef<Path>>(
+    client: &Client,
+    output: P,
+    terms: &[&str],
+    settings: &download::Settings,
+) -> io::Result<(manifest::Entry, download::Report)> {
+    let start = trends::Summary::now();
+    let mut output = compression::async_writer(output).await?;
+    let report = download::download(client, terms, settings, &mut output).await;
+    output.finish().await?;
+    let entry = manifest::Entry::new(start, trends::Summary::now(), &report);
+    Ok((entry, report))
+}
+
+/// Like `stage1`, but each term goes to its own `<directory>/<srcdb>.jsonl`, so
+/// that one term can be downloaded again without touching the others. The terms
+/// still download together, sharing one limit on requests in flight.
+async fn stage1_sharded<P: AsRef<Path>>(
+    client: &Client,
+    directory: P,
+    terms: &[&str],
+    settings: download::Settings,
+) -> io::Result<download::Report> {
+    std::fs::create_dir_all(&directory)?;
+    let start = trends::Summary::now();
+    let files: Vec<String> = terms.iter().map(|term| format!("{term}.jsonl")).collect();
+    let mut shards = Vec::new();
+    for file in files.iter() {
+        shards.push(compression::async_writer(directory.as_ref().join(file)).await?);
+    }
+    let (report, hashes) = download::download_sharded(client, terms, &settings, &mut shards).await;
+    for (shard, ((term, file), hash)) in shards.into_iter().zip(terms.iter().zip(files).zip(hashes))
+    {
+        shard.finish().await?;
+        let shard_report = download::Report {
+            terms: report
+                .terms
+                .get(*term)
+                .map(|counts| (term.to_string(), counts.clone()))
+                .into_iter()
+                .collect(),
+            hash,
+            requests: download::Requests::default(),
+        };
+        let entry = manifest::Entry::new(start, trends::Summary::now(), &shard_report);
+        manifest::record_shard(&directory, &file, entry)?;
+    }
+    Ok(report)
 }
 
 fn file_at(path: &str, extension: &str) -> io::Result<File> {
@@ -118,6 +1049,10 @@ fn file_at(path: &str, extension: &str) -> io::Result<File> {
             .open(format!("{path}{number}{extension}"));
         match file {
             Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Ok(file) => {
+                summary::output(format!("{path}{number}{extension}"));
+                return Ok(file);
+            }
             file => return file,
         }
     }
@@ -0,0 +1,171 @@
+use crate::compression;
+use crate::download::{Report, TermReport};
+use crate::hash::Fnv;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `manifest.json`, describing the downloads in its directory so that `process`
+/// can check it is reading complete and uncorrupted files.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Keyed by path relative to the manifest's directory
+    files: BTreeMap<String, Entry>,
+}
+
+/// One downloaded file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub start: u64,
+    pub end: u64,
+    /// FNV-1a of the uncompressed contents
+    pub hash: String,
+    pub records: usize,
+    pub terms: BTreeMap<String, TermReport>,
+}
+
+impl Entry {
+    pub fn new(start: u64, end: u64, report: &Report) -> Entry {
+        Entry {
+            start,
+            end,
+            hash: report.hash.hex(),
+            records: report.terms.values().map(|term| term.records).sum(),
+            terms: report.terms.clone(),
+        }
+    }
+
+    fn failures(&self) -> usize {
+        self.terms.values().map(|term| term.failures).sum()
+    }
+
+    fn unfinished(&self) -> usize {
+        self.terms.values().map(|term| term.unfinished).sum()
+    }
+}
+
+impl Manifest {
+    fn path(directory: &Path) -> PathBuf {
+        directory.join("manifest.json")
+    }
+
+    /// An empty manifest if there is none yet
+    pub fn load<P: AsRef<Path>>(directory: P) -> io::Result<Manifest> {
+        match fs::read(Manifest::path(directory.as_ref())) {
+            Ok(json) => Ok(serde_json::from_slice(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, directory: P) -> io::Result<()> {
+        compression::write(
+            Manifest::path(directory.as_ref()),
+            serde_json::to_vec_pretty(self)?,
+        )
+    }
+}
+
+/// The manifest directory for `path`, and `path`'s key in that manifest
+fn location(path: &Path) -> (PathBuf, String) {
+    let directory = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (directory, name)
+}
+
+/// Adds or replaces the entry for the file at `path` in the manifest beside it
+pub fn record<P: AsRef<Path>>(path: P, entry: Entry) -> io::Result<()> {
+    let (directory, key) = location(path.as_ref());
+    let mut manifest = Manifest::load(&directory)?;
+    manifest.files.insert(key, entry);
+    manifest.save(&directory)
+}
+
+/// Like `record`, but for the shard `file` of the directory `shards`
+pub fn record_shard<P: AsRef<Path>>(shards: P, file: &str, entry: Entry) -> io::Result<()> {
+    let (directory, key) = location(shards.as_ref());
+    let mut manifest = Manifest::load(&directory)?;
+    manifest.files.insert(format!("{key}/{file}"), entry);
+    manifest.save(&directory)
+}
+
+/// Checks the file (or directory of shards) at `path` against its manifest,
+/// erroring if anything was changed or cut short since it was downloaded
+pub fn verify<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let (directory, key) = location(path.as_ref());
+    let manifest = Manifest::load(&directory)?;
+    let prefix = format!("{key}/");
+    let entries: Vec<_> = manifest
+        .files
+        .iter()
+        .filter(|(file, _)| **file == key || file.starts_with(&prefix))
+        .collect();
+    if entries.is_empty() {
+        eprintln!("no manifest for {}, not verifying", path.as_ref().display());
+        return Ok(());
+    }
+    for (file, entry) in entries {
+        let mut hash = Fnv::new();
+        io::copy(&mut compression::reader(directory.join(file))?, &mut hash)?;
+        if hash.hex() != entry.hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{file} has hash {}, but its manifest says {} for {} records; it was changed or cut short",
+                    hash.hex(),
+                    entry.hash,
+                    entry.records,
+                ),
+            ));
+        }
+        if entry.failures() > 0 {
+            crate::summary::warn(format!(
+                "{} requests failed while downloading {file}, so it is incomplete",
+                entry.failures()
+            ));
+        }
+        if entry.unfinished() > 0 {
+            crate::summary::warn(format!(
+                "{file} stopped early with {} sections left, so it is incomplete",
+                entry.unfinished()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verification() {
+        let directory = std::env::temp_dir().join(format!("cab-manifest-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("cab.jsonl");
+        fs::write(&path, "{}\n{}\n").unwrap();
+        let mut report = Report::default();
+        report.hash.write_all(b"{}\n{}\n").unwrap();
+        report.terms.insert(
+            String::from("202210"),
+            TermReport {
+                records: 2,
+                ..TermReport::default()
+            },
+        );
+        record(&path, Entry::new(0, 1, &report)).unwrap();
+        verify(&path).unwrap();
+        fs::write(&path, "{}\n").unwrap();
+        assert!(verify(&path).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
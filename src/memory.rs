@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The system allocator, counting allocations and the bytes allocated. Only
+/// installed with the `memory` feature, since the counting isn't free.
+pub struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "memory")]
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+impl Counting {
+    fn grew(size: usize) {
+        let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(current, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            Counting::grew(layout.size());
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        let new = System.realloc(pointer, layout, size);
+        if !new.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            Counting::grew(size);
+        }
+        new
+    }
+}
+
+/// The memory one stage of a run used. Heap figures are only known with the
+/// `memory` feature, and peak RSS only on Linux.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub stage: String,
+    pub peak_rss_kib: Option<u64>,
+    pub peak_heap_bytes: Option<usize>,
+    pub allocations: Option<usize>,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.stage)?;
+        match self.peak_rss_kib {
+            Some(kib) => write!(f, " {} peak RSS", Size(kib * 1024))?,
+            None => write!(f, " peak RSS unknown")?,
+        }
+        if let Some(bytes) = self.peak_heap_bytes {
+            write!(f, ", {} peak heap", Size(bytes as u64))?;
+        }
+        if let Some(allocations) = self.allocations {
+            write!(f, " in {allocations} allocations")?;
+        }
+        Ok(())
+    }
+}
+
+/// A number of bytes in the largest binary unit that keeps it at least 1
+struct Size(u64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.0 as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit + 1 < units.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => write!(f, "{} B", self.0),
+            _ => write!(f, "{size:.1} {}", units[unit]),
+        }
+    }
+}
+
+/// Measures each stage of a run from when the previous one finished
+pub struct Stages {
+    stages: Vec<Usage>,
+}
+
+impl Stages {
+    pub fn start() -> Stages {
+        reset();
+        Stages { stages: Vec::new() }
+    }
+
+    pub fn finish(&mut self, stage: &str) {
+        let usage = Usage {
+            stage: stage.to_string(),
+            peak_rss_kib: peak_rss_kib(),
+            peak_heap_bytes: counting().then(|| PEAK.load(Ordering::Relaxed)),
+            allocations: counting().then(|| ALLOCATIONS.load(Ordering::Relaxed)),
+        };
+        eprintln!("Memory for {usage}");
+        self.stages.push(usage);
+        reset();
+    }
+
+    pub fn into_vec(self) -> Vec<Usage> {
+        self.stages
+    }
+}
+
+fn counting() -> bool {
+    cfg!(feature = "memory")
+}
+
+/// Starts the peaks over from the memory in use now
+fn reset() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    // Resets VmHWM, on Linux 4.0 and later
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+/// The high water mark of the resident set size since the last reset
+fn peak_rss_kib() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counting, Usage, CURRENT, PEAK};
+    use std::alloc::{GlobalAlloc, Layout};
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn counting() {
+        let layout = Layout::from_size_align(1 << 20, 8).unwrap();
+        unsafe {
+            let pointer = Counting.alloc(layout);
+            assert!(!pointer.is_null());
+            assert!(CURRENT.load(Ordering::Relaxed) >= 1 << 20);
+            assert!(PEAK.load(Ordering::Relaxed) >= 1 << 20);
+            Counting.dealloc(pointer, layout);
+        }
+    }
+
+    #[test]
+    fn display() {
+        let usage = Usage {
+            stage: String::from("process"),
+            peak_rss_kib: Some(1536 * 1024),
+            peak_heap_bytes: Some(1000),
+            allocations: Some(42),
+        };
+        assert_eq!(
+            usage.to_string(),
+            "process: 1.5 GiB peak RSS, 1000 B peak heap in 42 allocations"
+        );
+        let usage = Usage {
+            peak_rss_kib: None,
+            peak_heap_bytes: None,
+            allocations: None,
+            ..usage
+        };
+        assert_eq!(usage.to_string(), "process: peak RSS unknown");
+    }
+}
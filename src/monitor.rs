@@ -0,0 +1,297 @@
+use crate::compression;
+use crate::download;
+use crate::download::CatalogSource;
+use crate::process;
+use crate::trends;
+use crate::trends::Summary;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A section to poll, written `<srcdb>:<crn>` on the command line
+#[derive(Debug, Clone)]
+pub struct Section {
+    term: String,
+    crn: String,
+}
+
+impl FromStr for Section {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (term, crn) = string.split_once(':').ok_or(())?;
+        Ok(Section {
+            term: term.to_string(),
+            crn: crn.to_string(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub srcdb: String,
+    pub crn: String,
+    pub seats: process::Seats,
+    pub enrollment: u16,
+    /// The course `watch` polled the section for
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+/// The current seats of `section`, or `None` after logging why there aren't any
+async fn snapshot(
+    client: &Client,
+    source: &dyn CatalogSource,
+    section: &Section,
+) -> Option<Snapshot> {
+    let detail = match download::detail(client, source, &section.term, &section.crn).await {
+        Ok(detail) => detail,
+        Err(e) => {
+            eprintln!("seat lookup {}/{} failed: {e:?}", section.term, section.crn);
+            return None;
+        }
+    };
+    let Some(seats) = process::seats_from_detail(&detail) else {
+        eprintln!("no seats found for {}/{}", section.term, section.crn);
+        return None;
+    };
+    Some(Snapshot {
+        time: Summary::now(),
+        srcdb: section.term.clone(),
+        crn: section.crn.clone(),
+        seats,
+        enrollment: seats.enrollment(),
+        code: None,
+    })
+}
+
+/// Polls the seats of every section each `interval`, appending snapshots to `output`.
+/// Runs forever unless `rounds` is given.
+pub async fn monitor<P: AsRef<Path>>(
+    client: &Client,
+    source: &dyn CatalogSource,
+    sections: &[Section],
+    interval: Duration,
+    rounds: Option<usize>,
+    output: P,
+) -> io::Result<()> {
+    let mut output = File::options().create(true).append(true).open(output)?;
+    let mut ticks = tokio::time::interval(interval);
+    let mut round = 0;
+    while rounds.is_none_or(|rounds| round < rounds) {
+        ticks.tick().await;
+        round += 1;
+        for section in sections {
+            if let Some(snapshot) = snapshot(client, source, section).await {
+                serde_json::to_writer(&mut output, &snapshot)?;
+                output.write_all(b"\n")?;
+            }
+        }
+        output.flush()?;
+        eprint!("[{round}] polled {} sections\r", sections.len());
+    }
+    Ok(())
+}
+
+/// Like `monitor`, for every section of each of `courses` in `term`, and redraws
+/// `<charts>/<SUBJ-NUMBER>.svg`, the fill rate of each course so far, after every round
+#[allow(clippy::too_many_arguments)]
+pub async fn watch<P: AsRef<Path>, Q: AsRef<Path>>(
+    client: &Client,
+    source: &dyn CatalogSource,
+    term: &str,
+    courses: &[String],
+    interval: Duration,
+    rounds: Option<usize>,
+    output: P,
+    charts: Q,
+) -> io::Result<()> {
+    let mut sections = Vec::new();
+    for code in courses {
+        let crns = download::sections_of(client, source, term, code).await?;
+        if crns.is_empty() {
+            crate::summary::warn(format!("no sections of {code} in {term}"));
+        }
+        sections.extend(crns.into_iter().map(|crn| {
+            let section = Section {
+                term: term.to_string(),
+                crn,
+            };
+            (code, section)
+        }));
+    }
+    eprintln!(
+        "watching {} sections of {} courses",
+        sections.len(),
+        courses.len()
+    );
+    let output = output.as_ref();
+    let mut file = File::options().create(true).append(true).open(output)?;
+    let mut ticks = tokio::time::interval(interval);
+    let mut round = 0;
+    while rounds.is_none_or(|rounds| round < rounds) {
+        ticks.tick().await;
+        round += 1;
+        for (code, section) in sections.iter() {
+            if let Some(mut snapshot) = snapshot(client, source, section).await {
+                snapshot.code = Some(code.to_string());
+                serde_json::to_writer(&mut file, &snapshot)?;
+                file.write_all(b"\n")?;
+            }
+        }
+        file.flush()?;
+        write_charts(output, &charts)?;
+        eprint!("[{round}] polled {} sections\r", sections.len());
+    }
+    Ok(())
+}
+
+/// Charts every course in the snapshots at `input` into `directory`
+fn write_charts<P: AsRef<Path>, Q: AsRef<Path>>(input: P, directory: Q) -> io::Result<()> {
+    let snapshots: Vec<Snapshot> = serde_json::Deserializer::from_reader(File::open(input)?)
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    for (code, points) in fill_rates(&snapshots) {
+        let name = format!("{}.svg", code.replace(' ', "-"));
+        compression::write(directory.join(name), chart(&code, &points))?;
+    }
+    Ok(())
+}
+
+/// Each course's enrollment over capacity, summed over its sections, as of each
+/// snapshot in order. A section counts from its first snapshot on.
+pub fn fill_rates(snapshots: &[Snapshot]) -> BTreeMap<String, Vec<(u64, f32)>> {
+    let mut latest: BTreeMap<&str, BTreeMap<(&str, &str), process::Seats>> = BTreeMap::new();
+    let mut ret: BTreeMap<String, Vec<(u64, f32)>> = BTreeMap::new();
+    for snapshot in snapshots {
+        let Some(code) = snapshot.code.as_deref() else {
+            continue;
+        };
+        let sections = latest.entry(code).or_default();
+        sections.insert((&snapshot.srcdb, &snapshot.crn), snapshot.seats);
+        let enrollment: u32 = sections
+            .values()
+            .map(|seats| seats.enrollment() as u32)
+            .sum();
+        let capacity: i32 = sections.values().map(|seats| seats.max as i32).sum();
+        if capacity > 0 {
+            let fill = enrollment as f32 / capacity as f32;
+            ret.entry(code.to_string())
+                .or_default()
+                .push((snapshot.time, fill));
+        }
+    }
+    ret
+}
+
+/// A line chart of `points`, with the fill rate up the side and hours along the bottom
+fn chart(code: &str, points: &[(u64, f32)]) -> String {
+    const WIDTH: f32 = 600.0;
+    const HEIGHT: f32 = 300.0;
+    const LEFT: f32 = 50.0;
+    const TOP: f32 = 30.0;
+    let start = points.first().map_or(0, |&(time, _)| time);
+    let end = points.last().map_or(0, |&(time, _)| time).max(start + 1);
+    let top = points.iter().map(|&(_, fill)| fill).fold(1.0, f32::max);
+    let x = |time: u64| LEFT + WIDTH * (time - start) as f32 / (end - start) as f32;
+    let y = |fill: f32| TOP + HEIGHT * (1.0 - fill / top);
+    let mut ret = String::new();
+    writeln!(
+        ret,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+        LEFT * 2.0 + WIDTH,
+        TOP * 2.0 + HEIGHT,
+    )
+    .unwrap();
+    writeln!(
+        ret,
+        r#"<text x="{LEFT}" y="{}" style="font-family:monospace;font-size:14px">{code} from {}</text>"#,
+        TOP - 10.0,
+        trends::date(start),
+    )
+    .unwrap();
+    for fill in [0.0, 0.5, 1.0]
+        .into_iter()
+        .chain((top > 1.0).then_some(top))
+    {
+        writeln!(
+            ret,
+            r#"<line x1="{LEFT}" x2="{}" y1="{y}" y2="{y}" style="stroke:#cccccc" /><text x="0" y="{}" style="font-family:monospace;font-size:10px">{:.0}%</text>"#,
+            LEFT + WIDTH,
+            y(fill) + 3.0,
+            fill * 100.0,
+            y = y(fill),
+        )
+        .unwrap();
+    }
+    writeln!(
+        ret,
+        r#"<text x="{}" y="{}" style="font-family:monospace;font-size:10px">{:.1} hours</text>"#,
+        LEFT + WIDTH - 60.0,
+        TOP + HEIGHT + 20.0,
+        (end - start) as f32 / 3600.0,
+    )
+    .unwrap();
+    let line: Vec<_> = points
+        .iter()
+        .map(|&(time, fill)| format!("{:.1},{:.1}", x(time), y(fill)))
+        .collect();
+    writeln!(
+        ret,
+        r#"<polyline points="{}" style="fill:none;stroke:#cc3333;stroke-width:2" />"#,
+        line.join(" ")
+    )
+    .unwrap();
+    ret.push_str("</svg>\n");
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_rates, Snapshot};
+    use crate::process::Seats;
+
+    fn snapshot(time: u64, crn: &str, code: Option<&str>, max: i16, available: i16) -> Snapshot {
+        Snapshot {
+            time,
+            srcdb: String::from("202310"),
+            crn: crn.to_string(),
+            seats: Seats {
+                max,
+                available,
+                waitlist: None,
+            },
+            enrollment: (max - available) as u16,
+            code: code.map(String::from),
+        }
+    }
+
+    #[test]
+    fn fill() {
+        let snapshots = [
+            snapshot(0, "1", Some("CSCI 0150"), 100, 50),
+            snapshot(1, "2", Some("CSCI 0150"), 100, 100),
+            snapshot(2, "3", None, 10, 0),
+            snapshot(60, "1", Some("CSCI 0150"), 100, 0),
+            snapshot(61, "2", Some("CSCI 0150"), 100, 20),
+        ];
+        let rates = fill_rates(&snapshots);
+        assert_eq!(
+            rates["CSCI 0150"],
+            [(0, 0.5), (1, 0.25), (60, 0.5), (61, 0.9)]
+        );
+        assert_eq!(rates.len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::process::SemesterRange;
 use crate::restrictions::{CourseCode, ExamScore, Operator, PrerequisiteTree, Qualification};
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -11,8 +12,7 @@ use std::fmt::Formatter;
 /// top      | any_expr Eoi
 /// any_expr | and_expr (Any and_expr)*
 /// and_expr | base (All base)*
-/// base     | Course \| ExamScore \| LeftParen any_expr RightParen
-
+/// base     | Course \| CourseGrade \| ExamScore \| SemesterLevel \| Standing \| InstructorPermission \| Membership \| Not base \| LeftParen any_expr RightParen
 impl<'a> TryFrom<&'a str> for PrerequisiteTree {
     type Error = PrerequisiteStringError<'a>;
     fn try_from(string: &'a str) -> Result<Self, Self::Error> {
@@ -23,8 +23,8 @@ impl<'a> TryFrom<&'a str> for PrerequisiteTree {
     }
 }
 
-fn parse_any_expr<'a, 'b>(
-    tokens: &'b mut TokenStream<'a>,
+fn parse_any_expr<'a>(
+    tokens: &mut TokenStream<'a>,
 ) -> Result<PrerequisiteTree, PrerequisiteStringError<'a>> {
     let mut ret = Vec::new();
     let token = parse_all_expr(tokens)?;
@@ -43,8 +43,8 @@ fn parse_any_expr<'a, 'b>(
     }
 }
 
-fn parse_all_expr<'a, 'b>(
-    tokens: &'b mut TokenStream<'a>,
+fn parse_all_expr<'a>(
+    tokens: &mut TokenStream<'a>,
 ) -> Result<Option<PrerequisiteTree>, PrerequisiteStringError<'a>> {
     let mut ret = Vec::new();
     let token = parse_bottom(tokens)?;
@@ -63,8 +63,8 @@ fn parse_all_expr<'a, 'b>(
     }
 }
 
-fn parse_bottom<'a, 'b>(
-    tokens: &'b mut TokenStream<'a>,
+fn parse_bottom<'a>(
+    tokens: &mut TokenStream<'a>,
 ) -> Result<Option<PrerequisiteTree>, PrerequisiteStringError<'a>> {
     let token = tokens.peek_token()?;
     tokens.consume_token(&token.kind)?;
@@ -72,6 +72,8 @@ fn parse_bottom<'a, 'b>(
     match token.kind {
         TokenKind::Qualification(qual) => Ok(Some(PrerequisiteTree::Qualification(qual))),
         TokenKind::GraduateStudentWaive => Ok(None),
+        TokenKind::Operator(Operator::Not) => Ok(parse_bottom(tokens)?
+            .map(|child| PrerequisiteTree::Operator(Operator::Not, vec![child]))),
         TokenKind::LeftParen => {
             let ret = parse_any_expr(tokens)?;
             tokens.consume_token(&TokenKind::RightParen)?;
@@ -121,7 +123,7 @@ impl<'a> TryFrom<&'a str> for TokenStream<'a> {
                 let matching_token = &token.kind;
 
                 match matching_token {
-                    TokenKind::Operator(conj) => {
+                    TokenKind::Operator(conj @ (Operator::Any | Operator::All)) => {
                         conjunctives.insert(paren_level, *conj);
                     }
                     TokenKind::LeftParen => paren_level += 1,
@@ -195,9 +197,22 @@ impl fmt::Display for TokenKind {
     }
 }
 
-fn tokenize(string: &str) -> Result<Vec<Token>, PrerequisiteStringError<'_>> {
+/// A course number takes the subject before it if it doesn't have its own, as in
+/// "CSCI 0150 or 0170". `None` if there is no subject before it.
+fn course_code(
+    last_subject: &mut Option<String>,
+    subject: Option<regex::Match>,
+    number: &str,
+) -> Option<CourseCode> {
+    if let Some(subject) = subject {
+        *last_subject = Some(subject.as_str().to_string());
+    }
+    Some(CourseCode::new(last_subject.clone()?, number.to_string()).unwrap())
+}
+
+fn tokenize(string: &str) -> Result<Vec<Token<'_>>, PrerequisiteStringError<'_>> {
     static TOKEN: Lazy<Regex> = Lazy::new(|| {
-        Regex::new(r"^( |and|or|,|\(|\)|minimum score of WAIVE in 'Graduate Student PreReq'|minimum score of (?P<score>\d*?) in '(?P<exam>.*?)'|((?P<subj>[A-Z]{3,4}) )?(?P<num>\d{4}[A-Z]?)\*?)").unwrap()
+        Regex::new(r"^( |and|or|not|,|\(|\)|minimum score of WAIVE in 'Graduate Student PreReq'|minimum score of (?P<score>\d*?) in '(?P<exam>.*?)'|(?i:semester level) (?P<level>\d{2}|GM|GP) or (?:above|higher)|(?i:(?P<standing>sophomore|junior|senior) standing)|(?P<permission>(?i:instructor permission|permission of (?:the )?instructor))|(?P<membership>program|concentration) in '(?P<name>[^']*)'|(?i:minimum grade of) (?P<grade>[ABC]) in ((?P<gsubj>[A-Z]{3,4}) )?(?P<gnum>\d{4}[A-Z]?)|((?P<subj>[A-Z]{3,4}) )?(?P<num>\d{4}[A-Z]?)\*?)").unwrap()
     });
 
     let mut last_subject = None;
@@ -227,6 +242,7 @@ fn tokenize(string: &str) -> Result<Vec<Token>, PrerequisiteStringError<'_>> {
             }
             "and" => TokenKind::Operator(Operator::All),
             "or" => TokenKind::Operator(Operator::Any),
+            "not" => TokenKind::Operator(Operator::Not),
             "," => TokenKind::Comma,
             "(" => TokenKind::LeftParen,
             ")" => TokenKind::RightParen,
@@ -236,21 +252,48 @@ fn tokenize(string: &str) -> Result<Vec<Token>, PrerequisiteStringError<'_>> {
                     score: captures["score"].parse().unwrap(),
                 }))
             }
+            _ if captures.name("level").is_some() => {
+                TokenKind::Qualification(Qualification::SemesterLevel(SemesterRange::at_least(
+                    captures["level"].parse().unwrap(),
+                )))
+            }
+            _ if captures.name("membership").is_some() => {
+                let name = captures["name"].to_string();
+                TokenKind::Qualification(match &captures["membership"] {
+                    "program" => Qualification::Program(name),
+                    _ => Qualification::Concentration(name),
+                })
+            }
+            _ if captures.name("permission").is_some() => {
+                TokenKind::Qualification(Qualification::InstructorPermission)
+            }
+            _ if captures.name("standing").is_some() => {
+                // the first semester of the year
+                let semester = match captures["standing"].to_lowercase().as_str() {
+                    "sophomore" => "03",
+                    "junior" => "05",
+                    _ => "07",
+                };
+                TokenKind::Qualification(Qualification::SemesterLevel(SemesterRange::at_least(
+                    semester.parse().unwrap(),
+                )))
+            }
+            _ if captures.name("grade").is_some() => {
+                let code =
+                    course_code(&mut last_subject, captures.name("gsubj"), &captures["gnum"])
+                        .ok_or(PrerequisiteStringError::NoSubjectContext { span })?;
+                TokenKind::Qualification(Qualification::CourseGrade {
+                    code,
+                    min_grade: captures["grade"].parse().unwrap(),
+                })
+            }
             _ if captures.name("num").is_some() => {
-                if let Some(subject) = captures.name("subj") {
-                    let subject = subject.as_str().parse().unwrap();
-                    last_subject = Some(subject);
+                let code = course_code(&mut last_subject, captures.name("subj"), &captures["num"])
+                    .ok_or(PrerequisiteStringError::NoSubjectContext { span })?;
+                match entire_match.ends_with('*') {
+                    true => TokenKind::Qualification(Qualification::Concurrent(code)),
+                    false => TokenKind::Qualification(Qualification::Course(code)),
                 }
-
-                TokenKind::Qualification(Qualification::Course(
-                    CourseCode::new(
-                        last_subject
-                            .clone()
-                            .ok_or(PrerequisiteStringError::NoSubjectContext { span })?,
-                        captures["num"].parse().unwrap(),
-                    )
-                    .unwrap(),
-                ))
             }
             _ => unreachable!(),
         };
@@ -315,3 +358,200 @@ impl<'a> fmt::Debug for PrerequisiteStringError<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::process::SemesterRange;
+    use crate::restrictions::{
+        CourseCode, ExamScore, Grade, Operator, PrerequisiteTree, Qualification, StudentProfile,
+    };
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn display() {
+        let string = "CSCI 0150 and (MATH 0090 or MATH 0100)";
+        let tree = PrerequisiteTree::try_from(string).unwrap();
+        assert_eq!(tree.to_string(), string);
+    }
+
+    #[test]
+    fn semester_level() {
+        let tree = PrerequisiteTree::try_from("CSCI 0150 and semester level 05 or above").unwrap();
+        let code = CourseCode::try_from("CSCI 0150").unwrap();
+        let level = SemesterRange::at_least("05".parse().unwrap());
+        assert_eq!(
+            tree,
+            PrerequisiteTree::Operator(
+                Operator::All,
+                vec![
+                    PrerequisiteTree::Qualification(Qualification::Course(code.clone())),
+                    PrerequisiteTree::Qualification(Qualification::SemesterLevel(level)),
+                ]
+            )
+        );
+        let mut profile = StudentProfile {
+            qualifications: HashSet::from([Qualification::Course(code)]),
+            semester_level: "04".parse().unwrap(),
+        };
+        assert!(!tree.evaluate(&profile));
+        profile.semester_level = "06".parse().unwrap();
+        assert!(tree.evaluate(&profile));
+    }
+
+    #[test]
+    fn minimum_grade() {
+        let tree = PrerequisiteTree::try_from("Minimum Grade of B in MATH 0100 or 0170*").unwrap();
+        let math = |number| CourseCode::try_from(format!("MATH {number}").as_str()).unwrap();
+        assert_eq!(
+            tree,
+            PrerequisiteTree::Operator(
+                Operator::Any,
+                vec![
+                    PrerequisiteTree::Qualification(Qualification::CourseGrade {
+                        code: math("0100"),
+                        min_grade: Grade::B,
+                    }),
+                    PrerequisiteTree::Qualification(Qualification::Concurrent(math("0170"))),
+                ]
+            )
+        );
+        assert_eq!(
+            tree.to_string(),
+            "minimum grade of B in MATH 0100 or MATH 0170*"
+        );
+        assert!(PrerequisiteTree::try_from("minimum grade of B in 0100").is_err());
+    }
+
+    #[test]
+    fn standing() {
+        let tree = PrerequisiteTree::try_from("Junior standing or CSCI 0330").unwrap();
+        let junior = SemesterRange::at_least("05".parse().unwrap());
+        assert_eq!(
+            tree,
+            PrerequisiteTree::Operator(
+                Operator::Any,
+                vec![
+                    PrerequisiteTree::Qualification(Qualification::SemesterLevel(junior)),
+                    PrerequisiteTree::Qualification(Qualification::Course(
+                        CourseCode::try_from("CSCI 0330").unwrap()
+                    )),
+                ]
+            )
+        );
+        let mut profile = StudentProfile {
+            qualifications: HashSet::new(),
+            semester_level: "04".parse().unwrap(),
+        };
+        assert!(!tree.evaluate(&profile));
+        profile.semester_level = "05".parse().unwrap();
+        assert!(tree.evaluate(&profile));
+    }
+
+    #[test]
+    fn instructor_permission() {
+        let tree = PrerequisiteTree::try_from("CSCI 1010 or Permission of the Instructor").unwrap();
+        assert_eq!(tree.to_string(), "CSCI 1010 or instructor permission");
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(
+            json["any"][1],
+            serde_json::json!({"instructor_permission": true})
+        );
+        assert_eq!(
+            serde_json::from_value::<PrerequisiteTree>(json).unwrap(),
+            tree
+        );
+        let profile = StudentProfile {
+            qualifications: HashSet::from([Qualification::InstructorPermission]),
+            semester_level: "01".parse().unwrap(),
+        };
+        assert!(tree.evaluate(&profile));
+    }
+
+    fn random_tree(rng: &mut StdRng, depth: usize) -> PrerequisiteTree {
+        if depth == 0 || rng.gen_bool(0.4) {
+            let codes = ["CSCI 0150", "MATH 0100", "ENGL 0900A", "APMA 1650"];
+            let code = CourseCode::try_from(codes[rng.gen_range(0..codes.len())]).unwrap();
+            let semester = ["01", "03", "05", "07", "GM"][rng.gen_range(0..5)];
+            let grade = [Grade::A, Grade::B, Grade::C][rng.gen_range(0..3)];
+            return PrerequisiteTree::Qualification(match rng.gen_range(0..8) {
+                0 => Qualification::Course(code),
+                1 => Qualification::Concurrent(code),
+                2 => Qualification::CourseGrade {
+                    code,
+                    min_grade: grade,
+                },
+                3 => Qualification::ExamScore(ExamScore {
+                    exam: String::from("AP Calculus BC"),
+                    score: rng.gen_range(1..6),
+                }),
+                4 => {
+                    Qualification::SemesterLevel(SemesterRange::at_least(semester.parse().unwrap()))
+                }
+                5 => Qualification::Program(String::from("Computer Science (SCB)")),
+                6 => Qualification::Concentration(String::from("Computer Science")),
+                _ => Qualification::InstructorPermission,
+            });
+        }
+        // including the shapes the parser never gives, which read back simplified
+        let (operator, children) = match rng.gen_range(0..5) {
+            0..=1 => (Operator::Any, rng.gen_range(0..4)),
+            2..=3 => (Operator::All, rng.gen_range(0..4)),
+            _ => (Operator::Not, rng.gen_range(0..3)),
+        };
+        let children = (0..children).map(|_| random_tree(rng, depth - 1)).collect();
+        PrerequisiteTree::Operator(operator, children)
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let mut rng = StdRng::seed_from_u64(4326);
+        let mut empty = 0;
+        for _ in 0..2000 {
+            let tree = random_tree(&mut rng, 4);
+            let string = tree.to_string();
+            let parsed = PrerequisiteTree::try_from(string.as_str());
+            let has_empty = tree.fold(&mut |_| false, &mut |_, children: Vec<bool>| {
+                children.is_empty() || children.contains(&true)
+            });
+            if has_empty {
+                assert!(parsed.is_err(), "{string}");
+                empty += 1;
+                continue;
+            }
+            let parsed = parsed.unwrap_or_else(|e| panic!("{string}: {e:?}"));
+            assert_eq!(parsed, tree.simplified(), "{string}");
+            let satisfied =
+                |tree: &PrerequisiteTree, have: &HashSet<Qualification>| tree.satisfied_by(have);
+            let have: HashSet<_> = tree.qualifications().take(2).cloned().collect();
+            assert_eq!(
+                satisfied(&parsed, &have),
+                satisfied(&tree, &have),
+                "{string}"
+            );
+        }
+        assert!((1..2000).contains(&empty));
+
+        let corrections = include_str!("../resources/prerequisite_corrections.txt");
+        for line in corrections.lines().filter(|line| !line.is_empty()) {
+            let (_, string) = line.split_once(';').unwrap();
+            let tree = PrerequisiteTree::try_from(string).unwrap();
+            let displayed = tree.to_string();
+            let parsed = PrerequisiteTree::try_from(displayed.as_str());
+            assert_eq!(parsed.as_ref().ok(), Some(&tree), "{displayed}");
+        }
+
+        let course = |code| PrerequisiteTree::try_from(code).unwrap();
+        let both = PrerequisiteTree::Operator(
+            Operator::Not,
+            vec![course("CSCI 0150"), course("MATH 0100")],
+        );
+        assert_eq!(both.to_string(), "not (CSCI 0150 and MATH 0100)");
+        let not_one =
+            serde_json::json!({"not": [{"course": {"subject": "CSCI", "number": "0150"}}]});
+        assert!(serde_json::from_value::<PrerequisiteTree>(not_one).is_ok());
+        let not_two = serde_json::to_value(&both).unwrap();
+        assert!(serde_json::from_value::<PrerequisiteTree>(not_two).is_err());
+    }
+}
@@ -1,20 +1,37 @@
+use crate::annotations::Annotation;
+use crate::hash::Fnv;
+use crate::html;
+use crate::instructor;
+use crate::registration::{self, Sentence};
 use crate::restrictions::CourseCode;
+use crate::restrictions::Operator;
 use crate::restrictions::PrerequisiteTree;
+use crate::restrictions::Qualification;
+use crate::term::Term;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::num::ParseIntError;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::path::Path;
 
 use once_cell::sync::Lazy;
-use regex::NoExpand;
 use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::de;
+use serde_json::de::IoRead;
 use serde_json::StreamDeserializer;
+use serde_json::Value;
 use std::convert::Infallible;
 use std::fmt;
 use std::iter;
 use std::str::FromStr;
+use std::thread;
 
 fn yes_or_no(string: &str) -> Option<bool> {
     match string {
@@ -24,20 +41,46 @@ fn yes_or_no(string: &str) -> Option<bool> {
     }
 }
 
-fn enrollment_from_seats(string: &str) -> Option<u16> {
-    static SEATS_MAX: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r#"<span class="seats_max">(\d+?)</span>"#).unwrap());
-    static SEATS_AVAILABLE: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r#"<span class="seats_avail">(-?\d+?)</span>"#).unwrap());
-    let max: i16 = match SEATS_MAX.captures(string) {
-        Some(captures) => captures.get(1).unwrap().as_str().parse().unwrap(),
-        None => return None,
-    };
-    let available: i16 = match SEATS_AVAILABLE.captures(string) {
-        Some(captures) => captures.get(1).unwrap().as_str().parse().unwrap(),
-        None => return None,
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seats {
+    pub max: i16,
+    /// Negative when the section is over-enrolled
+    pub available: i16,
+    /// Students waiting for a seat, for sections with a waitlist
+    #[serde(default)]
+    pub waitlist: Option<u16>,
+}
+
+impl Seats {
+    /// None enrolled, rather than a wrapped count, when the API reports more seats
+    /// available than the maximum
+    pub fn enrollment(&self) -> u16 {
+        self.max.saturating_sub(self.available).max(0) as u16
+    }
+}
+
+fn seats(string: &str) -> Option<Seats> {
+    let nodes = html::parse(string);
+    let number = |class| -> Option<i16> {
+        let span = html::select(&nodes, "span", class).into_iter().next()?;
+        span.text().trim().parse().ok()
     };
-    Some((max - available) as u16)
+    Some(Seats {
+        max: number("seats_max")?,
+        available: number("seats_avail")?,
+        waitlist: number("seats_wait").map(|waitlist| waitlist.max(0) as u16),
+    })
+}
+
+/// Seats of a raw detail response
+pub fn seats_from_detail(json: &[u8]) -> Option<Seats> {
+    #[derive(Deserialize)]
+    struct Detail {
+        seats: String,
+    }
+
+    let detail: Detail = serde_json::from_slice(json).ok()?;
+    seats(&detail.seats)
 }
 
 fn enrollment_from_html(string: &str) -> Option<u16> {
@@ -73,42 +116,128 @@ impl FromStr for Title {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Demographics {
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Demographics {
     #[serde(default)]
     #[serde(alias = "FY")]
-    freshmen: u16,
+    freshmen: u32,
     #[serde(default)]
     #[serde(alias = "So")]
-    sophomores: u16,
+    sophomores: u32,
     #[serde(default)]
     #[serde(alias = "Jr")]
-    juniors: u16,
+    juniors: u32,
     #[serde(default)]
     #[serde(alias = "Sr")]
-    seniors: u16,
+    seniors: u32,
     #[serde(default)]
     #[serde(alias = "Gr")]
-    graduates: u16,
+    graduates: u32,
     #[serde(default)]
     #[serde(alias = "Oth")]
-    others: u16,
+    others: u32,
+}
+
+impl Demographics {
+    /// First years, sophomores, juniors, seniors, graduate students, and others
+    pub fn classes(&self) -> [u32; 6] {
+        [
+            self.freshmen,
+            self.sophomores,
+            self.juniors,
+            self.seniors,
+            self.graduates,
+            self.others,
+        ]
+    }
+
+    /// The students of both, class by class
+    fn add(self, other: &Demographics) -> Demographics {
+        Demographics {
+            freshmen: self.freshmen + other.freshmen,
+            sophomores: self.sophomores + other.sophomores,
+            juniors: self.juniors + other.juniors,
+            seniors: self.seniors + other.seniors,
+            graduates: self.graduates + other.graduates,
+            others: self.others + other.others,
+        }
+    }
+
+    /// Enrolled classes that `range` shouldn't have allowed to enroll
+    fn outside(&self, range: SemesterRange) -> Vec<(&'static str, u32)> {
+        let between =
+            |from, to| SemesterRange::to(to).intersection(SemesterRange::to(from).complement());
+        [
+            ("freshmen", self.freshmen, between(0, 2)),
+            ("sophomores", self.sophomores, between(2, 4)),
+            ("juniors", self.juniors, between(4, 6)),
+            ("seniors", self.seniors, between(6, 8)),
+            ("graduates", self.graduates, SemesterRange::GRADUATE),
+        ]
+        .into_iter()
+        .filter(|&(_, count, class)| count > 0 && class.intersection(range).is_empty())
+        .map(|(name, count, _)| (name, count))
+        .collect()
+    }
 }
 
-fn strip_html(string: &str) -> String {
-    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<.*?>"#).unwrap());
-    static AMP: Lazy<Regex> = Lazy::new(|| Regex::new(r#"&amp;"#).unwrap());
-    static LT: Lazy<Regex> = Lazy::new(|| Regex::new(r#"&lt;"#).unwrap());
-    static GT: Lazy<Regex> = Lazy::new(|| Regex::new(r#"&gt;"#).unwrap());
-    let string = TAG.replace_all(&string, NoExpand(""));
-    let string = AMP.replace_all(&string, NoExpand("&"));
-    let string = LT.replace_all(&string, NoExpand("<"));
-    let string = GT.replace_all(&string, NoExpand(">"));
-    string.to_string()
+/// Text without tags or character references, with accents composed, so that
+/// descriptions and prerequisites match however the catalog happened to encode them
+pub fn strip_html(string: &str) -> String {
+    text(&html::parse(string))
 }
 
-#[derive(Serialize, Deserialize)]
-struct Semester {
+/// Like `strip_html`, of nodes already parsed
+fn text(nodes: &[html::Node]) -> String {
+    let string = html::text(nodes).replace('\u{a0}', " ");
+    html::compose(&string).into_owned()
+}
+
+/// The text of each line of `string`, which ends after each paragraph, item,
+/// division, and break
+fn lines(string: &str) -> Vec<String> {
+    fn split(nodes: &[html::Node], lines: &mut Vec<Vec<html::Node>>) {
+        for node in nodes {
+            match node {
+                html::Node::Element(element) => {
+                    split(&element.children, lines);
+                    if matches!(element.name.as_str(), "p" | "li" | "div" | "br") {
+                        lines.push(Vec::new());
+                    }
+                }
+                text => lines.last_mut().unwrap().push(text.clone()),
+            }
+        }
+    }
+    let mut lines = vec![Vec::new()];
+    split(&html::parse(string), &mut lines);
+    lines.iter().map(|line| text(line)).collect()
+}
+
+/// The text of each `<li>` in `string`, or if it has none, the whole text, split at
+/// commas and line breaks, trimmed, and without empty items
+fn list_items(string: &str) -> Vec<String> {
+    let nodes = html::parse(string);
+    let items: Vec<_> = html::elements(&nodes)
+        .into_iter()
+        .filter(|element| element.name == "li")
+        .map(|item| text(&item.children))
+        .collect();
+    let items = match items.is_empty() {
+        true => vec![text(&nodes)],
+        false => items,
+    };
+    items
+        .iter()
+        .flat_map(|item| item.split([',', '\n']))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Semester {
     inner: u16,
 }
 
@@ -123,21 +252,30 @@ impl fmt::Display for Semester {
 }
 
 impl FromStr for Semester {
-    type Err = ParseIntError;
+    type Err = String;
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         let semester_number = match string {
             "GM" => 14,
             "GP" => 15,
             "F2" => 2,
-            s => s.parse()?,
+            s => s
+                .parse()
+                .map_err(|e| format!("invalid semester {s:?}: {e}"))?,
         };
-        Ok(Semester {
-            inner: semester_number - 1,
-        })
+        u16::checked_sub(semester_number, 1)
+            .and_then(Semester::new)
+            .ok_or_else(|| format!("invalid semester {string:?}"))
+    }
+}
+
+impl Semester {
+    /// One of the fifteen semesters a `SemesterRange` holds, counting from zero
+    fn new(inner: u16) -> Option<Semester> {
+        (inner < 15).then_some(Semester { inner })
     }
 }
 
-#[derive(Serialize, Deserialize, Copy, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Copy, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(try_from = "Vec<u16>")]
 #[serde(into = "Vec<u16>")]
 pub struct SemesterRange {
@@ -156,6 +294,19 @@ impl SemesterRange {
         }
     }
 
+    /// `semester` and every later one, including graduate semesters
+    pub const fn at_least(semester: Semester) -> SemesterRange {
+        SemesterRange::to(semester.inner).complement()
+    }
+
+    pub fn contains(self, semester: Semester) -> bool {
+        self.inner & (1 << semester.inner) != 0
+    }
+
+    pub fn earliest(self) -> Option<Semester> {
+        self.semesters().next()
+    }
+
     pub fn is_full(&self) -> bool {
         self == &SemesterRange::FULL
     }
@@ -172,6 +323,14 @@ impl SemesterRange {
         }
     }
 
+    fn is_empty(self) -> bool {
+        self.inner == 0
+    }
+
+    pub fn is_subset(self, other: Self) -> bool {
+        self.inner & !other.inner == 0
+    }
+
     fn intersection(self, other: Self) -> Self {
         SemesterRange {
             inner: self.inner & other.inner,
@@ -194,13 +353,15 @@ impl SemesterRange {
 }
 
 impl TryFrom<Vec<u16>> for SemesterRange {
-    type Error = Infallible;
+    type Error = String;
     fn try_from(semesters: Vec<u16>) -> Result<Self, Self::Error> {
-        Ok(semesters
+        semesters
             .into_iter()
-            .fold(SemesterRange::EMPTY, |accum, inner| {
-                accum.add(Semester { inner })
-            }))
+            .try_fold(SemesterRange::EMPTY, |accum, inner| {
+                let semester = Semester::new(inner)
+                    .ok_or_else(|| format!("invalid semester index {inner}"))?;
+                Ok(accum.add(semester))
+            })
     }
 }
 
@@ -211,14 +372,15 @@ impl From<SemesterRange> for Vec<u16> {
 }
 
 impl<'a> TryFrom<&'a str> for SemesterRange {
-    type Error = Infallible;
+    type Error = String;
     fn try_from(string: &'a str) -> Result<Self, Self::Error> {
         static DELIM: Lazy<Regex> = Lazy::new(|| Regex::new(r#", | or "#).unwrap());
-        Ok(DELIM
+        DELIM
             .split(string)
             .map(Semester::from_str)
-            .map(Result::unwrap)
-            .fold(SemesterRange::EMPTY, SemesterRange::add))
+            .try_fold(SemesterRange::EMPTY, |accum, semester| {
+                Ok(accum.add(semester?))
+            })
     }
 }
 
@@ -239,11 +401,53 @@ impl Default for SemesterRange {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Level {
+    Undergraduate,
+    Graduate,
+}
+
+impl FromStr for Level {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "undergrad" => Ok(Level::Undergraduate),
+            "grad" => Ok(Level::Graduate),
+            _ => Err(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Semester, SemesterRange};
+    use super::{
+        attributes, audit_demographics, building_and_room, canonicalize_aliases, credits, exam,
+        fee, grading, instructors, meetings, parallel_map, process, process_partitioned,
+        registration_dates, seats, strip_html, term_dates, validate, Alias, AliasMap, Attribute,
+        Day, Demographics, Filters, GradingOptions, ParseFailures, ProgramRestriction,
+        Qualifications, Seats, Semester, SemesterRange, Time,
+    };
+    use crate::restrictions::CourseCode;
+    use crate::restrictions::PrerequisiteTree;
+    use serde_json::{json, Value};
+    use std::collections::BTreeSet;
     use std::str::FromStr;
 
+    /// A detail response for section S01 of CSCI 0150 in 202210, with the fields of
+    /// `overrides` in place of its own
+    fn raw_record(overrides: Value) -> String {
+        let mut record = json!({
+            "permreq": "N", "code": "CSCI 0150", "section": "S01", "title": "Intro",
+            "description": "", "registration_restrictions": "", "seats": "",
+            "instructordetail_html": "", "regdemog_html": "", "regdemog_json": "",
+            "srcdb": "202210",
+        });
+        for (field, value) in overrides.as_object().unwrap() {
+            record[field] = value.clone();
+        }
+        record.to_string()
+    }
+
     #[test]
     fn semseter_range() {
         let text = "05, 06, 07, 08, 09, 10, 11, 12 or 13";
@@ -265,6 +469,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn malformed_semester_range() {
+        assert!(SemesterRange::try_from("05, 00").is_err());
+        assert!(SemesterRange::try_from("05 and 06").is_err());
+        assert!(serde_json::from_str::<SemesterRange>("[4, 15]").is_err());
+        assert_eq!(
+            serde_json::from_str::<SemesterRange>("[4, 14]").unwrap(),
+            SemesterRange::try_from("05 or GP").unwrap()
+        );
+    }
+
     #[test]
     fn semseter_range3() {
         let range = SemesterRange::EMPTY;
@@ -277,259 +492,3111 @@ mod tests {
         let range = SemesterRange::to(4);
         assert_eq!(range.to_string(), "01, 02, 03, 04", "{}", range.inner);
     }
-}
 
-fn program_string(string: &str) -> Vec<String> {
-    static DELIM: Lazy<Regex> = Lazy::new(|| Regex::new(r#", | or "#).unwrap());
-    DELIM.split(string).map(str::to_string).collect()
-}
+    #[test]
+    fn meeting() {
+        let html = r#"<div class="meet">TTh 1-2:20p<span class="meet-loc">in <a href="/map">Barus &amp; Holley 166</a></span></div><div class="meet">MWF 11am-12:20pm</div><div class="meet">TBA</div>"#;
+        let meetings = meetings(html);
+        assert_eq!(meetings.len(), 2);
+        assert_eq!(meetings[0].days(), [Day::Tuesday, Day::Thursday]);
+        assert_eq!(meetings[0].start(), Time::new(1, 0, true));
+        assert_eq!(meetings[0].end(), Time::new(2, 20, true));
+        assert_eq!(meetings[0].location(), Some("Barus & Holley 166"));
+        assert_eq!(meetings[0].building(), Some("Barus & Holley"));
+        assert_eq!(meetings[0].room(), Some("166"));
+        assert_eq!(
+            meetings[1].days(),
+            [Day::Monday, Day::Wednesday, Day::Friday]
+        );
+        assert_eq!(meetings[1].start().to_string(), "11:00");
+        assert_eq!(meetings[1].end().to_string(), "12:20");
+        assert_eq!(meetings[1].location(), None);
+        assert_eq!(meetings[1].building(), None);
+        let reordered = super::meetings(
+            r#"<DIV id=m1 class='meet big'>M 9-9:50a <SPAN class=meet-loc>in CIT 368</SPAN></DIV>"#,
+        );
+        assert_eq!(reordered[0].room(), Some("368"));
+        assert_eq!(
+            building_and_room("Salomon Center DECI"),
+            (Some(String::from("Salomon Center DECI")), None)
+        );
+        assert_eq!(
+            building_and_room("CIT 368"),
+            (Some(String::from("CIT")), Some(String::from("368")))
+        );
+    }
 
-#[derive(Debug)]
-struct Qualifications {
-    prerequisites: Option<PrerequisiteTree>,
-    programs: Option<Vec<String>>,
-    semester_range: SemesterRange,
-}
+    #[test]
+    fn stripping() {
+        let description = "<p>An introduction to Brown&#8217;s <em>Caf&eacute;</em> culture,&nbsp;1850&ndash;1900. Taught in Franc\u{327}ais.</p>";
+        assert_eq!(
+            strip_html(description),
+            "An introduction to Brown\u{2019}s Café culture, 1850\u{2013}1900. Taught in Français."
+        );
+    }
 
-impl FromStr for Qualifications {
-    type Err = Infallible;
-    fn from_str(string: &str) -> Result<Self, Self::Err> {
-        static TAG: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r#"^(<p class="prereq">Prerequisites?: (?P<prereq>.*?)\.(<br/><sup>\*</sup> May be taken concurrently\.)?</p>)?(<p class="cls">Enrollment limited to students with a semester level of (?P<cls>.*?)\.</p>)?(<p class="cls">Students with a semester level of (?P<clsc>.*?) may <strong>not</strong> enroll\.</p>)?(<p class="maj">Enrollment is limited to students with a major in (?P<maj>.*?)\.</p>)?(<p class="maj">Students cannot enroll who have a concentration in (.*?)\.</p>)?(<p class="prg">Enrollment limited to students in the (?P<prg>.*?) programs\.</p>)?(<p class="prg">Enrollment limited to students in the following programs:<ul>(?P<prgl>.*?)</ul></p>)?(<p class="prg">Enrollment limited to students in the (?P<prgs>.*?) program.</p>)?(<p class="prg">Enrollment limited to students in the (?P<prg1>.*?) or (?P<prg2>.*?) programs.</p>)?(<p class="prg">Students in the (.*?) program may <strong>not</strong> enroll.</p>)?(<p class="lvl">Enrollment is limited to (?P<lvl>Undergraduate|Graduate) level students\.</p>)?(<p class="lvl">(?P<lvlc>Undergraduate|Graduate) level students may <strong>not</strong> enroll\.</p>)?(<p class="chr">Enrollment limited to students in the (?P<chr>.*?) chohort\.</p>)?$"#).unwrap()
-        });
-        let captures = TAG.captures(string).unwrap();
-        let prerequisites = captures
-            .name("prereq")
-            .as_ref()
-            .map(regex::Match::as_str)
-            .map(strip_html)
-            .as_deref()
-            .map(PrerequisiteTree::try_from)
-            .map(Result::unwrap);
-        let semester_level = captures
-            .name("cls")
-            .as_ref()
-            .map(regex::Match::as_str)
-            .map(SemesterRange::try_from)
-            .map(Result::unwrap)
-            .unwrap_or_default();
-        let semester_level_complement = captures
-            .name("clsc")
-            .as_ref()
-            .map(regex::Match::as_str)
-            .map(SemesterRange::try_from)
-            .map(Result::unwrap)
-            .map(SemesterRange::complement)
-            .unwrap_or_default();
-        let programs = captures
-            .name("prg")
-            .as_ref()
-            .map(regex::Match::as_str)
-            .map(program_string);
-        let level = captures
-            .name("lvl")
-            .as_ref()
-            .map(regex::Match::as_str)
-            .and_then(|level| match level {
-                "Undergraduate" => Some(SemesterRange::UNDERGRADUATE),
-                "Graduate" => Some(SemesterRange::GRADUATE),
-                _ => None,
+    #[test]
+    fn rejected_records() {
+        let raw = |permreq: &str, restrictions: &str| {
+            raw_record(json!({"permreq": permreq, "registration_restrictions": restrictions}))
+        };
+        let input = [
+            raw("N", ""),
+            raw("maybe", ""),
+            raw("N", r#"<p class="prereq">Prerequisites: CSCI 0111 (.</p>"#),
+            raw("N", r#"<p class="new">Something new.</p>"#),
+            String::from("[1, 2]"),
+        ]
+        .join("\n");
+        let (courses, rejected) =
+            process(serde_json::de::StrRead::new(&input), &Filters::default());
+        // a paragraph in a new form is left out, with a warning, rather than rejected
+        assert_eq!(courses.len(), 1);
+        assert_eq!(courses[0].offerings().len(), 2);
+        let reasons: Vec<_> = rejected.iter().map(|r| r.reason.as_str()).collect();
+        assert_eq!(reasons.len(), 3);
+        assert_eq!(reasons[0], r#"neither Y nor N in permreq: "maybe""#);
+        assert!(
+            reasons[1].starts_with("unparsable prerequisites"),
+            "{reasons:?}"
+        );
+        let fields: Vec<_> = rejected.iter().map(|r| r.field).collect();
+        assert_eq!(
+            fields,
+            [Some("permreq"), Some("registration_restrictions"), None]
+        );
+        assert_eq!(rejected[0].record["permreq"], "maybe");
+        assert_eq!(rejected[2].record, json!([1, 2]));
+    }
+
+    #[test]
+    fn seats_markup() {
+        let html = r#"<SPAN id="max" class="seats_max big">40</SPAN> / <span class='seats_avail'> -2 </span>"#;
+        assert_eq!(
+            seats(html),
+            Some(Seats {
+                max: 40,
+                available: -2,
+                waitlist: None,
             })
-            .unwrap_or_default();
-        let semester_range = semester_level
-            .intersection(semester_level_complement)
-            .intersection(level);
-        Ok(Qualifications {
-            prerequisites,
-            programs,
-            semester_range,
-        })
+        );
+        let html = r#"<span class="seats_max">20</span><span class="seats_avail">0</span><span class="seats_wait">7</span>"#;
+        assert_eq!(seats(html).unwrap().waitlist, Some(7));
+        assert_eq!(seats(r#"<span class="seats_max">40</span>"#), None);
     }
-}
 
-fn instructors(string: &str) -> Vec<String> {
-    static NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<h4>.*?</h4>"#).unwrap());
-    NAME.find_iter(string)
-        .map(|name| strip_html(name.as_str()))
-        .filter(|name| name != "TBD")
-        .collect()
-}
+    #[test]
+    fn seats_enrollment() {
+        let seats = |max, available| Seats {
+            max,
+            available,
+            waitlist: None,
+        };
+        assert_eq!(seats(40, -2).enrollment(), 42);
+        assert_eq!(seats(40, 10).enrollment(), 30);
+        assert_eq!(seats(20, 25).enrollment(), 0);
+        assert_eq!(seats(0, 5).enrollment(), 0);
+    }
 
-#[derive(Debug)]
-struct Record {
-    restricted: bool,
-    code: CourseCode,
-    section: Option<u8>,
-    title: Title,
-    description: String,
-    qualifications: Qualifications,
-    enrollment: Option<u16>,
-    instructors: Vec<String>,
-    demographics: Option<Demographics>,
-    srcdb: String,
-}
+    #[test]
+    fn course_attributes() {
+        let html = "<ul><li>Writing-designated (WRIT)</li><li>FYS</li><li>DIAP</li><li>Writing-designated (WRIT)</li></ul>";
+        assert_eq!(
+            attributes(html),
+            [
+                Attribute::Writing,
+                Attribute::Diversity,
+                Attribute::FirstYearSeminar
+            ]
+        );
+        assert_eq!(
+            attributes("CBLR, RPP"),
+            [
+                Attribute::CommunityBased,
+                Attribute::Other(String::from("RPP"))
+            ]
+        );
+        assert_eq!(
+            attributes("<ul><li class='attr'>Writing-designated<br>(WRIT)</li></ul>"),
+            [Attribute::Writing]
+        );
+        assert_eq!(attributes(""), []);
+    }
 
-impl FromStr for Record {
-    type Err = ();
-    fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let raw: Raw = serde_json::from_str(string).unwrap();
-        Ok(Record::from(raw))
+    #[test]
+    fn grading_options() {
+        let snc = grading("<ul><li>Mandatory S/NC</li></ul>");
+        assert!(snc.satisfactory_only() && snc.mandatory && !snc.audit);
+        assert_eq!(
+            grading("<li>Student Option</li><li>Audit</li>"),
+            GradingOptions {
+                letter: true,
+                satisfactory: true,
+                audit: true,
+                mandatory: false,
+            }
+        );
+        let letter = grading("Mandatory ABC/NC");
+        assert!(letter.letter && letter.mandatory && !letter.satisfactory);
+        assert_eq!(grading(""), GradingOptions::default());
     }
-}
 
-impl From<Raw> for Record {
-    fn from(raw: Raw) -> Record {
-        let restricted = yes_or_no(&raw.permreq).unwrap();
-        let code = CourseCode::try_from(raw.code.as_str()).unwrap();
-        let section = section(&raw.section);
-        let title = Title::from_str(&raw.title).unwrap();
-        let description = strip_html(&raw.description);
-        let qualifications = Qualifications::from_str(&raw.registration_restrictions).unwrap();
-        let enrollment_seats = enrollment_from_seats(&raw.seats);
-        let enrollment_html = enrollment_from_html(&raw.regdemog_html);
-        let enrollment = enrollment_seats.or(enrollment_html);
-        let instructors = instructors(&raw.instructordetail_html);
-        let demographics = serde_json::from_str(&raw.regdemog_json).ok();
-        let srcdb = raw.srcdb;
-        Record {
-            restricted,
-            code,
-            section,
-            title,
-            description,
-            qualifications,
-            enrollment,
-            instructors,
-            demographics,
-            srcdb,
-        }
+    #[test]
+    fn credit_hours() {
+        assert_eq!(credits("1").unwrap().to_string(), "1");
+        assert_eq!(credits("0.5").unwrap().to_string(), "0.5");
+        assert_eq!(credits("1-4").unwrap().to_string(), "1-4");
+        let variable = credits("Variable 1 to 4").unwrap();
+        assert_eq!((variable.min, variable.max), (1.0, 4.0));
+        assert_eq!(credits(""), None);
     }
-}
 
-#[derive(Deserialize, Debug)]
-struct Raw {
-    permreq: String,
-    code: String,
-    section: String,
-    title: String,
-    description: String,
-    registration_restrictions: String,
-    seats: String,
-    instructordetail_html: String,
-    regdemog_html: String,
-    regdemog_json: String,
-    srcdb: String,
-}
+    #[test]
+    fn corequisites() {
+        let string = r#"<p class="prereq">Prerequisite: MATH 0090.</p><p class="coreq">Must be taken concurrently with PHYS 0070.</p>"#;
+        let qualifications = Qualifications::from_str(string).unwrap();
+        let tree = |s| PrerequisiteTree::try_from(s).unwrap();
+        assert_eq!(qualifications.prerequisites, Some(tree("MATH 0090")));
+        assert_eq!(qualifications.corequisites, Some(tree("PHYS 0070")));
 
-#[derive(Serialize, Deserialize)]
-pub struct Offering {
-    date: String,
-    section: u8,
-    instructors: Vec<String>,
-    enrollment: Option<u16>,
-    demographics: Option<Demographics>,
-}
+        let string = r#"<p class="coreq">Corequisites: PHYS 0070 or PHYS 0050.</p>"#;
+        let qualifications = Qualifications::from_str(string).unwrap();
+        assert_eq!(qualifications.prerequisites, None);
+        assert_eq!(
+            qualifications.corequisites,
+            Some(tree("PHYS 0070 or PHYS 0050"))
+        );
+    }
 
-#[derive(Serialize, Deserialize)]
-pub struct Course {
-    code: CourseCode,
-    title: String,
-    description: String,
-    prerequisites: Option<PrerequisiteTree>,
-    semester_range: SemesterRange,
-    restricted: bool,
-    aliases: Vec<CourseCode>,
-    offerings: Vec<Offering>,
-}
+    #[test]
+    fn concentrations() {
+        let string = r#"<p class="maj">Enrollment is limited to students with a major in Computer Science, Applied Mathematics-Computer Science or Computational Biology.</p>"#;
+        let concentrations = Qualifications::from_str(string).unwrap().concentrations;
+        assert_eq!(
+            concentrations.allowed,
+            [
+                "Computer Science",
+                "Applied Mathematics-Computer Science",
+                "Computational Biology"
+            ]
+        );
+        assert!(concentrations.excluded.is_empty());
 
-impl Course {
-    pub fn code(&self) -> &CourseCode {
-        &self.code
+        let string =
+            r#"<p class="maj">Students cannot enroll who have a concentration in Economics.</p>"#;
+        let concentrations = Qualifications::from_str(string).unwrap().concentrations;
+        assert_eq!(concentrations.excluded, ["Economics"]);
+        assert!(Qualifications::from_str("")
+            .unwrap()
+            .concentrations
+            .is_empty());
     }
 
-    pub fn prerequisites(&self) -> Option<&PrerequisiteTree> {
-        self.prerequisites.as_ref()
+    #[test]
+    fn programs() {
+        let programs = |string| Qualifications::from_str(string).unwrap().programs;
+        assert_eq!(
+            programs(
+                r#"<p class="prg">Enrollment limited to students in the Biology (AB), Biology (SCB) or Chemistry (AB) programs.</p>"#
+            ),
+            [ProgramRestriction::Allowed(vec![
+                String::from("Biology (AB)"),
+                String::from("Biology (SCB)"),
+                String::from("Chemistry (AB)"),
+            ])]
+        );
+        assert_eq!(
+            programs(
+                r#"<p class="prg">Enrollment limited to students in the following programs:<ul><li>Public Health (MPH)</li><li> Epidemiology (PHD)</li></ul></p>"#
+            ),
+            [ProgramRestriction::Allowed(vec![
+                String::from("Public Health (MPH)"),
+                String::from("Epidemiology (PHD)"),
+            ])]
+        );
+        assert_eq!(
+            programs(
+                r#"<p class="prg">Students in the Public Affairs (MPA) program may <strong>not</strong> enroll.</p>"#
+            ),
+            [ProgramRestriction::Excluded(vec![String::from(
+                "Public Affairs (MPA)"
+            )])]
+        );
+        assert!(programs("").is_empty());
     }
 
-    pub fn prerequisites_mut(&mut self) -> &mut Option<PrerequisiteTree> {
-        &mut self.prerequisites
+    #[test]
+    fn requirements() {
+        let restrictions = [
+            r#"<p class="prereq">Prerequisites: CSCI 0150.</p>"#,
+            r#"<p class="maj">Enrollment is limited to students with a major in Computer Science.</p>"#,
+            r#"<p class="prg">Students in the Public Affairs (MPA) program may <strong>not</strong> enroll.</p>"#,
+        ]
+        .concat();
+        let raw =
+            raw_record(json!({"code": "CSCI 0160", "registration_restrictions": restrictions}));
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let requirements = courses[0].requirements().unwrap();
+        assert_eq!(
+            requirements.to_string(),
+            "CSCI 0150 and (not program in 'Public Affairs (MPA)') and concentration in 'Computer Science'"
+        );
+        let json = serde_json::to_value(&requirements).unwrap();
+        assert_eq!(json["all"][2]["concentration"], "Computer Science");
+        assert_eq!(
+            serde_json::from_value::<PrerequisiteTree>(json).unwrap(),
+            requirements
+        );
+        let raw = raw_record(json!({"code": "CSCI 0160", "permreq": "Y"}));
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        assert_eq!(
+            courses[0].requirements().unwrap().to_string(),
+            "instructor permission"
+        );
     }
 
-    pub fn semester_range(&self) -> &SemesterRange {
-        &self.semester_range
+    #[test]
+    fn cohort() {
+        let string = r#"<p class="lvl">Enrollment is limited to Undergraduate level students.</p><p class="chr">Enrollment limited to students in the PLME cohort.</p>"#;
+        let qualifications = Qualifications::from_str(string).unwrap();
+        assert_eq!(qualifications.cohort.as_deref(), Some("PLME"));
+        assert_eq!(qualifications.semester_range, SemesterRange::UNDERGRADUATE);
+        assert_eq!(Qualifications::from_str("").unwrap().cohort, None);
+
+        let string = r#"<p class="lvl">Graduate level students may <strong>not</strong> enroll.</p><p class="new">Something new.</p>"#;
+        let qualifications = Qualifications::from_str(string).unwrap();
+        assert_eq!(qualifications.semester_range, SemesterRange::UNDERGRADUATE);
+        assert_eq!(
+            qualifications.unrecognized,
+            [r#"<p class="new">Something new.</p>"#]
+        );
     }
 
-    fn from_offerings(
-        code: CourseCode,
-        mut offerings: Vec<Record>,
-        aliases: Vec<CourseCode>,
-    ) -> Course {
-        offerings.sort_by(|a, b| a.srcdb.cmp(&b.srcdb).reverse()); // recent first
-        let latest = offerings.first().unwrap();
-        let title = match latest.title {
-            Title::Title(ref t) => t.clone(),
-            _ => unreachable!("method precondition"),
+    #[test]
+    fn instructor_ids() {
+        let html = r#"<div><h4>Jane Doe</h4><a href="mailto:Jane_Doe@brown.edu">Email</a></div><div><h4>Smith, J.</h4></div><div><h4>TBD</h4></div>"#;
+        let (names, ids) = instructors(html);
+        assert_eq!(names, ["Jane Doe", "Smith, J."]);
+        assert_eq!(ids, ["email:jane_doe", "name:smith j"]);
+    }
+
+    #[test]
+    fn listed_aliases() {
+        let raw = raw_record(json!({
+            "code": "VISA 1110",
+            "search": {"groups": ["code:VISA 1110", "code:ARTS 1110"], "matched": ["1"]},
+        }));
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let aliases = courses[0].aliases();
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].code().to_string(), "ARTS 1110");
+        assert!(aliases[0].valid_in("202210"));
+    }
+
+    #[test]
+    fn sections() {
+        let record = |srcdb: &str, section: &str, seats: &str| {
+            raw_record(json!({"srcdb": srcdb, "section": section, "seats": seats}))
         };
-        let description = latest.description.clone();
-        let prerequisites = offerings
-            .iter()
-            .find_map(|offering| offering.qualifications.prerequisites.as_ref())
-            .cloned();
-        let semester_range = latest.qualifications.semester_range;
-        let restricted = latest.restricted;
-        let offerings = offerings
+        let seats = |max: u16, available: u16| {
+            format!(
+                r#"<span class="seats_max">{max}</span><span class="seats_avail">{available}</span>"#
+            )
+        };
+        let raw = [
+            record("202220", "S01", &seats(200, 10)),
+            record("202210", "S02", &seats(30, 5)),
+            record("202210", "S01", &seats(40, 0)),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        assert_eq!(courses.len(), 1);
+        let sections: Vec<_> = courses[0]
+            .sections()
             .into_iter()
-            .map(|offering| Offering {
-                date: offering.srcdb,
-                section: offering.section.unwrap(),
-                instructors: offering.instructors,
-                enrollment: offering.enrollment,
-                demographics: offering.demographics,
-            })
+            .map(|section| (section.srcdb, section.section, section.enrollment))
             .collect();
-        Course {
-            code,
-            title,
-            description,
-            prerequisites,
+        assert_eq!(
+            sections,
+            [
+                ("202210", 1, Some(40)),
+                ("202210", 2, Some(25)),
+                ("202220", 1, Some(190))
+            ]
+        );
+        let line = serde_json::to_value(&courses[0].sections()[0]).unwrap();
+        assert_eq!(line["code"]["subject"], "CSCI");
+        assert_eq!(line["capacity"], 40);
+    }
+
+    #[test]
+    fn offering_semester_ranges() {
+        let record = |srcdb: &str, restrictions: &str| {
+            raw_record(json!({"srcdb": srcdb, "registration_restrictions": restrictions}))
+        };
+        let raw = [
+            record("202110", ""),
+            record(
+                "202210",
+                r#"<p class="cls">Enrollment limited to students with a semester level of 03, 04, 05, 06, 07, 08, 09, 10, 11, 12, 13, 14 or 15.</p>"#,
+            ),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let course = &courses[0];
+        let ranges: Vec<_> = course
+            .offerings()
+            .iter()
+            .map(|offering| (offering.date(), offering.semester_range()))
+            .collect();
+        let upperclass = SemesterRange::FULL
+            .intersection(SemesterRange::try_from("01, 02").unwrap().complement());
+        assert_eq!(
+            ranges,
+            [
+                ("202210", Some(upperclass)),
+                ("202110", Some(SemesterRange::FULL))
+            ]
+        );
+        assert_eq!(*course.semester_range(), upperclass);
+        let earlier = courses.into_iter().next().unwrap().until("202110").unwrap();
+        assert_eq!(*earlier.semester_range(), SemesterRange::FULL);
+    }
+
+    #[test]
+    fn demographics_outside() {
+        let demographics: Demographics =
+            serde_json::from_str(r#"{"FY": 3, "Jr": 10, "Sr": 12}"#).unwrap();
+        let upperclass = SemesterRange::try_from("05, 06, 07, 08").unwrap();
+        assert_eq!(demographics.outside(upperclass), [("freshmen", 3)]);
+        assert_eq!(demographics.outside(SemesterRange::FULL), []);
+    }
+
+    #[test]
+    fn demographics_history() {
+        let record = |srcdb: &str, section: &str, demographics: &str| {
+            raw_record(json!({"srcdb": srcdb, "section": section, "regdemog_json": demographics}))
+        };
+        let raw = [
+            record("202210", "S01", r#"{"FY": 30, "So": 5}"#),
+            record("202210", "S02", r#"{"FY": 10, "Gr": 1}"#),
+            record("202110", "S01", r#"{"So": 20}"#),
+            record("202010", "S01", ""),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let history = courses[0].demographics();
+        let terms: Vec<_> = history
+            .terms()
+            .iter()
+            .map(|term| (term.term.to_string(), term.demographics.classes()))
+            .collect();
+        assert_eq!(
+            terms,
+            [
+                (String::from("Fall 2021"), [0, 20, 0, 0, 0, 0]),
+                (String::from("Fall 2022"), [40, 5, 0, 0, 1, 0])
+            ]
+        );
+        assert_eq!(history.total().classes(), [40, 25, 0, 0, 1, 0]);
+
+        // more students over every term than one section's count could hold
+        let large = [
+            record("202210", "S01", r#"{"FY": 40000}"#),
+            record("202210", "S02", r#"{"FY": 40000}"#),
+            record("202110", "S01", r#"{"FY": 40000}"#),
+        ]
+        .join("\n");
+        let (large, _) = process(serde_json::de::StrRead::new(&large), &Filters::default());
+        assert_eq!(large[0].demographics().total().classes()[0], 120000);
+
+        let json = serde_json::to_value(history).unwrap();
+        assert_eq!(json["terms"][1]["term"]["srcdb"], "202210");
+        assert_eq!(json["terms"][1]["freshmen"], 40);
+
+        let earlier = courses.into_iter().next().unwrap().until("202110").unwrap();
+        assert_eq!(
+            earlier.demographics().total().classes(),
+            [0, 20, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn prerequisite_changes() {
+        let record = |srcdb: &str, section: &str, prerequisites: &str| {
+            let restrictions = match prerequisites {
+                "" => String::new(),
+                p => format!(r#"<p class="prereq">Prerequisites: {p}.</p>"#),
+            };
+            raw_record(json!({
+                "code": "CSCI 0200", "section": section, "registration_restrictions": restrictions,
+                "srcdb": srcdb,
+            }))
+        };
+        let raw = [
+            record("201910", "S01", "CSCI 0150"),
+            record("202010", "S01", ""),
+            record("202010", "S02", "CSCI 0150"),
+            record("202110", "S01", "CSCI 0150 or CSCI 0170"),
+            record("202210", "S01", ""),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let history: Vec<_> = courses[0]
+            .prerequisite_history()
+            .iter()
+            .map(|change| (change.term.srcdb(), change.prerequisites.clone()))
+            .collect();
+        let tree = |s| Some(PrerequisiteTree::try_from(s).unwrap());
+        assert_eq!(
+            history,
+            [
+                (String::from("201910"), tree("CSCI 0150")),
+                (String::from("202110"), tree("CSCI 0150 or CSCI 0170")),
+                (String::from("202210"), None),
+            ]
+        );
+        let earlier = courses.into_iter().next().unwrap().until("202010").unwrap();
+        assert_eq!(earlier.prerequisite_history().len(), 1);
+    }
+
+    #[test]
+    fn title_changes() {
+        let record = |srcdb: &str, title: &str, description: &str| {
+            raw_record(json!({"srcdb": srcdb, "title": title, "description": description}))
+        };
+        let raw = [
+            record("201910", "Studio Foundation", "Drawing."),
+            record("202010", "Studio Foundation", "Drawing."),
+            record("202110", "Studio Foundations", "Drawing."),
+            record("202210", "Studio Foundations", "Drawing and painting."),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let history: Vec<_> = courses[0]
+            .title_history()
+            .iter()
+            .map(|change| {
+                (
+                    change.term.srcdb(),
+                    change.title.as_str(),
+                    change.description.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (
+                    String::from("201910"),
+                    "Studio Foundation",
+                    Some("Drawing.")
+                ),
+                (String::from("202110"), "Studio Foundations", None),
+                (
+                    String::from("202210"),
+                    "Studio Foundations",
+                    Some("Drawing and painting.")
+                ),
+            ]
+        );
+        let earlier = courses.into_iter().next().unwrap().until("202010").unwrap();
+        assert_eq!(earlier.title_history().len(), 1);
+    }
+
+    #[test]
+    fn as_of() {
+        let record = |srcdb: &str, title: &str, restrictions: &[&str]| {
+            raw_record(json!({
+                "code": "CSCI 0200", "srcdb": srcdb, "title": title, "description": title,
+                "registration_restrictions": restrictions.concat(),
+            }))
+        };
+        let raw = [
+            record(
+                "201910",
+                "Data Structures",
+                &[r#"<p class="prereq">Prerequisites: CSCI 0150.</p>"#],
+            ),
+            record(
+                "202310",
+                "Program Design with Data Structures and Algorithms",
+                &[
+                    r#"<p class="prereq">Prerequisites: CSCI 0150 or CSCI 0170.</p>"#,
+                    r#"<p class="coreq">Corequisites: CSCI 0220.</p>"#,
+                    r#"<p class="maj">Enrollment is limited to students with a major in Computer Science.</p>"#,
+                ],
+            ),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let tree = |s| PrerequisiteTree::try_from(s).unwrap();
+        let earlier = courses.into_iter().next().unwrap().until("202210").unwrap();
+        assert_eq!(earlier.title(), "Data Structures");
+        assert_eq!(earlier.description(), "Data Structures");
+        assert_eq!(earlier.prerequisites(), Some(&tree("CSCI 0150")));
+        assert_eq!(earlier.corequisites(), None);
+        assert!(earlier.concentrations().is_empty());
+        assert_eq!(earlier.offerings().len(), 1);
+
+        // the same as processing only the terms until then
+        let filters = Filters {
+            as_of: Some(String::from("202210")),
+            ..Filters::default()
+        };
+        let (processed, _) = process(serde_json::de::StrRead::new(&raw), &filters);
+        assert_eq!(
+            serde_json::to_value(&processed[0]).unwrap(),
+            serde_json::to_value(&earlier).unwrap()
+        );
+
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let current = courses.into_iter().next().unwrap().until("202310").unwrap();
+        assert_eq!(
+            current.title(),
+            "Program Design with Data Structures and Algorithms"
+        );
+        assert_eq!(current.corequisites(), Some(&tree("CSCI 0220")));
+        assert_eq!(
+            current.concentrations().allowed,
+            [String::from("Computer Science")]
+        );
+    }
+
+    #[test]
+    fn as_of_attributes() {
+        let raw = [
+            raw_record(json!({"srcdb": "202110", "attr_html": "FYS", "gmods": "Student Option"})),
+            raw_record(json!({"srcdb": "202210", "attr_html": "WRIT", "gmods": "Mandatory S/NC"})),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        assert_eq!(courses[0].attributes(), [Attribute::Writing]);
+        assert!(!courses[0].is_first_year_seminar());
+        let earlier = courses.into_iter().next().unwrap().until("202110").unwrap();
+        assert_eq!(earlier.attributes(), [Attribute::FirstYearSeminar]);
+        assert!(earlier.grading().letter && !earlier.grading().mandatory);
+        assert!(earlier.is_first_year_seminar());
+
+        let filters = Filters {
+            as_of: Some(String::from("202110")),
+            ..Filters::default()
+        };
+        let (processed, _) = process(serde_json::de::StrRead::new(&raw), &filters);
+        assert_eq!(
+            serde_json::to_value(&processed[0]).unwrap(),
+            serde_json::to_value(&earlier).unwrap()
+        );
+    }
+
+    #[test]
+    fn alias_validity() {
+        let terms = BTreeSet::from([
+            String::from("201710"),
+            String::from("201610"),
+            String::from("201920"),
+        ]);
+        let alias = Alias::new(CourseCode::try_from("APMA 1690").unwrap(), &terms);
+        assert!(alias.valid_in("201610"));
+        assert!(alias.valid_in("201810"));
+        assert!(alias.valid_in("201920"));
+        assert!(!alias.valid_in("202010"));
+        assert!(!alias.valid_in("201600"));
+    }
+
+    #[test]
+    fn parallel_order() {
+        let squares = parallel_map((0..1000u32).collect(), |n| n * n);
+        assert_eq!(squares, (0..1000u32).map(|n| n * n).collect::<Vec<_>>());
+        assert!(parallel_map(Vec::<u32>::new(), |n| n).is_empty());
+    }
+
+    #[test]
+    fn partitioned() {
+        let record = |code: &str, title: &str, srcdb: &str| {
+            raw_record(json!({"code": code, "title": title, "srcdb": srcdb}))
+        };
+        let raw = [
+            record("CSCI 0150", "Intro", "202110"),
+            record("MATH 0100", "Calculus", "202110"),
+            record("APMA 0150", "Cross-listed as CSCI 0150", "202210"),
+            record("CSCI 0150", "Intro", "202210"),
+            record("ENGN 0030", "Engineering", "202210"),
+            String::from("[1, 2]"),
+        ]
+        .join("\n");
+        let directory = std::env::temp_dir().join(format!("cab-partitions-{}", std::process::id()));
+        let (courses, rejected) = process_partitioned(
+            serde_json::de::StrRead::new(&raw),
+            &Filters::default(),
+            &directory,
+            3,
+        )
+        .unwrap();
+        let (expected, expected_rejected) =
+            process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        assert_eq!(
+            serde_json::to_value(&courses).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+        assert_eq!(courses[0].aliases().len(), 1);
+        assert_eq!(rejected.len(), expected_rejected.len());
+        assert!(!directory.exists());
+    }
+
+    #[test]
+    fn filters() {
+        let record = |code: &str, title: &str, srcdb: &str, permreq: &str| {
+            raw_record(json!({"code": code, "title": title, "srcdb": srcdb, "permreq": permreq}))
+        };
+        let raw = [
+            record("CSCI 0150", "Intro", "201710", "N"),
+            record("CSCI 0150", "Intro", "202210", "N"),
+            record("CSCI 2950", "Seminar", "202210", "N"),
+            record("CSCI 1970", "Independent Study", "202210", "Y"),
+            record("MATH 0100", "Calculus", "202210", "N"),
+            record("APMA 0150", "Cross-listed as CSCI 0150", "202210", "N"),
+        ]
+        .join("\n");
+        let codes = |filters: &Filters| {
+            let (courses, _) = process(serde_json::de::StrRead::new(&raw), filters);
+            courses
+                .iter()
+                .map(|course| {
+                    let aliases = course.aliases().len();
+                    (course.code().to_string(), course.offerings().len(), aliases)
+                })
+                .collect::<Vec<_>>()
+        };
+        let course = |code: &str, offerings, aliases| (String::from(code), offerings, aliases);
+        let focused = Filters {
+            subjects: vec![String::from("CSCI")],
+            level: "undergrad".parse().ok(),
+            exclude_restricted: true,
+            since: Some(String::from("201800")),
+            ..Filters::default()
+        };
+        assert_eq!(codes(&focused), [course("CSCI 0150", 1, 1)]);
+        let numbers = Filters {
+            numbers: Some(1000..=2999),
+            ..Filters::default()
+        };
+        assert_eq!(
+            codes(&numbers),
+            [course("CSCI 1970", 1, 0), course("CSCI 2950", 1, 0)]
+        );
+        assert_eq!(codes(&Filters::default()).len(), 4);
+    }
+
+    #[test]
+    fn canonical_aliases() {
+        let record = |code: &str, title: &str, srcdb: &str, restrictions: &str| {
+            raw_record(json!({
+                "code": code, "title": title, "srcdb": srcdb,
+                "registration_restrictions": restrictions,
+            }))
+        };
+        let prerequisites = r#"<p class="prereq">Prerequisites: APMA 1690 or MATH 1010.</p>"#;
+        let raw = [
+            record("CSCI 1450", "Probability for Computing", "202110", ""),
+            record("APMA 1690", "Cross-listed as CSCI 1450", "202110", ""),
+            record("CSCI 1570", "Algorithms", "202120", prerequisites),
+            record("MATH 1010", "Analysis", "202110", ""),
+        ]
+        .join("\n");
+        let (mut courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let entries = AliasMap::new(courses.iter())
+            .entries()
+            .into_iter()
+            .map(|(alias, entries)| {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .map(|entry| (entry.canonical.to_string(), entry.first, entry.last))
+                    .collect();
+                (alias, entries)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [(
+                String::from("APMA 1690"),
+                vec![(String::from("CSCI 1450"), "202110", "202110")]
+            )]
+        );
+        canonicalize_aliases(&mut courses);
+        let algorithms = courses
+            .iter()
+            .find(|course| course.code().to_string() == "CSCI 1570")
+            .unwrap();
+        assert_eq!(
+            algorithms.prerequisites(),
+            Some(&PrerequisiteTree::try_from("CSCI 1450 or MATH 1010").unwrap())
+        );
+    }
+
+    #[test]
+    fn seminars() {
+        let record = |code: &str, section: &str, srcdb: &str, max: u16, extra: &str| {
+            raw_record(json!({
+                "code": code, "section": section, "srcdb": srcdb,
+                "registration_restrictions": extra, "seats":
+                format!(r#"<span class="seats_max">{max}</span><span class="seats_avail">0</span>"#),
+                "attr_html": if extra.is_empty() { "FYS" } else { "" },
+            }))
+        };
+        let first_years = r#"<p class="cls">Enrollment limited to students with a semester level of 01 or 02.</p>"#;
+        let raw = [
+            record("ENGL 0150", "S01", "202110", 40, ""),
+            record("ENGL 0150", "S01", "202210", 19, ""),
+            record("ENGL 0150", "S02", "202210", 17, ""),
+            record("HIST 0970", "S01", "202210", 20, first_years),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let flags: Vec<_> = courses
+            .iter()
+            .map(|course| {
+                (
+                    course.is_first_year_seminar(),
+                    course.is_sophomore_seminar(),
+                    course.enrollment_cap(),
+                )
+            })
+            .collect();
+        assert_eq!(flags, [(true, false, Some(19)), (true, false, Some(20))]);
+    }
+
+    #[test]
+    fn fees() {
+        let lab = fee("<p>Lab fee: $75.00</p>").unwrap();
+        assert_eq!(
+            (lab.cents, lab.description.as_str()),
+            (7500, "Lab fee: $75.00")
+        );
+        assert_eq!(lab.to_string(), "$75.00");
+        let both = fee("Studio fee of $1,200 and a materials fee of $35.50").unwrap();
+        assert_eq!(both.to_string(), "$1235.50");
+        let rate = fee("Lab fee of $75, and $20 per credit hour for the field trip").unwrap();
+        assert_eq!(rate.cents, 7500);
+        assert_eq!(fee("$20 per credit"), None);
+        assert_eq!(fee("Lab fee: $50,000,000"), None);
+        assert_eq!(
+            fee("Studio fee of $30,000,000 and a materials fee of $20,000,000"),
+            None
+        );
+        assert_eq!(fee("Fees vary"), None);
+        assert_eq!(fee(""), None);
+    }
+
+    #[test]
+    fn registration_deadlines() {
+        let dates = registration_dates(
+            "<p>Registration changes end: 9/20/2022</p><p><strong>Last day to drop:</strong> 10/28/2022<br>Classes begin: Sept 7</p><p>Not a date</p>",
+        );
+        assert_eq!(
+            dates.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("classes_begin"), String::from("Sept 7")),
+                (String::from("last_day_to_drop"), String::from("2022-10-28")),
+                (
+                    String::from("registration_changes_end"),
+                    String::from("2022-09-20")
+                ),
+            ]
+        );
+        assert!(registration_dates("").is_empty());
+
+        let record = |section: &str, dates: &str| {
+            raw_record(json!({"section": section, "dates_html": dates}))
+        };
+        let raw = [
+            record("S01", "<p>Registration changes end: 09/20/2022</p>"),
+            record("S02", "<p>Registration changes end: 09/20/2022</p>"),
+            record("S03", "<p>Registration changes end: 10/25/2022</p>"),
+            record("S04", ""),
+        ]
+        .join("\n");
+        let (courses, _) = process(serde_json::de::StrRead::new(&raw), &Filters::default());
+        let terms = term_dates(&courses);
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].term.srcdb(), "202210");
+        assert_eq!(terms[0].dates["registration_changes_end"], "2022-09-20");
+        assert_eq!(terms[0].sections, 3);
+    }
+
+    #[test]
+    fn exams() {
+        let scheduled = exam("<p>Exam Group: 14<br/>Exam Date: 12/19/2022 2pm</p>").unwrap();
+        assert!(!scheduled.no_final());
+        assert_eq!(scheduled.group(), Some("14"));
+        assert_eq!(scheduled.date(), Some("12/19/2022 2pm"));
+        let none = exam("<p>No Final Exam</p>").unwrap();
+        assert!(none.no_final());
+        assert_eq!(none.group(), None);
+        assert_eq!(exam(""), None);
+    }
+
+    #[test]
+    fn validation() {
+        let mut raw: Value = serde_json::from_str(&raw_record(json!({}))).unwrap();
+        assert_eq!(validate(raw.to_string().as_bytes()), Ok(()));
+        raw["permreq"] = "maybe".into();
+        assert!(validate(raw.to_string().as_bytes()).is_err());
+        raw.as_object_mut().unwrap().remove("permreq");
+        assert!(validate(raw.to_string().as_bytes()).is_err());
+        assert!(validate(b"<html>rate limited</html>").is_err());
+    }
+
+    #[test]
+    fn parse_failures() {
+        let raw = |restrictions: &str, demographics: &str| {
+            raw_record(json!({
+                "registration_restrictions": restrictions, "regdemog_json": demographics,
+            }))
+        };
+        let input = [
+            raw(r#"<p class="prereq">Prerequisites: CSCI 0111.</p>"#, ""),
+            raw(r#"<p class="prereq">Prerequisites: CSCI 0111 (.</p>"#, ""),
+            raw(r#"<p class="new">Something new.</p>"#, r#"{"Fr": "#),
+            String::from("[1, 2]"),
+        ]
+        .join("\n");
+        let failures = ParseFailures::new(serde_json::de::StrRead::new(&input));
+        let expected = ParseFailures {
+            records: 1,
+            prerequisites: 1,
+            restrictions: 1,
+            demographics: 1,
+        };
+        assert_eq!(failures, expected);
+        assert!(failures.regressions(&expected).is_empty());
+        assert_eq!(failures.regressions(&ParseFailures::default()).len(), 4);
+    }
+
+    #[test]
+    fn malformed_records_midstream() {
+        let raw = |srcdb: &str| {
+            raw_record(json!({
+                "registration_restrictions": r#"<p class="new">Something new.</p><p class="cls">Enrollment limited to students with a semester level of 05, 06, 07 or 08.</p>"#,
+                "regdemog_json": r#"{"FY": 3, "Sr": 2}"#, "srcdb": srcdb,
+            }))
+        };
+        let input = [
+            raw("202110"),
+            String::from("[1, 2]"),
+            raw("202210"),
+            raw("202220"),
+        ]
+        .join("\n");
+        let failures = ParseFailures::new(serde_json::de::StrRead::new(&input));
+        assert_eq!((failures.records, failures.restrictions), (1, 3));
+        let (inconsistencies, skipped) = audit_demographics(serde_json::de::StrRead::new(&input));
+        assert_eq!((inconsistencies.len(), skipped), (3, 1));
+    }
+}
+
+#[derive(Debug)]
+struct Qualifications {
+    prerequisites: Option<PrerequisiteTree>,
+    /// Courses to be taken in the same semester, rather than before
+    corequisites: Option<PrerequisiteTree>,
+    programs: Vec<ProgramRestriction>,
+    concentrations: Concentrations,
+    /// The only cohort of students who may enroll, like `PLME`
+    cohort: Option<String>,
+    semester_range: SemesterRange,
+    /// Paragraphs in a form `registration::sentences` doesn't know, which are left out
+    unrecognized: Vec<String>,
+}
+
+/// Programs, like `Computer Science (SCB)` or `Public Health (MPH)`, whose students
+/// alone may enroll in a course, or may not
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgramRestriction {
+    Allowed(Vec<String>),
+    Excluded(Vec<String>),
+}
+
+/// The majors, or concentrations, a course is limited to or closed to
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Concentrations {
+    /// If any, only students in one of these may enroll
+    pub allowed: Vec<String>,
+    /// Students in any of these may not enroll
+    pub excluded: Vec<String>,
+}
+
+impl Concentrations {
+    pub fn is_empty(&self) -> bool {
+        self.allowed.is_empty() && self.excluded.is_empty()
+    }
+}
+
+/// Fails only on a prerequisite expression that can't be parsed
+impl FromStr for Qualifications {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let tree = |string: &str| {
+            PrerequisiteTree::try_from(string)
+                .map_err(|e| format!("unparsable prerequisites {e:?}"))
+        };
+        let mut qualifications = Qualifications {
+            prerequisites: None,
+            corequisites: None,
+            programs: Vec::new(),
+            concentrations: Concentrations::default(),
+            cohort: None,
+            semester_range: SemesterRange::FULL,
+            unrecognized: Vec::new(),
+        };
+        let mut allowed_programs = Vec::new();
+        let mut excluded_programs = Vec::new();
+        for sentence in registration::sentences(string) {
+            match sentence {
+                Sentence::Prerequisites(string) => {
+                    qualifications.prerequisites = Some(tree(&string)?)
+                }
+                Sentence::Corequisites(string) => {
+                    qualifications.corequisites = Some(tree(&string)?)
+                }
+                Sentence::SemesterLevels { semesters, allowed } => {
+                    let range = semesters
+                        .into_iter()
+                        .fold(SemesterRange::EMPTY, SemesterRange::add);
+                    let range = if allowed { range } else { range.complement() };
+                    qualifications.semester_range =
+                        qualifications.semester_range.intersection(range);
+                }
+                Sentence::Concentrations { names, allowed } => {
+                    let concentrations = &mut qualifications.concentrations;
+                    match allowed {
+                        true => concentrations.allowed.extend(names),
+                        false => concentrations.excluded.extend(names),
+                    }
+                }
+                Sentence::Programs { names, allowed } => match allowed {
+                    true => allowed_programs.extend(names),
+                    false => excluded_programs.extend(names),
+                },
+                Sentence::Level { level, allowed } => {
+                    let range = match level {
+                        Level::Undergraduate => SemesterRange::UNDERGRADUATE,
+                        Level::Graduate => SemesterRange::GRADUATE,
+                    };
+                    let range = if allowed { range } else { range.complement() };
+                    qualifications.semester_range =
+                        qualifications.semester_range.intersection(range);
+                }
+                Sentence::Cohort(cohort) => qualifications.cohort = Some(cohort),
+                Sentence::Unrecognized(paragraph) => qualifications.unrecognized.push(paragraph),
+            }
+        }
+        qualifications.programs = [
+            (!allowed_programs.is_empty()).then_some(ProgramRestriction::Allowed(allowed_programs)),
+            (!excluded_programs.is_empty())
+                .then_some(ProgramRestriction::Excluded(excluded_programs)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        Ok(qualifications)
+    }
+}
+
+/// Each `Label: date` line of a section's registration dates, keyed by the label in
+/// snake case, like `registration_changes_end`. Dates like `09/20/2022` become
+/// `2022-09-20`, and anything else is kept as written.
+fn registration_dates(string: &str) -> BTreeMap<String, String> {
+    static LINE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"^([A-Za-z][A-Za-z/&' -]*):\s*(.+)$"#).unwrap());
+    static DATE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"^(\d{1,2})/(\d{1,2})/(\d{4})$"#).unwrap());
+    lines(string)
+        .iter()
+        .filter_map(|line| {
+            let captures = LINE.captures(line.trim())?;
+            let label = captures[1]
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+                .join("_");
+            let value = captures[2].trim();
+            let value = match DATE.captures(value) {
+                Some(date) => format!("{}-{:0>2}-{:0>2}", &date[3], &date[1], &date[2]),
+                None => value.to_string(),
+            };
+            Some((label, value))
+        })
+        .collect()
+}
+
+/// Each instructor's name and `instructor::identifier`. The details give each
+/// instructor an `<h4>` name, then contact information that usually links their email.
+fn instructors(string: &str) -> (Vec<String>, Vec<String>) {
+    let nodes = html::parse(string);
+    let elements = html::elements(&nodes);
+    let mut ret = (Vec::new(), Vec::new());
+    for (i, heading) in elements.iter().enumerate() {
+        if heading.name != "h4" {
+            continue;
+        }
+        let name = heading.text().replace('\u{a0}', " ");
+        let name = html::compose(name.trim()).into_owned();
+        if name == "TBD" {
+            continue;
+        }
+        let email = elements[i + 1..]
+            .iter()
+            .take_while(|element| element.name != "h4")
+            .filter_map(|element| element.attribute("href")?.strip_prefix("mailto:"))
+            .map(|email| email.split('?').next().unwrap())
+            .next();
+        ret.1.push(instructor::identifier(&name, email));
+        ret.0.push(name);
+    }
+    ret
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Day {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+fn days(string: &str) -> Option<Vec<Day>> {
+    static DAY: Lazy<Regex> = Lazy::new(|| Regex::new(r#"Th|Sa|Su|M|T|W|F"#).unwrap());
+    let mut ret = Vec::new();
+    let mut end = 0;
+    for day in DAY.find_iter(string) {
+        if day.start() != end {
+            return None;
+        }
+        end = day.end();
+        ret.push(match day.as_str() {
+            "M" => Day::Monday,
+            "T" => Day::Tuesday,
+            "W" => Day::Wednesday,
+            "Th" => Day::Thursday,
+            "F" => Day::Friday,
+            "Sa" => Day::Saturday,
+            "Su" => Day::Sunday,
+            _ => unreachable!(),
+        });
+    }
+    (end == string.len()).then_some(ret)
+}
+
+/// Time of day on a 24 hour clock.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    hour: u8,
+    minute: u8,
+}
+
+impl Time {
+    /// `hour` is on a 12 hour clock
+    fn new(hour: u8, minute: u8, pm: bool) -> Time {
+        let hour = hour % 12 + if pm { 12 } else { 0 };
+        Time { hour, minute }
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Meeting {
+    days: Vec<Day>,
+    start: Time,
+    end: Time,
+    location: Option<String>,
+    #[serde(default)]
+    building: Option<String>,
+    #[serde(default)]
+    room: Option<String>,
+}
+
+impl Meeting {
+    pub fn days(&self) -> &[Day] {
+        &self.days
+    }
+
+    pub fn start(&self) -> Time {
+        self.start
+    }
+
+    pub fn end(&self) -> Time {
+        self.end
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    pub fn building(&self) -> Option<&str> {
+        self.building.as_deref()
+    }
+
+    pub fn room(&self) -> Option<&str> {
+        self.room.as_deref()
+    }
+}
+
+/// Splits a location like `Barus & Holley 166` into its building and room. The
+/// room is the last word, if it has a digit; otherwise the whole location is the building.
+fn building_and_room(location: &str) -> (Option<String>, Option<String>) {
+    let location = location.trim();
+    match location.rsplit_once(' ') {
+        Some((building, room)) if room.chars().any(|c| c.is_ascii_digit()) => {
+            (Some(building.trim().to_string()), Some(room.to_string()))
+        }
+        _ if location.is_empty() => (None, None),
+        _ => (Some(location.to_string()), None),
+    }
+}
+
+/// Parses meeting patterns like `TTh 1-2:20p` or `MWF 10am-10:50am`.
+/// Meetings that are to be announced or don't have a time are skipped.
+fn meetings(string: &str) -> Vec<Meeting> {
+    static TIME: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"^(?P<days>[A-Za-z]+) (?P<start_hour>\d{1,2})(:(?P<start_minute>\d{2}))?(?P<start_meridiem>[ap])?m?-(?P<end_hour>\d{1,2})(:(?P<end_minute>\d{2}))?(?P<end_meridiem>[ap])m?"#).unwrap()
+    });
+    let nodes = html::parse(string);
+    html::select(&nodes, "div", "meet")
+        .into_iter()
+        .filter_map(|meet| {
+            let is_location = |node: &html::Node| {
+                matches!(node, html::Node::Element(span) if span.has_class("meet-loc"))
+            };
+            let (location, time): (Vec<_>, Vec<_>) =
+                meet.children.iter().cloned().partition(is_location);
+            let time = text(&time);
+            let captures = TIME.captures(&time)?;
+            let days = days(&captures["days"])?;
+            let number = |name| {
+                captures
+                    .name(name)
+                    .map_or(0, |m| m.as_str().parse::<u8>().unwrap())
+            };
+            let end = Time::new(
+                number("end_hour"),
+                number("end_minute"),
+                &captures["end_meridiem"] == "p",
+            );
+            let start = match captures.name("start_meridiem") {
+                Some(meridiem) => Time::new(
+                    number("start_hour"),
+                    number("start_minute"),
+                    meridiem.as_str() == "p",
+                ),
+                None => {
+                    // the meridiem is shared with the end time unless that would start after it
+                    let start = Time::new(number("start_hour"), number("start_minute"), true);
+                    if start <= end {
+                        start
+                    } else {
+                        Time::new(number("start_hour"), number("start_minute"), false)
+                    }
+                }
+            };
+            let location = Some(text(&location))
+                .filter(|location| !location.is_empty())
+                .map(|location| location.trim_start_matches("in ").to_string());
+            let (building, room) = location.as_deref().map_or((None, None), building_and_room);
+            Some(Meeting {
+                days,
+                start,
+                end,
+                location,
+                building,
+                room,
+            })
+        })
+        .collect()
+}
+
+/// A designation Brown gives courses, like WRIT for writing designated courses
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(from = "String", into = "String")]
+pub enum Attribute {
+    /// WRIT
+    Writing,
+    /// DIAP, Diversity and Inclusion Action Plan
+    Diversity,
+    /// CBLR, community based learning and research
+    CommunityBased,
+    /// FYS
+    FirstYearSeminar,
+    /// SOPH
+    SophomoreSeminar,
+    Other(String),
+}
+
+impl From<String> for Attribute {
+    fn from(code: String) -> Attribute {
+        match code.as_str() {
+            "WRIT" => Attribute::Writing,
+            "DIAP" => Attribute::Diversity,
+            "CBLR" => Attribute::CommunityBased,
+            "FYS" => Attribute::FirstYearSeminar,
+            "SOPH" => Attribute::SophomoreSeminar,
+            _ => Attribute::Other(code),
+        }
+    }
+}
+
+impl From<Attribute> for String {
+    fn from(attribute: Attribute) -> String {
+        attribute.to_string()
+    }
+}
+
+impl FromStr for Attribute {
+    type Err = Infallible;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(Attribute::from(string.to_string()))
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Attribute::Writing => "WRIT",
+            Attribute::Diversity => "DIAP",
+            Attribute::CommunityBased => "CBLR",
+            Attribute::FirstYearSeminar => "FYS",
+            Attribute::SophomoreSeminar => "SOPH",
+            Attribute::Other(code) => code,
+        })
+    }
+}
+
+/// Parses attribute lists like `<ul><li>Writing-designated (WRIT)</li><li>FYS</li></ul>`,
+/// where each item is either a code or a description with the code in parentheses
+fn attributes(string: &str) -> Vec<Attribute> {
+    static CODE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\(([A-Z0-9]{2,6})\)\s*$"#).unwrap());
+    let mut attributes: Vec<Attribute> = list_items(string)
+        .into_iter()
+        .map(|item| match CODE.captures(&item) {
+            Some(captures) => Attribute::from(captures[1].to_string()),
+            None => Attribute::from(item),
+        })
+        .collect();
+    attributes.sort();
+    attributes.dedup();
+    attributes
+}
+
+/// How a course can be graded, all false when the catalog doesn't say
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GradingOptions {
+    /// ABC/NC, Brown's letter grades
+    pub letter: bool,
+    /// S/NC, satisfactory or no credit
+    pub satisfactory: bool,
+    pub audit: bool,
+    /// The course sets the grade option rather than the student choosing it
+    pub mandatory: bool,
+}
+
+impl GradingOptions {
+    /// Can only be taken S/NC
+    pub fn satisfactory_only(&self) -> bool {
+        self.satisfactory && !self.letter
+    }
+}
+
+/// From the grade mode items, like `<li>Mandatory S/NC</li><li>Audit</li>`
+fn grading(string: &str) -> GradingOptions {
+    let mut grading = GradingOptions::default();
+    for item in list_items(string).iter().map(|item| item.to_lowercase()) {
+        if item.contains("mandatory") {
+            grading.mandatory = true;
+        }
+        if item.contains("student option") {
+            grading.letter = true;
+            grading.satisfactory = true;
+        }
+        if item.contains("abc") || item.contains("letter") {
+            grading.letter = true;
+        }
+        if item.contains("s/nc") || item.contains("satisfactory") {
+            grading.satisfactory = true;
+        }
+        if item.contains("audit") {
+            grading.audit = true;
+        }
+    }
+    grading
+}
+
+/// Credit hours, a range for variable credit courses
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Credits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl fmt::Display for Credits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.min == self.max {
+            true => write!(f, "{}", self.min),
+            false => write!(f, "{}-{}", self.min, self.max),
+        }
+    }
+}
+
+/// A lab or materials fee, in addition to tuition
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Fee {
+    /// Of every flat amount the description gives, like a lab fee and a materials fee,
+    /// but not of rates like `$20 per credit`, which aren't part of the same total
+    pub cents: u32,
+    /// As the catalog gives it, like `Lab fee: $75`
+    pub description: String,
+}
+
+impl fmt::Display for Fee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}.{:02}", self.cents / 100, self.cents % 100)
+    }
+}
+
+/// Parses fees like `<p>Lab fee: $75.00</p>`, or `None` if they name no flat amount,
+/// or more than a `u32` of cents
+fn fee(string: &str) -> Option<Fee> {
+    static AMOUNT: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r#"\$\s*(?P<dollars>\d[\d,]*)(\.(?P<cents>\d{2}))?(?P<rate>\s*(/|per\b|each\b))?"#,
+        )
+        .unwrap()
+    });
+    let description = strip_html(string).trim().to_string();
+    let mut cents: Option<u32> = None;
+    for captures in AMOUNT.captures_iter(&description) {
+        if captures.name("rate").is_some() {
+            continue;
+        }
+        let dollars: u32 = captures["dollars"].replace(',', "").parse().ok()?;
+        let part = captures
+            .name("cents")
+            .map_or(Some(0), |c| c.as_str().parse().ok())?;
+        let amount = dollars.checked_mul(100)?.checked_add(part)?;
+        cents = Some(cents.unwrap_or(0).checked_add(amount)?);
+    }
+    Some(Fee {
+        cents: cents?,
+        description,
+    })
+}
+
+/// Parses hours like `1`, `0.5`, `1-4`, or `Variable 1 to 4`
+fn credits(string: &str) -> Option<Credits> {
+    static HOURS: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"(?P<min>\d+(\.\d+)?)(\s*(-|–|to)\s*(?P<max>\d+(\.\d+)?))?"#).unwrap()
+    });
+    let captures = HOURS.captures(string)?;
+    let min = captures["min"].parse().ok()?;
+    let max = match captures.name("max") {
+        Some(max) => max.as_str().parse().ok()?,
+        None => min,
+    };
+    Some(Credits { min, max })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Active,
+    Cancelled,
+}
+
+fn status(string: &str) -> Status {
+    match string {
+        "X" | "C" | "Cancelled" => Status::Cancelled,
+        _ => Status::Active,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Exam {
+    /// The course has no final exam
+    no_final: bool,
+    group: Option<String>,
+    date: Option<String>,
+}
+
+impl Exam {
+    pub fn no_final(&self) -> bool {
+        self.no_final
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+}
+
+/// Parses text like `Exam Group: 14, Exam Date: 12/19/2022 2pm` or `No Final Exam`
+fn exam(string: &str) -> Option<Exam> {
+    static NO_FINAL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)no final exam"#).unwrap());
+    static GROUP: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"(?i)exam group:? *([^,\n]+?) *(,|\n|$)"#).unwrap());
+    static DATE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"(?i)exam date:? *([^,\n]+?) *(,|\n|$)"#).unwrap());
+    let string = lines(string).join("\n");
+    let capture = |regex: &Regex| {
+        regex
+            .captures(&string)
+            .map(|captures| captures[1].to_string())
+    };
+    let exam = Exam {
+        no_final: NO_FINAL.is_match(&string),
+        group: capture(&GROUP),
+        date: capture(&DATE),
+    };
+    (exam != Exam::default()).then_some(exam)
+}
+
+/// A textbook listed by the bookstore for a section
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Book {
+    #[serde(default)]
+    isbn: String,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Debug)]
+struct Record {
+    status: Status,
+    restricted: bool,
+    code: CourseCode,
+    section: Option<u8>,
+    title: Title,
+    description: String,
+    qualifications: Qualifications,
+    enrollment: Option<u16>,
+    capacity: Option<u16>,
+    available: Option<i16>,
+    waitlist: Option<u16>,
+    instructors: Vec<String>,
+    instructor_ids: Vec<String>,
+    meetings: Vec<Meeting>,
+    books: Vec<Book>,
+    exam: Option<Exam>,
+    demographics: Option<Demographics>,
+    credits: Option<Credits>,
+    fee: Option<Fee>,
+    attributes: Vec<Attribute>,
+    grading: GradingOptions,
+    registration_dates: BTreeMap<String, String>,
+    /// Other codes the search listed the section under
+    listed_as: Vec<CourseCode>,
+    srcdb: String,
+}
+
+/// Why a detail response can't become a `Record`: which field, holding what
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    /// The key in the detail response, like `permreq`
+    pub field: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+impl RecordError {
+    fn new(field: &'static str, value: &str, reason: impl Into<String>) -> RecordError {
+        RecordError {
+            field,
+            value: value.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in {}: {:?}", self.reason, self.field, self.value)
+    }
+}
+
+impl FromStr for Record {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let raw: Raw = serde_json::from_str(string).map_err(|e| e.to_string())?;
+        Record::try_from(raw).map_err(|e| e.to_string())
+    }
+}
+
+impl Raw {
+    fn restricted(&self) -> Result<bool, RecordError> {
+        yes_or_no(&self.permreq)
+            .ok_or_else(|| RecordError::new("permreq", &self.permreq, "neither Y nor N"))
+    }
+
+    fn code(&self) -> Result<CourseCode, RecordError> {
+        CourseCode::try_from(self.code.as_str())
+            .map_err(|_| RecordError::new("code", &self.code, "not a course code"))
+    }
+}
+
+/// Fails with the field that can't be used
+impl TryFrom<Raw> for Record {
+    type Error = RecordError;
+    fn try_from(raw: Raw) -> Result<Record, RecordError> {
+        let status = status(&raw.stat);
+        let restricted = raw.restricted()?;
+        let code = raw.code()?;
+        let section = section(&raw.section);
+        let Ok(title) = Title::from_str(&raw.title);
+        let description = strip_html(&raw.description);
+        let qualifications =
+            Qualifications::from_str(&raw.registration_restrictions).map_err(|e| {
+                RecordError::new(
+                    "registration_restrictions",
+                    &raw.registration_restrictions,
+                    e,
+                )
+            })?;
+        let seats = seats(&raw.seats);
+        let enrollment_html = enrollment_from_html(&raw.regdemog_html);
+        let enrollment = seats.map(|seats| seats.enrollment()).or(enrollment_html);
+        let capacity = seats.map(|seats| seats.max.max(0) as u16);
+        let available = seats.map(|seats| seats.available);
+        let waitlist = seats.and_then(|seats| seats.waitlist);
+        let (instructors, instructor_ids) = instructors(&raw.instructordetail_html);
+        let meetings = meetings(&raw.meeting_html);
+        let books = raw.books;
+        let exam = exam(&raw.exam_html);
+        let demographics = serde_json::from_str(&raw.regdemog_json).ok();
+        let credits = credits(&raw.hours);
+        let fee = fee(&raw.fee);
+        let attributes = attributes(&raw.attributes);
+        let grading = grading(&raw.gmods);
+        let registration_dates = registration_dates(&raw.dates_html);
+        let listed_as = raw.search.aliases(&code);
+        let srcdb = raw.srcdb;
+        Ok(Record {
+            status,
+            restricted,
+            code,
+            section,
+            title,
+            description,
+            qualifications,
+            enrollment,
+            capacity,
+            available,
+            waitlist,
+            instructors,
+            instructor_ids,
+            meetings,
+            books,
+            exam,
+            demographics,
+            credits,
+            fee,
+            attributes,
+            grading,
+            registration_dates,
+            listed_as,
+            srcdb,
+        })
+    }
+}
+
+/// Checks that a detail response has the fields `process` needs, in forms it
+/// understands, returning why not otherwise
+pub fn validate(json: &[u8]) -> Result<(), String> {
+    let raw: Raw = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+    raw.restricted().map_err(|e| e.to_string())?;
+    raw.code().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Numbers of detail responses with parts `process` can't parse
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParseFailures {
+    /// Responses that aren't details at all
+    pub records: usize,
+    pub prerequisites: usize,
+    /// Registration restrictions with a paragraph `registration::sentences` doesn't know
+    pub restrictions: usize,
+    pub demographics: usize,
+}
+
+impl ParseFailures {
+    pub fn new<'a, R: de::Read<'a>>(source: R) -> ParseFailures {
+        let mut failures = ParseFailures::default();
+        for value in StreamDeserializer::<_, Value>::new(source) {
+            let value = match value {
+                Ok(value) => value,
+                Err(e) if e.is_io() || e.is_eof() => break,
+                Err(_) => {
+                    // the rest of the input can't be read past a syntax error
+                    failures.records += 1;
+                    break;
+                }
+            };
+            let raw = match Raw::deserialize(&value) {
+                Ok(raw) => raw,
+                Err(_) => {
+                    failures.records += 1;
+                    continue;
+                }
+            };
+            let mut unrecognized = false;
+            for sentence in registration::sentences(&raw.registration_restrictions) {
+                match sentence {
+                    Sentence::Prerequisites(tree) | Sentence::Corequisites(tree)
+                        if PrerequisiteTree::try_from(tree.as_str()).is_err() =>
+                    {
+                        failures.prerequisites += 1
+                    }
+                    Sentence::Unrecognized(_) => unrecognized = true,
+                    _ => {}
+                }
+            }
+            if unrecognized {
+                failures.restrictions += 1;
+            }
+            if !raw.regdemog_json.is_empty()
+                && serde_json::from_str::<Demographics>(&raw.regdemog_json).is_err()
+            {
+                failures.demographics += 1;
+            }
+        }
+        failures
+    }
+
+    /// Each kind of failure that happens more often than in `baseline`
+    pub fn regressions(&self, baseline: &ParseFailures) -> Vec<String> {
+        [
+            ("records", self.records, baseline.records),
+            ("prerequisites", self.prerequisites, baseline.prerequisites),
+            ("restrictions", self.restrictions, baseline.restrictions),
+            ("demographics", self.demographics, baseline.demographics),
+        ]
+        .into_iter()
+        .filter(|&(_, now, before)| now > before)
+        .map(|(kind, now, before)| format!("{kind}: {now} failures, up from {before}"))
+        .collect()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Raw {
+    #[serde(default)]
+    stat: String,
+    permreq: String,
+    code: String,
+    section: String,
+    title: String,
+    description: String,
+    registration_restrictions: String,
+    seats: String,
+    instructordetail_html: String,
+    #[serde(default)]
+    meeting_html: String,
+    #[serde(default)]
+    exam_html: String,
+    regdemog_html: String,
+    regdemog_json: String,
+    srcdb: String,
+    #[serde(default)]
+    books: Vec<Book>,
+    #[serde(default)]
+    #[serde(alias = "credit_hours")]
+    hours: String,
+    #[serde(default)]
+    #[serde(alias = "attr_html")]
+    attributes: String,
+    /// Grade modes, like `<li>Student Option</li><li>Audit</li>`
+    #[serde(default)]
+    #[serde(alias = "grade_mode")]
+    gmods: String,
+    /// How the search listed the section, added by `download`
+    #[serde(default)]
+    search: Listing,
+    /// Registration deadlines, like `<p>Registration changes end: 09/20/2022</p>`
+    #[serde(default)]
+    dates_html: String,
+    /// Lab or materials fees, like `<p>Lab fee: $75.00</p>`
+    #[serde(default)]
+    #[serde(alias = "fees_html")]
+    fee: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Listing {
+    /// Like `code:VISA 1110`, one for each code a cross-listed section goes by
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+impl Listing {
+    /// The codes the section was listed under other than `code`
+    fn aliases(&self, code: &CourseCode) -> Vec<CourseCode> {
+        self.groups
+            .iter()
+            .filter_map(|group| group.strip_prefix("code:"))
+            .filter_map(|group| CourseCode::try_from(group).ok())
+            .filter(|alias| alias != code)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Offering {
+    /// The srcdb
+    date: String,
+    /// `date` as a year and season, missing from outputs processed before it was kept
+    #[serde(default)]
+    term: Option<Term>,
+    section: u8,
+    #[serde(default)]
+    status: Status,
+    instructors: Vec<String>,
+    /// The `instructor::identifier` of each of `instructors`
+    #[serde(default)]
+    instructor_ids: Vec<String>,
+    #[serde(default)]
+    meetings: Vec<Meeting>,
+    #[serde(default)]
+    books: Vec<Book>,
+    #[serde(default)]
+    exam: Option<Exam>,
+    enrollment: Option<u16>,
+    /// The most students the section takes
+    #[serde(default)]
+    capacity: Option<u16>,
+    /// Open seats, negative when the section is over-enrolled
+    #[serde(default)]
+    available: Option<i16>,
+    /// Students waiting for a seat, for sections with a waitlist
+    #[serde(default)]
+    waitlist: Option<u16>,
+    demographics: Option<Demographics>,
+    #[serde(default)]
+    credits: Option<Credits>,
+    #[serde(default)]
+    fee: Option<Fee>,
+    /// Who could enroll in this offering, missing from outputs processed before it was kept
+    #[serde(default)]
+    semester_range: Option<SemesterRange>,
+    /// Of this offering, missing from outputs processed before they were kept
+    #[serde(default)]
+    attributes: Option<Vec<Attribute>>,
+    #[serde(default)]
+    grading: Option<GradingOptions>,
+    /// From `registration_dates`
+    #[serde(default)]
+    registration_dates: BTreeMap<String, String>,
+}
+
+impl Offering {
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    pub fn term(&self) -> Option<Term> {
+        self.term.or_else(|| self.date.parse().ok())
+    }
+
+    /// Like `registration_changes_end` to `2022-09-20`
+    pub fn registration_dates(&self) -> &BTreeMap<String, String> {
+        &self.registration_dates
+    }
+
+    pub fn section(&self) -> u8 {
+        self.section
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn instructors(&self) -> &[String] {
+        &self.instructors
+    }
+
+    pub fn instructor_ids(&self) -> &[String] {
+        &self.instructor_ids
+    }
+
+    pub fn semester_range(&self) -> Option<SemesterRange> {
+        self.semester_range
+    }
+
+    pub fn enrollment(&self) -> Option<u16> {
+        self.enrollment
+    }
+
+    pub fn capacity(&self) -> Option<u16> {
+        self.capacity
+    }
+
+    pub fn available(&self) -> Option<i16> {
+        self.available
+    }
+
+    pub fn waitlist(&self) -> Option<u16> {
+        self.waitlist
+    }
+
+    pub fn meetings(&self) -> &[Meeting] {
+        &self.meetings
+    }
+
+    pub fn books(&self) -> &[Book] {
+        &self.books
+    }
+
+    pub fn exam(&self) -> Option<&Exam> {
+        self.exam.as_ref()
+    }
+
+    pub fn demographics(&self) -> Option<&Demographics> {
+        self.demographics.as_ref()
+    }
+
+    pub fn credits(&self) -> Option<Credits> {
+        self.credits
+    }
+
+    pub fn fee(&self) -> Option<&Fee> {
+        self.fee.as_ref()
+    }
+}
+
+/// A line of `sections.jsonl`: one section of a course in one term, keyed by
+/// `code`, `srcdb`, and `section`, with the detail that `Course` aggregates away
+#[derive(Serialize, Debug)]
+pub struct Section<'a> {
+    pub code: &'a CourseCode,
+    pub srcdb: &'a str,
+    pub term: Option<Term>,
+    pub section: u8,
+    pub status: Status,
+    pub instructors: &'a [String],
+    pub instructor_ids: &'a [String],
+    pub meetings: &'a [Meeting],
+    pub books: &'a [Book],
+    pub exam: Option<&'a Exam>,
+    pub enrollment: Option<u16>,
+    pub capacity: Option<u16>,
+    pub available: Option<i16>,
+    pub waitlist: Option<u16>,
+    pub demographics: Option<&'a Demographics>,
+    pub credits: Option<Credits>,
+    pub fee: Option<&'a Fee>,
+    pub semester_range: Option<SemesterRange>,
+    pub registration_dates: &'a BTreeMap<String, String>,
+}
+
+/// Another code a course was cross-listed under, from the `first` to the `last` term
+/// (as srcdb strings) that the cross-listing was seen.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Alias {
+    code: CourseCode,
+    first: String,
+    last: String,
+}
+
+impl Alias {
+    /// `terms` can't be empty
+    fn new(code: CourseCode, terms: &BTreeSet<String>) -> Alias {
+        let first = terms.first().unwrap().clone();
+        let last = terms.last().unwrap().clone();
+        Alias { code, first, last }
+    }
+
+    pub fn code(&self) -> &CourseCode {
+        &self.code
+    }
+
+    pub fn valid_in(&self, term: &str) -> bool {
+        self.first.as_str() <= term && term <= self.last.as_str()
+    }
+}
+
+/// Finds the canonical course of an alias as of a given term
+pub struct AliasMap<'a> {
+    aliases: HashMap<&'a CourseCode, Vec<(&'a Alias, &'a CourseCode)>>,
+    /// With entries of their own
+    courses: HashSet<&'a CourseCode>,
+}
+
+impl<'a> AliasMap<'a> {
+    pub fn new<I: IntoIterator<Item = &'a Course>>(courses: I) -> AliasMap<'a> {
+        let mut aliases: HashMap<_, Vec<_>> = HashMap::new();
+        let mut codes = HashSet::new();
+        for course in courses {
+            codes.insert(&course.code);
+            for alias in course.aliases.iter() {
+                aliases
+                    .entry(&alias.code)
+                    .or_default()
+                    .push((alias, &course.code));
+            }
+        }
+        AliasMap {
+            aliases,
+            courses: codes,
+        }
+    }
+
+    pub fn canonical(&self, code: &CourseCode, term: &str) -> Option<&'a CourseCode> {
+        self.aliases
+            .get(code)?
+            .iter()
+            .find(|(alias, _)| alias.valid_in(term))
+            .map(|&(_, canonical)| canonical)
+    }
+
+    /// The canonical course of `code` as of `term`, or if it was never a course of
+    /// its own, as of the last term it was an alias
+    pub fn resolve(&self, code: &CourseCode, term: &str) -> Option<&'a CourseCode> {
+        self.canonical(code, term).or_else(|| {
+            if self.courses.contains(code) {
+                return None;
+            }
+            self.aliases
+                .get(code)?
+                .iter()
+                .max_by(|(a, _), (b, _)| a.last.cmp(&b.last))
+                .map(|&(_, canonical)| canonical)
+        })
+    }
+
+    /// `tree` with each course that `resolve` finds replaced by its canonical course
+    pub fn canonicalize(&self, tree: &PrerequisiteTree, term: &str) -> PrerequisiteTree {
+        tree.map_qualifications(|qualification| {
+            let canonical = |code| self.resolve(code, term).unwrap_or(code).clone();
+            match qualification {
+                Qualification::Course(code) => Qualification::Course(canonical(code)),
+                Qualification::Concurrent(code) => Qualification::Concurrent(canonical(code)),
+                Qualification::CourseGrade { code, min_grade } => Qualification::CourseGrade {
+                    code: canonical(code),
+                    min_grade: *min_grade,
+                },
+                qualification => qualification.clone(),
+            }
+        })
+    }
+
+    /// Each alias code, like `APMA 1690`, to the courses it stood for and when,
+    /// for `aliases.json`
+    pub fn entries(&self) -> BTreeMap<String, Vec<AliasEntry<'a>>> {
+        self.aliases
+            .iter()
+            .map(|(code, aliases)| {
+                let mut entries: Vec<_> = aliases
+                    .iter()
+                    .map(|&(alias, canonical)| AliasEntry {
+                        canonical,
+                        first: &alias.first,
+                        last: &alias.last,
+                    })
+                    .collect();
+                entries.sort_by_key(|entry| entry.first);
+                (code.to_string(), entries)
+            })
+            .collect()
+    }
+}
+
+/// An alias's canonical course, from its `first` term to its `last`
+#[derive(Serialize, Debug)]
+pub struct AliasEntry<'a> {
+    pub canonical: &'a CourseCode,
+    pub first: &'a str,
+    pub last: &'a str,
+}
+
+/// Rewrites the courses named in prerequisites and corequisites to their canonical
+/// codes, as of each course's latest offering, so that every reference to a course
+/// leads to its own entry
+pub fn canonicalize_aliases(courses: &mut [Course]) {
+    let trees: Vec<_> = {
+        let aliases = AliasMap::new(courses.iter());
+        courses
+            .iter()
+            .map(|course| {
+                let term = course
+                    .offerings
+                    .iter()
+                    .map(|offering| offering.date.as_str())
+                    .max()
+                    .unwrap_or_default();
+                let canonicalize = |tree: Option<&PrerequisiteTree>| {
+                    tree.map(|tree| aliases.canonicalize(tree, term))
+                };
+                (
+                    canonicalize(course.prerequisites()),
+                    canonicalize(course.corequisites()),
+                )
+            })
+            .collect()
+    };
+    for (course, (prerequisites, corequisites)) in courses.iter_mut().zip(trees) {
+        course.prerequisites = prerequisites;
+        course.corequisites = corequisites;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Course {
+    code: CourseCode,
+    title: String,
+    description: String,
+    prerequisites: Option<PrerequisiteTree>,
+    /// To be taken in the same semester, if not before
+    #[serde(default)]
+    corequisites: Option<PrerequisiteTree>,
+    semester_range: SemesterRange,
+    /// Of the most recent offering
+    #[serde(default)]
+    programs: Vec<ProgramRestriction>,
+    /// Of the most recent offering
+    #[serde(default)]
+    concentrations: Concentrations,
+    /// Of the most recent offering
+    #[serde(default)]
+    cohort: Option<String>,
+    restricted: bool,
+    /// Of the most recent offering
+    #[serde(default)]
+    credits: Option<Credits>,
+    /// Of the most recent offering
+    #[serde(default)]
+    fee: Option<Fee>,
+    /// Of the most recent offering
+    #[serde(default)]
+    attributes: Vec<Attribute>,
+    /// Of the most recent offering
+    #[serde(default)]
+    grading: GradingOptions,
+    aliases: Vec<Alias>,
+    offerings: Vec<Offering>,
+    /// From `annotations.toml`, never from the catalog
+    #[serde(default)]
+    annotation: Option<Annotation>,
+    /// From the rules in `tags.toml`, applied when processing
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Of the offerings, by term
+    #[serde(default)]
+    demographics: DemographicsHistory,
+    /// Each change to the prerequisites, oldest first
+    #[serde(default)]
+    prerequisite_history: Vec<PrerequisiteChange>,
+    /// Each change to the title or description, oldest first
+    #[serde(default)]
+    title_history: Vec<TitleChange>,
+    /// Each change to the corequisites or restrictions, oldest first
+    #[serde(default)]
+    restriction_history: Vec<RestrictionChange>,
+    /// Designated FYS, or only open to first years
+    #[serde(default)]
+    is_first_year_seminar: bool,
+    /// Designated SOPH, or only open to sophomores
+    #[serde(default)]
+    is_sophomore_seminar: bool,
+    /// The most students a section of the latest term takes
+    #[serde(default)]
+    enrollment_cap: Option<u16>,
+}
+
+impl Course {
+    pub fn code(&self) -> &CourseCode {
+        &self.code
+    }
+
+    pub fn prerequisites(&self) -> Option<&PrerequisiteTree> {
+        self.prerequisites.as_ref()
+    }
+
+    pub fn prerequisites_mut(&mut self) -> &mut Option<PrerequisiteTree> {
+        &mut self.prerequisites
+    }
+
+    pub fn corequisites(&self) -> Option<&PrerequisiteTree> {
+        self.corequisites.as_ref()
+    }
+
+    pub fn semester_range(&self) -> &SemesterRange {
+        &self.semester_range
+    }
+
+    pub fn programs(&self) -> &[ProgramRestriction] {
+        &self.programs
+    }
+
+    pub fn concentrations(&self) -> &Concentrations {
+        &self.concentrations
+    }
+
+    pub fn cohort(&self) -> Option<&str> {
+        self.cohort.as_deref()
+    }
+
+    pub fn annotation(&self) -> Option<&Annotation> {
+        self.annotation.as_ref()
+    }
+
+    pub fn set_annotation(&mut self, annotation: Annotation) {
+        self.annotation = Some(annotation);
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn credits(&self) -> Option<Credits> {
+        self.credits
+    }
+
+    pub fn fee(&self) -> Option<&Fee> {
+        self.fee.as_ref()
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    pub fn grading(&self) -> GradingOptions {
+        self.grading
+    }
+
+    pub fn offerings(&self) -> &[Offering] {
+        &self.offerings
+    }
+
+    pub fn aliases(&self) -> &[Alias] {
+        &self.aliases
+    }
+
+    pub fn demographics(&self) -> &DemographicsHistory {
+        &self.demographics
+    }
+
+    pub fn prerequisite_history(&self) -> &[PrerequisiteChange] {
+        &self.prerequisite_history
+    }
+
+    pub fn title_history(&self) -> &[TitleChange] {
+        &self.title_history
+    }
+
+    pub fn restriction_history(&self) -> &[RestrictionChange] {
+        &self.restriction_history
+    }
+
+    pub fn is_first_year_seminar(&self) -> bool {
+        self.is_first_year_seminar
+    }
+
+    pub fn is_sophomore_seminar(&self) -> bool {
+        self.is_sophomore_seminar
+    }
+
+    pub fn enrollment_cap(&self) -> Option<u16> {
+        self.enrollment_cap
+    }
+
+    /// The prerequisites together with who may enroll by semester, program, and
+    /// concentration, and whether the instructor has to let them in, as in
+    /// "CSCI 0160 and concentration in Computer Science"
+    pub fn requirements(&self) -> Option<PrerequisiteTree> {
+        let any = |names: &[String], qualification: fn(String) -> Qualification| {
+            let mut children: Vec<_> = names
+                .iter()
+                .map(|name| PrerequisiteTree::Qualification(qualification(name.clone())))
+                .collect();
+            match children.len() {
+                0 => None,
+                1 => children.pop(),
+                _ => Some(PrerequisiteTree::Operator(Operator::Any, children)),
+            }
+        };
+        let not = |tree| PrerequisiteTree::Operator(Operator::Not, vec![tree]);
+        let mut children: Vec<_> = self.prerequisites.iter().cloned().collect();
+        let range = self.semester_range;
+        if !range.is_full() && !range.is_empty() {
+            children.push(PrerequisiteTree::Qualification(
+                Qualification::SemesterLevel(range),
+            ));
+        }
+        for restriction in self.programs.iter() {
+            children.extend(match restriction {
+                ProgramRestriction::Allowed(names) => any(names, Qualification::Program),
+                ProgramRestriction::Excluded(names) => any(names, Qualification::Program).map(not),
+            });
+        }
+        let concentrations = &self.concentrations;
+        children.extend(any(&concentrations.allowed, Qualification::Concentration));
+        children.extend(any(&concentrations.excluded, Qualification::Concentration).map(not));
+        if self.restricted {
+            children.push(PrerequisiteTree::Qualification(
+                Qualification::InstructorPermission,
+            ));
+        }
+        match children.len() {
+            0 => None,
+            1 => children.pop(),
+            _ => Some(PrerequisiteTree::Operator(Operator::All, children)),
+        }
+    }
+
+    /// Each offering on its own, oldest term first
+    pub fn sections(&self) -> Vec<Section<'_>> {
+        let mut sections: Vec<_> = self
+            .offerings
+            .iter()
+            .map(|offering| Section {
+                code: &self.code,
+                srcdb: &offering.date,
+                term: offering.term(),
+                section: offering.section,
+                status: offering.status,
+                instructors: &offering.instructors,
+                instructor_ids: &offering.instructor_ids,
+                meetings: &offering.meetings,
+                books: &offering.books,
+                exam: offering.exam.as_ref(),
+                enrollment: offering.enrollment,
+                capacity: offering.capacity,
+                available: offering.available,
+                waitlist: offering.waitlist,
+                demographics: offering.demographics.as_ref(),
+                credits: offering.credits,
+                fee: offering.fee.as_ref(),
+                semester_range: offering.semester_range,
+                registration_dates: &offering.registration_dates,
+            })
+            .collect();
+        sections.sort_by_key(|section| (section.srcdb, section.section));
+        sections
+    }
+
+    /// As the course stood in `term`, without the offerings and aliases from after
+    /// it, or `None` if the course wasn't offered by then.
+    ///
+    /// The semester range, credits, fee, attributes, and grading become the latest
+    /// remaining offering's,
+    /// and the title, description, prerequisites, corequisites, and restrictions
+    /// those of the history from then, if they changed since. Unchanged ones stay as
+    /// processed, minimized or not.
+    pub fn until(mut self, term: &str) -> Option<Course> {
+        self.offerings
+            .retain(|offering| offering.date.as_str() <= term);
+        self.aliases.retain(|alias| alias.first.as_str() <= term);
+        // the first section of the latest term, as when processing
+        let date = self.offerings.iter().map(|offering| &offering.date).max()?;
+        let latest = self
+            .offerings
+            .iter()
+            .find(|offering| offering.date == *date)?;
+        if let Some(semester_range) = latest.semester_range {
+            self.semester_range = semester_range;
+        }
+        if let Some(attributes) = &latest.attributes {
+            self.attributes = attributes.clone();
+        }
+        if let Some(grading) = latest.grading {
+            self.grading = grading;
+        }
+        self.credits = latest.credits;
+        self.fee = latest.fee.clone();
+        let before = |srcdb: String| srcdb.as_str() <= term;
+
+        let len = self.title_history.len();
+        self.title_history
+            .retain(|change| before(change.term.srcdb()));
+        if let (true, Some(change)) = (self.title_history.len() < len, self.title_history.last()) {
+            self.title = change.title.clone();
+            self.description = self
+                .title_history
+                .iter()
+                .rev()
+                .find_map(|change| change.description.clone())
+                .unwrap_or_default();
+        }
+
+        let len = self.prerequisite_history.len();
+        self.prerequisite_history
+            .retain(|change| before(change.term.srcdb()));
+        if self.prerequisite_history.len() < len {
+            // as when processing, the latest that listed any
+            self.prerequisites = self
+                .prerequisite_history
+                .iter()
+                .rev()
+                .find_map(|change| change.prerequisites.clone());
+        }
+
+        let len = self.restriction_history.len();
+        self.restriction_history
+            .retain(|change| before(change.term.srcdb()));
+        if let (true, Some(change)) = (
+            self.restriction_history.len() < len,
+            self.restriction_history.last(),
+        ) {
+            let restrictions = change.restrictions.clone();
+            self.corequisites = restrictions.corequisites;
+            self.programs = restrictions.programs;
+            self.concentrations = restrictions.concentrations;
+            self.cohort = restrictions.cohort;
+            self.restricted = restrictions.restricted;
+        }
+        self.refresh();
+        Some(self)
+    }
+
+    /// Recomputes what is derived from the offerings, as after leaving some out, or
+    /// reading a file from before it was derived
+    pub fn refresh(&mut self) {
+        self.demographics = DemographicsHistory::new(&self.offerings);
+        let limited_to = |range: SemesterRange| {
+            !self.semester_range.is_empty() && self.semester_range.is_subset(range)
+        };
+        let first_year = SemesterRange::to(2);
+        let sophomore = SemesterRange::to(4).intersection(first_year.complement());
+        self.is_first_year_seminar =
+            self.attributes.contains(&Attribute::FirstYearSeminar) || limited_to(first_year);
+        self.is_sophomore_seminar =
+            self.attributes.contains(&Attribute::SophomoreSeminar) || limited_to(sophomore);
+        let latest = self.offerings.iter().map(|offering| &offering.date).max();
+        self.enrollment_cap = self
+            .offerings
+            .iter()
+            .filter(|offering| Some(&offering.date) == latest)
+            .filter_map(|offering| offering.capacity)
+            .max();
+    }
+
+    /// Graduate courses are numbered 2000 and above, or are only open to graduate students
+    pub fn level(&self) -> Level {
+        let number = self.code.numeric();
+        if number >= 2000 || self.semester_range.is_subset(SemesterRange::GRADUATE) {
+            Level::Graduate
+        } else {
+            Level::Undergraduate
+        }
+    }
+
+    fn from_offerings(code: CourseCode, mut offerings: Vec<Record>, aliases: Vec<Alias>) -> Course {
+        offerings.sort_by(|a, b| a.srcdb.cmp(&b.srcdb).reverse()); // recent first
+        let latest = offerings.first().unwrap();
+        let title = match latest.title {
+            Title::Title(ref t) => t.clone(),
+            _ => unreachable!("method precondition"),
+        };
+        let description = latest.description.clone();
+        let prerequisites = offerings
+            .iter()
+            .find_map(|offering| offering.qualifications.prerequisites.as_ref())
+            .cloned();
+        let corequisites = latest.qualifications.corequisites.clone();
+        let semester_range = latest.qualifications.semester_range;
+        let programs = latest.qualifications.programs.clone();
+        let concentrations = latest.qualifications.concentrations.clone();
+        let cohort = latest.qualifications.cohort.clone();
+        let restricted = latest.restricted;
+        let credits = latest.credits;
+        let fee = latest.fee.clone();
+        let attributes = latest.attributes.clone();
+        let grading = latest.grading;
+        let prerequisite_history = prerequisite_history(&offerings);
+        let title_history = title_history(&offerings);
+        let restriction_history = restriction_history(&offerings);
+        let offerings = offerings
+            .into_iter()
+            .map(|offering| Offering {
+                term: offering.srcdb.parse().ok(),
+                date: offering.srcdb,
+                section: offering.section.unwrap(),
+                status: offering.status,
+                instructors: offering.instructors,
+                instructor_ids: offering.instructor_ids,
+                meetings: offering.meetings,
+                books: offering.books,
+                exam: offering.exam,
+                enrollment: offering.enrollment,
+                capacity: offering.capacity,
+                available: offering.available,
+                waitlist: offering.waitlist,
+                demographics: offering.demographics,
+                credits: offering.credits,
+                fee: offering.fee,
+                semester_range: Some(offering.qualifications.semester_range),
+                attributes: Some(offering.attributes),
+                grading: Some(offering.grading),
+                registration_dates: offering.registration_dates,
+            })
+            .collect();
+        let mut course = Course {
+            code,
+            title,
+            description,
+            prerequisites,
+            corequisites,
             semester_range,
+            programs,
+            concentrations,
+            cohort,
             restricted,
+            credits,
+            fee,
+            attributes,
+            grading,
             aliases,
             offerings,
+            annotation: None,
+            tags: Vec::new(),
+            demographics: DemographicsHistory::default(),
+            prerequisite_history,
+            title_history,
+            restriction_history,
+            is_first_year_seminar: false,
+            is_sophomore_seminar: false,
+            enrollment_cap: None,
+        };
+        course.refresh();
+        course
+    }
+}
+
+#[cfg(test)]
+impl Course {
+    /// The JSON of a course as first written, with an empty title, description and
+    /// prerequisites, and an offering of section 1 in 202210 for each of `offerings`
+    /// with its fields in place of the offering's own
+    pub fn fixture(code: &str, offerings: serde_json::Value) -> serde_json::Value {
+        let offerings: Vec<_> = offerings
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|overrides| {
+                let mut offering = serde_json::json!({
+                    "date": "202210", "section": 1, "instructors": [], "meetings": [],
+                });
+                for (field, value) in overrides.as_object().unwrap() {
+                    offering[field] = value.clone();
+                }
+                offering
+            })
+            .collect();
+        serde_json::json!({
+            "code": CourseCode::try_from(code).unwrap(), "title": "", "description": "",
+            "prerequisites": null, "semester_range": [], "restricted": false, "aliases": [],
+            "offerings": offerings,
+        })
+    }
+}
+
+/// Who took a course in each term it reported demographics for, with every
+/// section of a term summed, so that visualizations can follow its audience
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DemographicsHistory {
+    /// Oldest first
+    terms: Vec<TermDemographics>,
+    /// Over every term
+    total: Demographics,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TermDemographics {
+    pub term: Term,
+    #[serde(flatten)]
+    pub demographics: Demographics,
+}
+
+impl DemographicsHistory {
+    fn new(offerings: &[Offering]) -> DemographicsHistory {
+        let mut terms: BTreeMap<Term, Demographics> = BTreeMap::new();
+        for offering in offerings {
+            if let (Some(term), Some(demographics)) = (offering.term(), &offering.demographics) {
+                let sum = terms.entry(term).or_default();
+                *sum = sum.add(demographics);
+            }
+        }
+        let total = terms
+            .values()
+            .fold(Demographics::default(), |total, term| total.add(term));
+        let terms = terms
+            .into_iter()
+            .map(|(term, demographics)| TermDemographics { term, demographics })
+            .collect();
+        DemographicsHistory { terms, total }
+    }
+
+    pub fn terms(&self) -> &[TermDemographics] {
+        &self.terms
+    }
+
+    pub fn total(&self) -> &Demographics {
+        &self.total
+    }
+}
+
+/// The prerequisites a course had from `term` until the next change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PrerequisiteChange {
+    pub term: Term,
+    /// `None` if the term's sections listed none
+    pub prerequisites: Option<PrerequisiteTree>,
+}
+
+/// The first term, and each term whose prerequisites differ from the term before.
+/// A term takes the prerequisites of the first of its sections that lists any.
+fn prerequisite_history(offerings: &[Record]) -> Vec<PrerequisiteChange> {
+    let mut terms: BTreeMap<Term, Option<&PrerequisiteTree>> = BTreeMap::new();
+    for offering in offerings {
+        if let Ok(term) = offering.srcdb.parse() {
+            let prerequisites = terms.entry(term).or_default();
+            if prerequisites.is_none() {
+                *prerequisites = offering.qualifications.prerequisites.as_ref();
+            }
+        }
+    }
+    let mut history: Vec<PrerequisiteChange> = Vec::new();
+    for (term, prerequisites) in terms {
+        if history
+            .last()
+            .is_none_or(|change| change.prerequisites.as_ref() != prerequisites)
+        {
+            history.push(PrerequisiteChange {
+                term,
+                prerequisites: prerequisites.cloned(),
+            });
+        }
+    }
+    history
+}
+
+/// The title a course had from `term` until the next change, as when a department
+/// renames its courses
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TitleChange {
+    pub term: Term,
+    pub title: String,
+    /// `None` if the same as the change before
+    pub description: Option<String>,
+}
+
+/// The first term, and each term whose title or description differs from the term
+/// before. A term takes the title of the first of its sections.
+fn title_history(offerings: &[Record]) -> Vec<TitleChange> {
+    let mut terms: BTreeMap<Term, (&str, &str)> = BTreeMap::new();
+    for offering in offerings {
+        if let (Ok(term), Title::Title(title)) = (offering.srcdb.parse(), &offering.title) {
+            terms.entry(term).or_insert((title, &offering.description));
+        }
+    }
+    let mut history = Vec::new();
+    let mut previous: Option<(&str, &str)> = None;
+    for (term, current) in terms {
+        if previous == Some(current) {
+            continue;
+        }
+        let (title, description) = current;
+        history.push(TitleChange {
+            term,
+            title: String::from(title),
+            description: match previous {
+                Some((_, previous)) if previous == description => None,
+                _ => Some(String::from(description)),
+            },
+        });
+        previous = Some(current);
+    }
+    history
+}
+
+/// The corequisites and restrictions a course had from `term` until the next change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RestrictionChange {
+    pub term: Term,
+    #[serde(flatten)]
+    pub restrictions: Restrictions,
+}
+
+/// Who may take a course besides by its prerequisites
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Restrictions {
+    pub corequisites: Option<PrerequisiteTree>,
+    pub programs: Vec<ProgramRestriction>,
+    pub concentrations: Concentrations,
+    pub cohort: Option<String>,
+    /// Only with the instructor's permission
+    pub restricted: bool,
+}
+
+/// The first term, and each term whose corequisites or restrictions differ from the
+/// term before. A term takes those of the first of its sections.
+fn restriction_history(offerings: &[Record]) -> Vec<RestrictionChange> {
+    let mut terms: BTreeMap<Term, &Record> = BTreeMap::new();
+    for offering in offerings {
+        if let Ok(term) = offering.srcdb.parse() {
+            terms.entry(term).or_insert(offering);
+        }
+    }
+    let mut history: Vec<RestrictionChange> = Vec::new();
+    for (term, offering) in terms {
+        let qualifications = &offering.qualifications;
+        let restrictions = Restrictions {
+            corequisites: qualifications.corequisites.clone(),
+            programs: qualifications.programs.clone(),
+            concentrations: qualifications.concentrations.clone(),
+            cohort: qualifications.cohort.clone(),
+            restricted: offering.restricted,
+        };
+        if history
+            .last()
+            .is_none_or(|change| change.restrictions != restrictions)
+        {
+            history.push(RestrictionChange { term, restrictions });
+        }
+    }
+    history
+}
+
+/// A term's registration deadlines, for `terms.json`
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct TermDates {
+    pub term: Term,
+    /// For each label, the date most of the term's sections give, since a few, like
+    /// half-semester courses, have their own
+    pub dates: BTreeMap<String, String>,
+    /// That gave any dates
+    pub sections: usize,
+}
+
+/// The registration deadlines of each term the sections of `courses` give, in order
+pub fn term_dates(courses: &[Course]) -> Vec<TermDates> {
+    #[derive(Default)]
+    struct Counts<'a> {
+        dates: BTreeMap<&'a str, BTreeMap<&'a str, usize>>,
+        sections: usize,
+    }
+    let mut terms: BTreeMap<Term, Counts> = BTreeMap::new();
+    for offering in courses.iter().flat_map(|course| course.offerings.iter()) {
+        let (Some(term), false) = (offering.term(), offering.registration_dates.is_empty()) else {
+            continue;
+        };
+        let counts = terms.entry(term).or_default();
+        counts.sections += 1;
+        for (label, date) in offering.registration_dates.iter() {
+            *counts
+                .dates
+                .entry(label)
+                .or_default()
+                .entry(date)
+                .or_default() += 1;
+        }
+    }
+    terms
+        .into_iter()
+        .map(|(term, counts)| TermDates {
+            term,
+            dates: counts
+                .dates
+                .into_iter()
+                .map(|(label, dates)| {
+                    // the earliest of the most common, if there's a tie
+                    let (date, _) = dates
+                        .into_iter()
+                        .rev()
+                        .max_by_key(|&(_, count)| count)
+                        .unwrap();
+                    (label.to_string(), date.to_string())
+                })
+                .collect(),
+            sections: counts.sections,
+        })
+        .collect()
+}
+
+/// A section whose demographics include students its semester range should have excluded,
+/// which suggests that the restriction was parsed incorrectly.
+pub struct Inconsistency {
+    srcdb: String,
+    code: CourseCode,
+    section: Option<u8>,
+    class: &'static str,
+    count: u32,
+    semester_range: SemesterRange,
+    restriction: String,
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.srcdb, self.code)?;
+        if let Some(section) = self.section {
+            write!(f, " S{section:02}")?;
+        }
+        write!(
+            f,
+            ": {} {} enrolled, but the semester range is {}\n    {}",
+            self.count, self.class, self.semester_range, self.restriction
+        )
+    }
+}
+
+/// The inconsistencies in the records of `source`, and how many records couldn't be
+/// audited because they aren't details `process` can read
+pub fn audit_demographics<'a, R: de::Read<'a>>(source: R) -> (Vec<Inconsistency>, usize) {
+    let mut skipped = 0;
+    let mut records = Vec::new();
+    for value in StreamDeserializer::<_, Value>::new(source) {
+        let value = match value {
+            Ok(value) => value,
+            Err(e) if e.is_eof() => break,
+            Err(_) => {
+                // the rest of the input can't be read past a syntax error
+                skipped += 1;
+                break;
+            }
+        };
+        let record = Raw::deserialize(&value).ok().and_then(|raw| {
+            let restriction = raw.registration_restrictions.clone();
+            Some((restriction, Record::try_from(raw).ok()?))
+        });
+        match record {
+            Some(record) => records.push(record),
+            None => skipped += 1,
+        }
+    }
+    let inconsistencies = records
+        .into_iter()
+        .flat_map(|(restriction, record)| {
+            let semester_range = record.qualifications.semester_range;
+            let outside = match record.demographics {
+                Some(ref demographics) if !semester_range.is_full() => {
+                    demographics.outside(semester_range)
+                }
+                _ => Vec::new(),
+            };
+            outside
+                .into_iter()
+                .map(move |(class, count)| Inconsistency {
+                    srcdb: record.srcdb.clone(),
+                    code: record.code.clone(),
+                    section: record.section,
+                    class,
+                    count,
+                    semester_range,
+                    restriction: restriction.clone(),
+                })
+        })
+        .collect();
+    (inconsistencies, skipped)
+}
+
+/// Which records `process` keeps. The status and term filters apply to every record,
+/// and the rest only to sections, so that a kept course keeps its aliases.
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    pub include_cancelled: bool,
+    /// Ignore terms after this srcdb, so that each course's title, description,
+    /// prerequisites, and restrictions are the ones it had as of then
+    pub as_of: Option<String>,
+    /// Ignore terms before this srcdb
+    pub since: Option<String>,
+    /// If any, only courses in these subjects
+    pub subjects: Vec<String>,
+    /// Only courses numbered in this range, like `0000..1999`
+    pub numbers: Option<RangeInclusive<u16>>,
+    /// Only courses of this level, by each section's number and restrictions
+    pub level: Option<Level>,
+    pub exclude_restricted: bool,
+}
+
+impl Filters {
+    fn keep(&self, record: &Record) -> bool {
+        let any = (self.include_cancelled || record.status != Status::Cancelled)
+            && self.as_of.as_ref().is_none_or(|term| record.srcdb <= *term)
+            && self.since.as_ref().is_none_or(|term| record.srcdb >= *term);
+        if let Title::AliasOf(_) = record.title {
+            return any;
         }
+        let number = record.code.numeric();
+        let level = match number >= 2000
+            || record
+                .qualifications
+                .semester_range
+                .is_subset(SemesterRange::GRADUATE)
+        {
+            true => Level::Graduate,
+            false => Level::Undergraduate,
+        };
+        any && (self.subjects.is_empty()
+            || self
+                .subjects
+                .iter()
+                .any(|subject| subject == record.code.subject()))
+            && self
+                .numbers
+                .as_ref()
+                .is_none_or(|numbers| numbers.contains(&number))
+            && self.level.is_none_or(|wanted| wanted == level)
+            && !(self.exclude_restricted && record.restricted)
+    }
+}
+
+/// A detail response `process` skipped, and why
+#[derive(Serialize, Debug)]
+pub struct Rejected {
+    pub reason: String,
+    /// The field at fault, or `None` if the record isn't a detail response at all
+    pub field: Option<&'static str>,
+    pub record: Value,
+}
+
+/// How many records `process` reads before converting them in parallel
+const BATCH: usize = 4096;
+
+/// `f` of each item, in order, with the items split evenly across the available cores
+fn parallel_map<T: Send, U: Send>(items: Vec<T>, f: impl Fn(T) -> U + Sync) -> Vec<U> {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let size = items.len().div_ceil(threads).max(1);
+    let mut items = items.into_iter();
+    let chunks: Vec<Vec<T>> = iter::from_fn(|| {
+        let chunk: Vec<T> = items.by_ref().take(size).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    })
+    .collect();
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// The courses in `source`, and the records that had to be skipped. Records are
+/// converted a batch at a time on every core, and merged in the order they came.
+pub fn process<'a, R: de::Read<'a>>(source: R, filters: &Filters) -> (Vec<Course>, Vec<Rejected>) {
+    let mut unrecognized = BTreeMap::new();
+    let (courses, rejected) = group(source, filters, &mut unrecognized);
+    warn_unrecognized(unrecognized);
+    (courses, rejected)
+}
+
+/// Like `process`, but holding only one of `partitions` parts of the records in
+/// memory at a time. A first pass writes each record under `directory` to the part of
+/// the course it belongs to, and a second groups each part into courses on its own.
+/// Records are rejected in the order of their parts, not the order they came.
+pub fn process_partitioned<'a, R: de::Read<'a>>(
+    source: R,
+    filters: &Filters,
+    directory: &Path,
+    partitions: usize,
+) -> io::Result<(Vec<Course>, Vec<Rejected>)> {
+    let path = |i: usize| directory.join(format!("{i}.jsonl"));
+    fs::create_dir_all(directory)?;
+    let mut files = (0..partitions)
+        .map(|i| Ok(BufWriter::new(File::create(path(i))?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut rejected = Vec::new();
+    for value in StreamDeserializer::<_, Value>::new(source) {
+        let value = match value {
+            Ok(value) => value,
+            Err(e) => {
+                // the rest of the input can't be read past a syntax error
+                rejected.push(Rejected {
+                    reason: e.to_string(),
+                    field: None,
+                    record: Value::Null,
+                });
+                break;
+            }
+        };
+        let file = &mut files[partition(&value, partitions)];
+        serde_json::to_writer(&mut *file, &value)?;
+        file.write_all(b"\n")?;
+    }
+    for mut file in files {
+        file.flush()?;
+    }
+    let mut courses = Vec::new();
+    let mut unrecognized = BTreeMap::new();
+    for i in 0..partitions {
+        let part = BufReader::new(File::open(path(i))?);
+        let (part_courses, part_rejected) = group(IoRead::new(part), filters, &mut unrecognized);
+        courses.extend(part_courses);
+        rejected.extend(part_rejected);
+        fs::remove_file(path(i))?;
     }
+    // left behind if it held anything else
+    fs::remove_dir(directory).ok();
+    warn_unrecognized(unrecognized);
+    courses.sort_by(|a, b| a.code.cmp(&b.code));
+    Ok((courses, rejected))
 }
 
-pub fn process<'a, R: de::Read<'a>>(source: R) -> Vec<Course> {
+/// Which of `partitions` parts a detail response goes in: the one of its course,
+/// which for an alias is the course it stands for
+fn partition(value: &Value, partitions: usize) -> usize {
+    let field = |name| value.get(name).and_then(Value::as_str).unwrap_or_default();
+    let Ok(title) = Title::from_str(field("title"));
+    let key = match title {
+        Title::AliasOf(canonical) => canonical.to_string(),
+        Title::Title(_) => match CourseCode::try_from(field("code")) {
+            Ok(code) => code.to_string(),
+            Err(_) => field("code").to_string(),
+        },
+    };
+    let mut hash = Fnv::new();
+    Hasher::write(&mut hash, key.as_bytes());
+    (hash.finish() % partitions as u64) as usize
+}
+
+fn warn_unrecognized(unrecognized: BTreeMap<String, usize>) {
+    for (paragraph, sections) in unrecognized {
+        crate::summary::warn(format!(
+            "unrecognized restriction in {sections} sections, left out: {paragraph}"
+        ));
+    }
+}
+
+/// The courses of the records in `source`, counting the paragraphs of restrictions
+/// left out of them in `unrecognized`
+fn group<'a, R: de::Read<'a>>(
+    source: R,
+    filters: &Filters,
+    unrecognized: &mut BTreeMap<String, usize>,
+) -> (Vec<Course>, Vec<Rejected>) {
     #[derive(Default)]
     struct Details {
         offerings: Vec<Record>,
-        aliases: HashSet<CourseCode>,
+        /// The terms each alias was seen in
+        aliases: HashMap<CourseCode, BTreeSet<String>>,
     }
     let mut map: HashMap<CourseCode, Details> = HashMap::new();
-    StreamDeserializer::<_, Raw>::new(source)
-        .filter_map(Result::ok)
-        .map(Record::from)
-        .for_each(|record| match record.title {
-            Title::Title(_) if record.section.is_some() => {
-                map.entry(record.code.clone())
+    let mut add = |record: Record| match record.title {
+        Title::Title(_) if record.section.is_some() => {
+            let details = map.entry(record.code.clone()).or_default();
+            for alias in record.listed_as.iter() {
+                details
+                    .aliases
+                    .entry(alias.clone())
                     .or_default()
-                    .offerings
-                    .push(record);
+                    .insert(record.srcdb.clone());
             }
-            Title::AliasOf(cannonical) => {
-                map.entry(cannonical)
-                    .or_default()
-                    .aliases
-                    .insert(record.code);
+            details.offerings.push(record);
+        }
+        Title::AliasOf(cannonical) => {
+            map.entry(cannonical)
+                .or_default()
+                .aliases
+                .entry(record.code)
+                .or_default()
+                .insert(record.srcdb);
+        }
+        _ => {}
+    };
+    let mut rejected = Vec::new();
+    let mut values = StreamDeserializer::<_, Value>::new(source);
+    loop {
+        // read a batch in order, then convert it on every core
+        let mut batch = Vec::with_capacity(BATCH);
+        let mut error = None;
+        for value in values.by_ref() {
+            match value {
+                Ok(value) => batch.push(value),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+            if batch.len() == BATCH {
+                break;
             }
-            _ => {}
+        }
+        let done = error.is_some() || batch.len() < BATCH;
+        let converted = parallel_map(batch, |value| {
+            let record = match Raw::deserialize(&value) {
+                Ok(raw) => Record::try_from(raw).map_err(|e| (e.to_string(), Some(e.field))),
+                Err(e) => Err((e.to_string(), None)),
+            };
+            (value, record)
         });
-    map.into_iter()
+        for (value, record) in converted {
+            match record {
+                Ok(record) => {
+                    for paragraph in record.qualifications.unrecognized.iter() {
+                        *unrecognized.entry(paragraph.clone()).or_default() += 1;
+                    }
+                    if filters.keep(&record) {
+                        add(record);
+                    }
+                }
+                Err((reason, field)) => rejected.push(Rejected {
+                    reason,
+                    field,
+                    record: value,
+                }),
+            }
+        }
+        if let Some(e) = error {
+            // the rest of the input can't be read past a syntax error
+            rejected.push(Rejected {
+                reason: e.to_string(),
+                field: None,
+                record: Value::Null,
+            });
+        }
+        if done {
+            break;
+        }
+    }
+    let map: Vec<_> = map
+        .into_iter()
         .filter(|(_, Details { offerings, .. })| !offerings.is_empty())
-        .map(|(code, Details { offerings, aliases })| {
-            let aliases = aliases.into_iter().collect();
-            Course::from_offerings(code, offerings, aliases)
-        })
-        .collect()
+        .collect();
+    let mut courses = parallel_map(map, |(code, Details { offerings, aliases })| {
+        let mut aliases: Vec<_> = aliases
+            .into_iter()
+            .map(|(code, terms)| Alias::new(code, &terms))
+            .collect();
+        aliases.sort_by(|a, b| a.code.cmp(&b.code));
+        Course::from_offerings(code, offerings, aliases)
+    });
+    courses.sort_by(|a, b| a.code.cmp(&b.code));
+    (courses, rejected)
 }
@@ -0,0 +1,378 @@
+use crate::html::{self, Node};
+use crate::process::{strip_html, Level, Semester};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::str::FromStr;
+
+/// One paragraph of a section's registration restrictions. CAB gives each kind of
+/// restriction its own `<p class=...>`, and words it in one of a few fixed ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sentence {
+    /// `prereq`: the expression after "Prerequisites:", without the trailing note
+    /// that some may be taken concurrently
+    Prerequisites(String),
+    /// `coreq`: "Corequisites: ..." or "Must be taken concurrently with ..."
+    Corequisites(String),
+    /// `cls`: the semester levels that may enroll, or that may not
+    SemesterLevels {
+        semesters: Vec<Semester>,
+        allowed: bool,
+    },
+    /// `maj`
+    Concentrations { names: Vec<String>, allowed: bool },
+    /// `prg`
+    Programs { names: Vec<String>, allowed: bool },
+    /// `lvl`
+    Level { level: Level, allowed: bool },
+    /// `chr`: the only cohort that may enroll, like `PLME`
+    Cohort(String),
+    /// A paragraph, or text between paragraphs, that isn't one of the above,
+    /// in canonical markup
+    Unrecognized(String),
+}
+
+/// The paragraphs of `restrictions`, in order. Never fails: a paragraph in a form
+/// this doesn't know becomes `Sentence::Unrecognized`, and the rest still parse.
+pub fn sentences(restrictions: &str) -> Vec<Sentence> {
+    html::parse(restrictions)
+        .iter()
+        .filter_map(|node| match node {
+            Node::Element(element) if element.name == "p" => {
+                let class = element.attribute("class").unwrap_or_default();
+                let inner = html::to_html(&element.children);
+                Some(sentence(class, &inner).unwrap_or_else(|| {
+                    Sentence::Unrecognized(html::to_html(std::slice::from_ref(node)))
+                }))
+            }
+            Node::Text(text) if text.trim().is_empty() => None,
+            node => Some(Sentence::Unrecognized(html::to_html(std::slice::from_ref(
+                node,
+            )))),
+        })
+        .collect()
+}
+
+/// The paragraph of class `class` whose contents are `inner`, if it has a known form
+fn sentence(class: &str, inner: &str) -> Option<Sentence> {
+    match class {
+        "prereq" => prerequisites(inner),
+        "coreq" => corequisites(inner),
+        "cls" => semester_levels(inner),
+        "maj" => concentrations(inner),
+        "prg" => programs(inner),
+        "lvl" => level(inner),
+        "chr" => cohort(inner),
+        _ => None,
+    }
+}
+
+fn prerequisites(inner: &str) -> Option<Sentence> {
+    static PATTERN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Prerequisites?: (.*?)\.(<br/><sup>\*</sup> May be taken concurrently\.)?$")
+            .unwrap()
+    });
+    let captures = PATTERN.captures(inner)?;
+    Some(Sentence::Prerequisites(strip_html(&captures[1])))
+}
+
+fn corequisites(inner: &str) -> Option<Sentence> {
+    static PATTERN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(?:Corequisites?: |Must be taken concurrently with )(.*?)\.$").unwrap()
+    });
+    let captures = PATTERN.captures(inner)?;
+    Some(Sentence::Corequisites(strip_html(&captures[1])))
+}
+
+fn semester_levels(inner: &str) -> Option<Sentence> {
+    static ALLOWED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Enrollment limited to students with a semester level of (.*?)\.$").unwrap()
+    });
+    static EXCLUDED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Students with a semester level of (.*?) may <strong>not</strong> enroll\.$")
+            .unwrap()
+    });
+    let (captures, allowed) = match ALLOWED.captures(inner) {
+        Some(captures) => (captures, true),
+        None => (EXCLUDED.captures(inner)?, false),
+    };
+    let semesters = list(&captures[1])
+        .iter()
+        .map(|semester| Semester::from_str(semester).ok())
+        .collect::<Option<_>>()?;
+    Some(Sentence::SemesterLevels { semesters, allowed })
+}
+
+fn concentrations(inner: &str) -> Option<Sentence> {
+    static ALLOWED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Enrollment is limited to students with a major in (.*?)\.$").unwrap()
+    });
+    static EXCLUDED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Students cannot enroll who have a concentration in (.*?)\.$").unwrap()
+    });
+    let (captures, allowed) = match ALLOWED.captures(inner) {
+        Some(captures) => (captures, true),
+        None => (EXCLUDED.captures(inner)?, false),
+    };
+    let names = list(&strip_html(&captures[1]));
+    Some(Sentence::Concentrations { names, allowed })
+}
+
+fn programs(inner: &str) -> Option<Sentence> {
+    static LIST: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Enrollment limited to students in the following programs:<ul>(.*?)</ul>$")
+            .unwrap()
+    });
+    static ALLOWED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Enrollment limited to students in the (.*?) program(s?)\.$").unwrap()
+    });
+    static EXCLUDED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Students in the (.*?) program may <strong>not</strong> enroll\.$").unwrap()
+    });
+    if let Some(captures) = LIST.captures(inner) {
+        let nodes = html::parse(&captures[1]);
+        let names = html::elements(&nodes)
+            .into_iter()
+            .filter(|element| element.name == "li")
+            .map(|item| {
+                strip_html(&html::to_html(&item.children))
+                    .trim()
+                    .to_string()
+            })
+            .collect();
+        return Some(Sentence::Programs {
+            names,
+            allowed: true,
+        });
+    }
+    if let Some(captures) = ALLOWED.captures(inner) {
+        let programs = strip_html(&captures[1]);
+        let names = match &captures[2] {
+            "s" => list(&programs),
+            _ => vec![programs],
+        };
+        return Some(Sentence::Programs {
+            names,
+            allowed: true,
+        });
+    }
+    let captures = EXCLUDED.captures(inner)?;
+    Some(Sentence::Programs {
+        names: vec![strip_html(&captures[1])],
+        allowed: false,
+    })
+}
+
+fn level(inner: &str) -> Option<Sentence> {
+    static ALLOWED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Enrollment is limited to (Undergraduate|Graduate) level students\.$").unwrap()
+    });
+    static EXCLUDED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(Undergraduate|Graduate) level students may <strong>not</strong> enroll\.$")
+            .unwrap()
+    });
+    let (captures, allowed) = match ALLOWED.captures(inner) {
+        Some(captures) => (captures, true),
+        None => (EXCLUDED.captures(inner)?, false),
+    };
+    let level = match &captures[1] {
+        "Undergraduate" => Level::Undergraduate,
+        _ => Level::Graduate,
+    };
+    Some(Sentence::Level { level, allowed })
+}
+
+fn cohort(inner: &str) -> Option<Sentence> {
+    // CAB has misspelled it
+    static PATTERN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^Enrollment limited to students in the (.*?) (?:cohort|chohort)\.$").unwrap()
+    });
+    let captures = PATTERN.captures(inner)?;
+    Some(Sentence::Cohort(strip_html(&captures[1])))
+}
+
+/// Names separated like `A, B or C`
+fn list(string: &str) -> Vec<String> {
+    static DELIM: Lazy<Regex> = Lazy::new(|| Regex::new(r#", | or "#).unwrap());
+    DELIM.split(string).map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sentences, Sentence};
+    use crate::process::Level;
+
+    fn one(paragraph: &str) -> Sentence {
+        let mut sentences = sentences(paragraph);
+        assert_eq!(sentences.len(), 1, "{sentences:?}");
+        sentences.remove(0)
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn prerequisites() {
+        assert_eq!(
+            one(r#"<p class="prereq">Prerequisite: <a href="/">MATH 0090</a>.</p>"#),
+            Sentence::Prerequisites("MATH 0090".into())
+        );
+        assert_eq!(
+            one(
+                r#"<p class="prereq">Prerequisites: CSCI 0150 or CSCI 0170*.<br/><sup>*</sup> May be taken concurrently.</p>"#
+            ),
+            Sentence::Prerequisites("CSCI 0150 or CSCI 0170*".into())
+        );
+    }
+
+    #[test]
+    fn corequisites() {
+        assert_eq!(
+            one(r#"<p class="coreq">Corequisite: PHYS 0070.</p>"#),
+            Sentence::Corequisites("PHYS 0070".into())
+        );
+        assert_eq!(
+            one(r#"<p class="coreq">Must be taken concurrently with CHEM 0330.</p>"#),
+            Sentence::Corequisites("CHEM 0330".into())
+        );
+    }
+
+    #[test]
+    fn semester_levels() {
+        let Sentence::SemesterLevels { semesters, allowed } = one(
+            r#"<p class="cls">Enrollment limited to students with a semester level of 05, 06, 07 or 08.</p>"#,
+        ) else {
+            panic!()
+        };
+        assert!(allowed);
+        let semesters: Vec<_> = semesters.iter().map(ToString::to_string).collect();
+        assert_eq!(semesters, ["05", "06", "07", "08"]);
+        assert!(matches!(
+            one(
+                r#"<p class="cls">Students with a semester level of 01 may <strong>not</strong> enroll.</p>"#
+            ),
+            Sentence::SemesterLevels { allowed: false, .. }
+        ));
+        assert!(matches!(
+            one(
+                r#"<p class="cls">Enrollment limited to students with a semester level of first-years.</p>"#
+            ),
+            Sentence::Unrecognized(_)
+        ));
+    }
+
+    #[test]
+    fn concentrations() {
+        assert_eq!(
+            one(
+                r#"<p class="maj">Enrollment is limited to students with a major in Applied Mathematics or Computer Science.</p>"#
+            ),
+            Sentence::Concentrations {
+                names: names(&["Applied Mathematics", "Computer Science"]),
+                allowed: true
+            }
+        );
+        assert_eq!(
+            one(
+                r#"<p class="maj">Students cannot enroll who have a concentration in Economics.</p>"#
+            ),
+            Sentence::Concentrations {
+                names: names(&["Economics"]),
+                allowed: false
+            }
+        );
+    }
+
+    #[test]
+    fn programs() {
+        assert_eq!(
+            one(
+                r#"<p class="prg">Enrollment limited to students in the following programs:<ul><li>Public Health (MPH)</li><li> Biostatistics (SCM) </li></ul></p>"#
+            ),
+            Sentence::Programs {
+                names: names(&["Public Health (MPH)", "Biostatistics (SCM)"]),
+                allowed: true
+            }
+        );
+        assert_eq!(
+            one(
+                r#"<p class="prg">Enrollment limited to students in the Engineering (SCB) or Engineering (ScM) programs.</p>"#
+            ),
+            Sentence::Programs {
+                names: names(&["Engineering (SCB)", "Engineering (ScM)"]),
+                allowed: true
+            }
+        );
+        assert_eq!(
+            one(
+                r#"<p class="prg">Enrollment limited to students in the Medicine (MD) program.</p>"#
+            ),
+            Sentence::Programs {
+                names: names(&["Medicine (MD)"]),
+                allowed: true
+            }
+        );
+        assert_eq!(
+            one(
+                r#"<p class="prg">Students in the Computer Science (SCB) program may <strong>not</strong> enroll.</p>"#
+            ),
+            Sentence::Programs {
+                names: names(&["Computer Science (SCB)"]),
+                allowed: false
+            }
+        );
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(
+            one(r#"<p class="lvl">Enrollment is limited to Graduate level students.</p>"#),
+            Sentence::Level {
+                level: Level::Graduate,
+                allowed: true
+            }
+        );
+        assert_eq!(
+            one(
+                r#"<p class="lvl">Undergraduate level students may <strong>not</strong> enroll.</p>"#
+            ),
+            Sentence::Level {
+                level: Level::Undergraduate,
+                allowed: false
+            }
+        );
+    }
+
+    #[test]
+    fn cohorts() {
+        assert_eq!(
+            one(r#"<p class="chr">Enrollment limited to students in the PLME cohort.</p>"#),
+            Sentence::Cohort("PLME".into())
+        );
+        assert_eq!(
+            one(r#"<p class="chr">Enrollment limited to students in the RUE chohort.</p>"#),
+            Sentence::Cohort("RUE".into())
+        );
+    }
+
+    #[test]
+    fn unrecognized() {
+        let parsed = sentences(
+            r#"<p class="new">Something new.</p>
+            <p class="coreq">Corequisite: PHYS 0070.</p>
+            <p class="prereq">Prerequisites are up to the instructor</p>stray"#,
+        );
+        assert_eq!(
+            parsed,
+            [
+                Sentence::Unrecognized(r#"<p class="new">Something new.</p>"#.into()),
+                Sentence::Corequisites("PHYS 0070".into()),
+                Sentence::Unrecognized(
+                    r#"<p class="prereq">Prerequisites are up to the instructor</p>"#.into()
+                ),
+                Sentence::Unrecognized("stray".into()),
+            ]
+        );
+        assert!(sentences("").is_empty());
+    }
+}
@@ -1,7 +1,9 @@
+use crate::exams;
 use crate::logic::Product;
 use crate::logic::Symbol;
 use crate::logic::Tree;
 use crate::logic::{visit_all, visit_any, visit_symbol};
+use crate::process::{Semester, SemesterRange};
 use serde::de;
 use serde::de::Error;
 use serde::de::MapAccess;
@@ -11,22 +13,128 @@ use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
 use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A subject and a number with an optional letter suffix, as in `CSCI 0150` or
+/// `ENGL 0900A`. Codes compare by the value of the number, so `CSCI 160` is
+/// `CSCI 0160`, but each keeps the digits it was written with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "WrittenCourseCode", into = "WrittenCourseCode")]
 pub struct CourseCode {
+    subject: String,
+    number: u16,
+    suffix: String,
+    /// How many digits the number was written with
+    digits: u8,
+}
+
+/// How a course code is stored, which `CourseCode` round trips through
+#[derive(Serialize, Deserialize)]
+struct WrittenCourseCode {
     subject: String,
     number: String,
 }
 
 impl CourseCode {
+    /// Fails unless `subject` is capital letters and digits, and `number` is digits
+    /// followed by any letters
     pub fn new(subject: String, number: String) -> Result<CourseCode, ()> {
-        Ok(CourseCode { subject, number })
+        if subject.is_empty()
+            || !subject
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            return Err(());
+        }
+        let digits = number
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(number.len());
+        let (value, suffix) = number.split_at(digits);
+        if !suffix.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(());
+        }
+        Ok(CourseCode {
+            number: value.parse().map_err(|_| ())?,
+            suffix: suffix.to_string(),
+            digits: u8::try_from(digits).map_err(|_| ())?,
+            subject,
+        })
     }
 
     pub fn subject(&self) -> &str {
         &self.subject
     }
+
+    /// As written, so `0001A`
+    pub fn number(&self) -> String {
+        format!(
+            "{:0width$}{}",
+            self.number,
+            self.suffix,
+            width = usize::from(self.digits)
+        )
+    }
+
+    /// The number without its letter suffix, so 1 for `0001A`
+    pub fn numeric(&self) -> u16 {
+        self.number
+    }
+
+    /// The letters after the number, if any, so `A` for `0001A`
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    fn key(&self) -> (&str, u16, &str) {
+        (&self.subject, self.number, &self.suffix)
+    }
+}
+
+impl PartialEq for CourseCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for CourseCode {}
+
+impl PartialOrd for CourseCode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CourseCode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for CourseCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl TryFrom<WrittenCourseCode> for CourseCode {
+    type Error = String;
+    fn try_from(written: WrittenCourseCode) -> Result<Self, Self::Error> {
+        let string = format!("{} {}", written.subject, written.number);
+        CourseCode::new(written.subject, written.number)
+            .map_err(|_| format!("{string:?} is not a course code"))
+    }
+}
+
+impl From<CourseCode> for WrittenCourseCode {
+    fn from(code: CourseCode) -> Self {
+        WrittenCourseCode {
+            number: code.number(),
+            subject: code.subject,
+        }
+    }
 }
 
 impl<'a> TryFrom<&'a str> for CourseCode {
@@ -38,13 +146,13 @@ impl<'a> TryFrom<&'a str> for CourseCode {
         if split.next().is_some() {
             return Err(());
         }
-        Ok(CourseCode { subject, number })
+        CourseCode::new(subject, number)
     }
 }
 
 impl fmt::Display for CourseCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.subject, self.number)
+        write!(f, "{} {}", self.subject, self.number())
     }
 }
 
@@ -56,22 +164,96 @@ pub struct ExamScore {
 
 impl fmt::Display for ExamScore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} on '{}'", self.exam, self.score)
+        write!(f, "minimum score of {} in '{}'", self.score, self.exam)
+    }
+}
+
+/// A letter grade, where a better grade is greater
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Grade {
+    C,
+    B,
+    A,
+}
+
+impl FromStr for Grade {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "A" => Ok(Grade::A),
+            "B" => Ok(Grade::B),
+            "C" => Ok(Grade::C),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+        })
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub enum Qualification {
     Course(CourseCode),
+    /// A course that may also be taken in the same semester, marked with `*` in the catalog
+    Concurrent(CourseCode),
+    /// A course passed with at least a grade, as in "minimum grade of B in MATH 0100"
+    CourseGrade {
+        code: CourseCode,
+        min_grade: Grade,
+    },
     ExamScore(ExamScore),
+    /// The student is in one of these semesters, as in "semester level 05 or above"
+    SemesterLevel(SemesterRange),
+    /// The student is in a program, like `Computer Science (SCB)`
+    Program(String),
+    /// The student has declared a concentration, like `Computer Science`
+    Concentration(String),
+    /// The instructor has let the student in
+    InstructorPermission,
 }
 
 impl Symbol for Qualification {
     fn cmp_rank(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
-            (Qualification::Course(c1), Qualification::Course(c2)) => {
+            (Qualification::Course(c1), Qualification::Course(c2))
+            | (Qualification::Concurrent(c1), Qualification::Concurrent(c2)) => {
                 c1.eq(c2).then_some(Ordering::Equal)
             }
+            // having taken the course already is at least as good as taking it alongside
+            (Qualification::Course(c1), Qualification::Concurrent(c2)) => {
+                c1.eq(c2).then_some(Ordering::Greater)
+            }
+            (Qualification::Concurrent(c1), Qualification::Course(c2)) => {
+                c1.eq(c2).then_some(Ordering::Less)
+            }
+            // a better grade is the stronger requirement, and any grade means it was taken
+            (
+                Qualification::CourseGrade {
+                    code: c1,
+                    min_grade: g1,
+                },
+                Qualification::CourseGrade {
+                    code: c2,
+                    min_grade: g2,
+                },
+            ) => c1.eq(c2).then(|| g1.cmp(g2)),
+            (
+                Qualification::CourseGrade { code: c1, .. },
+                Qualification::Course(c2) | Qualification::Concurrent(c2),
+            ) => c1.eq(c2).then_some(Ordering::Greater),
+            (
+                Qualification::Course(c1) | Qualification::Concurrent(c1),
+                Qualification::CourseGrade { code: c2, .. },
+            ) => c1.eq(c2).then_some(Ordering::Less),
+            // a score counts for its own exam, and for any the registry says it stands in
+            // for, like AP Calculus BC for AB; scores on unrelated exams don't compare
             (
                 Qualification::ExamScore(ExamScore {
                     exam: e1,
@@ -81,7 +263,39 @@ impl Symbol for Qualification {
                     exam: e2,
                     score: s2,
                 }),
-            ) => e1.eq(e2).then(|| s1.cmp(s2)),
+            ) => {
+                let ordering = s1.cmp(s2);
+                if e1 == e2 {
+                    Some(ordering)
+                } else if ordering.is_ge() && exams::implies(e1, e2) {
+                    Some(Ordering::Greater)
+                } else if ordering.is_le() && exams::implies(e2, e1) {
+                    Some(Ordering::Less)
+                } else {
+                    None
+                }
+            }
+            // a narrower range is the stronger requirement
+            (Qualification::SemesterLevel(r1), Qualification::SemesterLevel(r2)) => {
+                match (r1.is_subset(*r2), r2.is_subset(*r1)) {
+                    (true, true) => Some(Ordering::Equal),
+                    (true, false) => Some(Ordering::Greater),
+                    (false, true) => Some(Ordering::Less),
+                    (false, false) => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Qualification {
+    /// The course this qualification is about, whether taken before or alongside
+    pub fn course(&self) -> Option<&CourseCode> {
+        match self {
+            Qualification::Course(code)
+            | Qualification::Concurrent(code)
+            | Qualification::CourseGrade { code, .. } => Some(code),
             _ => None,
         }
     }
@@ -91,7 +305,52 @@ impl fmt::Display for Qualification {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Qualification::Course(c) => fmt::Display::fmt(c, f),
+            Qualification::Concurrent(c) => write!(f, "{c}*"),
+            Qualification::CourseGrade { code, min_grade } => {
+                write!(f, "minimum grade of {min_grade} in {code}")
+            }
             Qualification::ExamScore(e) => fmt::Display::fmt(e, f),
+            Qualification::SemesterLevel(r) => match r.earliest() {
+                Some(earliest) if SemesterRange::at_least(earliest) == *r => {
+                    write!(f, "semester level {earliest} or above")
+                }
+                _ => write!(f, "semester level {r}"),
+            },
+            Qualification::Program(name) => write!(f, "program in '{name}'"),
+            Qualification::Concentration(name) => write!(f, "concentration in '{name}'"),
+            Qualification::InstructorPermission => f.write_str("instructor permission"),
+        }
+    }
+}
+
+/// Which courses' prerequisites may be used to minimize a course's prerequisites
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MinimizationScope {
+    /// Only those of courses in the same subject, so that a MATH prerequisite can't
+    /// rewrite a CSCI tree
+    Subject,
+    Global,
+}
+
+impl MinimizationScope {
+    /// Trees with the same group are minimized together
+    pub fn group(self, qualification: &Qualification) -> Option<&str> {
+        match (self, qualification) {
+            (MinimizationScope::Subject, qualification) => {
+                qualification.course().map(CourseCode::subject)
+            }
+            (MinimizationScope::Global, _) => None,
+        }
+    }
+}
+
+impl FromStr for MinimizationScope {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "subject" => Ok(MinimizationScope::Subject),
+            "global" => Ok(MinimizationScope::Global),
+            _ => Err(()),
         }
     }
 }
@@ -101,6 +360,8 @@ impl fmt::Display for Qualification {
 pub enum Operator {
     Any,
     All,
+    /// Of its one child, as in "may not have taken CSCI 0170"
+    Not,
 }
 
 impl fmt::Display for Operator {
@@ -108,6 +369,7 @@ impl fmt::Display for Operator {
         f.write_str(match self {
             Operator::Any => "any",
             Operator::All => "all",
+            Operator::Not => "not",
         })
     }
 }
@@ -118,13 +380,259 @@ pub enum PrerequisiteTree {
     Operator(Operator, Vec<PrerequisiteTree>),
 }
 
+/// In words, like "CSCI 0150 and (MATH 0090 or MATH 0100)", which
+/// `PrerequisiteTree::try_from` reads back as the `simplified` tree, except for
+/// semester levels other than a semester and those after it. An operator without
+/// children has no words, and shows as `()`, which doesn't parse.
+impl fmt::Display for PrerequisiteTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (operator, children) = match self {
+            PrerequisiteTree::Qualification(qualification) => {
+                return fmt::Display::fmt(qualification, f)
+            }
+            PrerequisiteTree::Operator(_, children) if children.is_empty() => {
+                return f.write_str("()")
+            }
+            PrerequisiteTree::Operator(Operator::All, children) => (" and ", children),
+            PrerequisiteTree::Operator(Operator::Any, children) => (" or ", children),
+            // negates all of its children, so more than one is negated as a group
+            PrerequisiteTree::Operator(Operator::Not, children) => {
+                return match children.as_slice() {
+                    [child @ PrerequisiteTree::Qualification(_)] => write!(f, "not {child}"),
+                    [child] => write!(f, "not ({child})"),
+                    children => write!(
+                        f,
+                        "not ({})",
+                        PrerequisiteTree::Operator(Operator::All, children.to_vec())
+                    ),
+                };
+            }
+        };
+        let mut sep = "";
+        for child in children.iter() {
+            f.write_str(sep)?;
+            match child {
+                PrerequisiteTree::Operator(..) => write!(f, "({child})")?,
+                PrerequisiteTree::Qualification(_) => write!(f, "{child}")?,
+            }
+            sep = operator;
+        }
+        Ok(())
+    }
+}
+
+impl PrerequisiteTree {
+    /// Whether a student with `profile` meets these prerequisites
+    pub fn evaluate(&self, profile: &StudentProfile) -> bool {
+        self.satisfied_with(&|qualification| profile.has(qualification))
+    }
+
+    /// Whether `have` meets these prerequisites, where a qualification also meets
+    /// those it ranks above, like a higher score on the same exam
+    pub fn satisfied_by(&self, have: &HashSet<Qualification>) -> bool {
+        self.satisfied_with(&|qualification| has(have, qualification))
+    }
+
+    fn satisfied_with(&self, has: &dyn Fn(&Qualification) -> bool) -> bool {
+        match self {
+            PrerequisiteTree::Qualification(qualification) => has(qualification),
+            PrerequisiteTree::Operator(Operator::All, children) => {
+                children.iter().all(|child| child.satisfied_with(has))
+            }
+            PrerequisiteTree::Operator(Operator::Any, children) => {
+                children.iter().any(|child| child.satisfied_with(has))
+            }
+            PrerequisiteTree::Operator(Operator::Not, children) => {
+                !children.iter().all(|child| child.satisfied_with(has))
+            }
+        }
+    }
+
+    /// The leaves keeping `have` from meeting these prerequisites, empty if it does.
+    /// Of the alternatives, only those of the one closest to being met count, and
+    /// a negation counts the leaves under it that `have` does meet.
+    pub fn unmet(&self, have: &HashSet<Qualification>) -> Vec<&Qualification> {
+        match self {
+            PrerequisiteTree::Qualification(qualification) => match has(have, qualification) {
+                true => Vec::new(),
+                false => vec![qualification],
+            },
+            PrerequisiteTree::Operator(Operator::All, children) => children
+                .iter()
+                .flat_map(|child| child.unmet(have))
+                .collect(),
+            PrerequisiteTree::Operator(Operator::Any, children) => children
+                .iter()
+                .map(|child| child.unmet(have))
+                .min_by_key(Vec::len)
+                .unwrap_or_default(),
+            PrerequisiteTree::Operator(Operator::Not, _) if !self.satisfied_by(have) => self
+                .qualifications()
+                .filter(|qualification| has(have, qualification))
+                .collect(),
+            PrerequisiteTree::Operator(Operator::Not, _) => Vec::new(),
+        }
+    }
+
+    /// The qualifications at the leaves, left to right, repeats included
+    pub fn qualifications(&self) -> Leaves<'_> {
+        Leaves { stack: vec![self] }
+    }
+
+    /// Each distinct qualification in the tree
+    pub fn qualifications_set(&self) -> BTreeSet<&Qualification> {
+        self.qualifications().collect()
+    }
+
+    /// The operands of an operator, or nothing for a qualification
+    pub fn children(&self) -> &[PrerequisiteTree] {
+        match self {
+            PrerequisiteTree::Qualification(_) => &[],
+            PrerequisiteTree::Operator(_, children) => children,
+        }
+    }
+
+    /// Combines the tree from the leaves up, with `leaf` of each qualification, and
+    /// `operator` of each operator and what its children combined to, in order
+    pub fn fold<T, L, O>(&self, leaf: &mut L, operator: &mut O) -> T
+    where
+        L: FnMut(&Qualification) -> T,
+        O: FnMut(Operator, Vec<T>) -> T,
+    {
+        match self {
+            PrerequisiteTree::Qualification(qualification) => leaf(qualification),
+            PrerequisiteTree::Operator(op, children) => {
+                let children = children
+                    .iter()
+                    .map(|child| child.fold(leaf, operator))
+                    .collect();
+                operator(*op, children)
+            }
+        }
+    }
+
+    /// Walks the tree depth first, telling `visitor` of each node before its children
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+        fn visit<V: Visitor>(tree: &PrerequisiteTree, visitor: &mut V, depth: usize) {
+            match tree {
+                PrerequisiteTree::Qualification(qualification) => {
+                    visitor.qualification(qualification, depth)
+                }
+                PrerequisiteTree::Operator(operator, children) => {
+                    visitor.enter(*operator, depth);
+                    for child in children {
+                        visit(child, visitor, depth + 1);
+                    }
+                    visitor.exit(*operator, depth);
+                }
+            }
+        }
+        visit(self, visitor, 0)
+    }
+
+    /// The same tree, with each qualification replaced by `f` of it
+    pub fn map_qualifications<F>(&self, mut f: F) -> PrerequisiteTree
+    where
+        F: FnMut(&Qualification) -> Qualification,
+    {
+        self.fold(
+            &mut |qualification| PrerequisiteTree::Qualification(f(qualification)),
+            &mut PrerequisiteTree::Operator,
+        )
+    }
+
+    /// The same requirement in the shape the parser gives: an `any` or `all` of one
+    /// child is that child, and a `not` of several children is a `not` of their `all`
+    pub fn simplified(&self) -> PrerequisiteTree {
+        self.fold(
+            &mut |qualification| PrerequisiteTree::Qualification(qualification.clone()),
+            &mut |operator, mut children| match (operator, children.len()) {
+                (Operator::Any | Operator::All, 1) => children.pop().unwrap(),
+                (Operator::Not, 2..) => PrerequisiteTree::Operator(
+                    Operator::Not,
+                    vec![PrerequisiteTree::Operator(Operator::All, children)],
+                ),
+                _ => PrerequisiteTree::Operator(operator, children),
+            },
+        )
+    }
+
+    /// Levels from the root to the deepest leaf, 1 for a lone qualification
+    pub fn depth(&self) -> usize {
+        self.fold(&mut |_| 1, &mut |_, depths: Vec<usize>| {
+            1 + depths.into_iter().max().unwrap_or(0)
+        })
+    }
+
+    /// The number of leaves
+    pub fn len(&self) -> usize {
+        self.qualifications().count()
+    }
+
+    /// Has no leaves, like an operator without children
+    pub fn is_empty(&self) -> bool {
+        self.qualifications().next().is_none()
+    }
+}
+
+/// Told of each node of a [`PrerequisiteTree`] by [`PrerequisiteTree::visit`], with
+/// its depth below the root
+pub trait Visitor {
+    fn qualification(&mut self, qualification: &Qualification, depth: usize);
+    fn enter(&mut self, operator: Operator, depth: usize);
+    /// After the operator's children
+    fn exit(&mut self, _operator: Operator, _depth: usize) {}
+}
+
+/// Iterator over the qualifications of a [`PrerequisiteTree`], from
+/// [`PrerequisiteTree::qualifications`]
+pub struct Leaves<'a> {
+    stack: Vec<&'a PrerequisiteTree>,
+}
+
+impl<'a> Iterator for Leaves<'a> {
+    type Item = &'a Qualification;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                PrerequisiteTree::Qualification(qualification) => return Some(qualification),
+                PrerequisiteTree::Operator(_, children) => self.stack.extend(children.iter().rev()),
+            }
+        }
+    }
+}
+
+/// What a student brings to a course: the courses taken and exam scores
+/// earned, and the semester they are in
+#[derive(Debug, Clone)]
+pub struct StudentProfile {
+    pub qualifications: HashSet<Qualification>,
+    pub semester_level: Semester,
+}
+
+impl StudentProfile {
+    fn has(&self, qualification: &Qualification) -> bool {
+        match qualification {
+            Qualification::SemesterLevel(range) => range.contains(self.semester_level),
+            qualification => has(&self.qualifications, qualification),
+        }
+    }
+}
+
+fn has(have: &HashSet<Qualification>, qualification: &Qualification) -> bool {
+    have.iter().any(|have| Symbol::ge(have, qualification))
+}
+
 impl Tree for PrerequisiteTree {
     type Symbol = Qualification;
-    fn into_product(&self) -> Product<Self::Symbol> {
+    fn to_product(&self) -> Option<Product<Self::Symbol>> {
         match self {
-            PrerequisiteTree::Qualification(qualification) => visit_symbol(qualification.clone()),
+            PrerequisiteTree::Qualification(qualification) => {
+                Some(visit_symbol(qualification.clone()))
+            }
             PrerequisiteTree::Operator(Operator::All, children) => visit_all(children),
             PrerequisiteTree::Operator(Operator::Any, children) => visit_any(children),
+            PrerequisiteTree::Operator(Operator::Not, _) => None,
         }
     }
 
@@ -141,12 +649,66 @@ impl Tree for PrerequisiteTree {
     }
 }
 
+/// The JSON keys of a serialized [`PrerequisiteTree`]: a leaf is `{"course": ..}`
+/// (with `"concurrent_ok": true` if it may be taken alongside, or `"min_grade": ..`
+/// if it has to be passed with at least that grade),
+/// `{"exam": .., "score": ..}`, `{"semester_level": ..}`, `{"program": ..}`,
+/// `{"concentration": ..}`, or `{"instructor_permission": true}`, and an operator is
+/// `{"any": [..]}`, `{"all": [..]}`, or `{"not": [..]}`
+mod key {
+    pub const COURSE: &str = "course";
+    pub const CONCURRENT_OK: &str = "concurrent_ok";
+    pub const MIN_GRADE: &str = "min_grade";
+    pub const EXAM: &str = "exam";
+    pub const SCORE: &str = "score";
+    pub const SEMESTER_LEVEL: &str = "semester_level";
+    pub const PROGRAM: &str = "program";
+    pub const CONCENTRATION: &str = "concentration";
+    pub const INSTRUCTOR_PERMISSION: &str = "instructor_permission";
+    pub const ANY: &str = "any";
+    pub const ALL: &str = "all";
+    pub const NOT: &str = "not";
+
+    /// Keys written by older versions, and what they are now called
+    const LEGACY: [(&str, &str); 3] = [("code", COURSE), ("or", ANY), ("and", ALL)];
+
+    /// The canonical name of `key`, warning once if it is a legacy name
+    pub fn canonical(key: &str) -> &str {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        static WARNED: AtomicBool = AtomicBool::new(false);
+
+        match LEGACY.iter().find(|&&(legacy, _)| legacy == key) {
+            Some(&(legacy, canonical)) => {
+                if !WARNED.swap(true, Ordering::Relaxed) {
+                    crate::summary::warn(format!(
+                        "prerequisite key `{legacy}` is deprecated, use `{canonical}`"
+                    ));
+                }
+                canonical
+            }
+            None => key,
+        }
+    }
+}
+
 impl ser::Serialize for PrerequisiteTree {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         match self {
             PrerequisiteTree::Qualification(Qualification::Course(course)) => {
                 let mut map = serializer.serialize_map(Some(1))?;
-                map.serialize_entry("course", course)?;
+                map.serialize_entry(key::COURSE, course)?;
+                map.end()
+            }
+            PrerequisiteTree::Qualification(Qualification::Concurrent(course)) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry(key::COURSE, course)?;
+                map.serialize_entry(key::CONCURRENT_OK, &true)?;
+                map.end()
+            }
+            PrerequisiteTree::Qualification(Qualification::CourseGrade { code, min_grade }) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry(key::COURSE, code)?;
+                map.serialize_entry(key::MIN_GRADE, min_grade)?;
                 map.end()
             }
             PrerequisiteTree::Qualification(Qualification::ExamScore(ExamScore {
@@ -154,14 +716,38 @@ impl ser::Serialize for PrerequisiteTree {
                 score,
             })) => {
                 let mut map = serializer.serialize_map(Some(2))?;
-                map.serialize_entry("exam", exam)?;
-                map.serialize_entry("score", score)?;
+                map.serialize_entry(key::EXAM, exam)?;
+                map.serialize_entry(key::SCORE, score)?;
+                map.end()
+            }
+            PrerequisiteTree::Qualification(Qualification::SemesterLevel(range)) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(key::SEMESTER_LEVEL, range)?;
+                map.end()
+            }
+            PrerequisiteTree::Qualification(Qualification::Program(name)) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(key::PROGRAM, name)?;
+                map.end()
+            }
+            PrerequisiteTree::Qualification(Qualification::Concentration(name)) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(key::CONCENTRATION, name)?;
+                map.end()
+            }
+            PrerequisiteTree::Qualification(Qualification::InstructorPermission) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(key::INSTRUCTOR_PERMISSION, &true)?;
                 map.end()
             }
-            PrerequisiteTree::Operator(conjunctive, children) => {
+            PrerequisiteTree::Operator(operator, children) => {
                 let mut map = serializer.serialize_map(Some(1))?;
-                let conjunctive = conjunctive.to_string();
-                map.serialize_entry(conjunctive.as_str(), children)?;
+                let operator = match operator {
+                    Operator::Any => key::ANY,
+                    Operator::All => key::ALL,
+                    Operator::Not => key::NOT,
+                };
+                map.serialize_entry(operator, children)?;
                 map.end()
             }
         }
@@ -176,33 +762,85 @@ impl<'de> Deserialize<'de> for PrerequisiteTree {
             type Value = PrerequisiteTree;
 
             fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                f.write_str(r#"{"code": "<>"} or {"exam": "<>", "score": <>}"#)
+                f.write_str(
+                    r#"{"course": <>}, {"exam": "<>", "score": <>}, {"semester_level": <>}, {"program": "<>"}, {"concentration": "<>"}, {"instructor_permission": true}, {"any": [<>]}, {"all": [<>]}, or {"not": [<>]}"#,
+                )
             }
 
             fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-                let missing_field = "missing `code`, `exam`, `score`, `or`, or `and`";
-                let key: String = map.next_key()?.ok_or(Error::missing_field(missing_field))?;
+                let expected = &[
+                    key::COURSE,
+                    key::EXAM,
+                    key::SEMESTER_LEVEL,
+                    key::PROGRAM,
+                    key::CONCENTRATION,
+                    key::INSTRUCTOR_PERMISSION,
+                    key::ANY,
+                    key::ALL,
+                    key::NOT,
+                ];
+                let found: String = map.next_key()?.ok_or(Error::missing_field(key::COURSE))?;
 
-                match key.as_str() {
-                    "course" => Ok(PrerequisiteTree::Qualification(Qualification::Course(
-                        map.next_value::<CourseCode>()?,
-                    ))),
-                    "exam" => Ok(PrerequisiteTree::Qualification(Qualification::ExamScore(
+                match key::canonical(&found) {
+                    key::COURSE => {
+                        let code = map.next_value::<CourseCode>()?;
+                        let qualification = match map.next_key::<String>()? {
+                            Some(key) if key == key::CONCURRENT_OK => match map.next_value()? {
+                                true => Qualification::Concurrent(code),
+                                false => Qualification::Course(code),
+                            },
+                            Some(key) if key == key::MIN_GRADE => Qualification::CourseGrade {
+                                code,
+                                min_grade: map.next_value()?,
+                            },
+                            Some(key) => {
+                                return Err(Error::unknown_field(
+                                    &key,
+                                    &[key::CONCURRENT_OK, key::MIN_GRADE],
+                                ))
+                            }
+                            None => Qualification::Course(code),
+                        };
+                        Ok(PrerequisiteTree::Qualification(qualification))
+                    }
+                    key::EXAM => Ok(PrerequisiteTree::Qualification(Qualification::ExamScore(
                         ExamScore {
                             exam: map.next_value()?,
                             score: {
                                 let (key, value): (String, _) =
-                                    map.next_entry()?.ok_or(Error::missing_field("score"))?;
-                                if key != "score" {
-                                    return Err(Error::missing_field("thing"));
+                                    map.next_entry()?.ok_or(Error::missing_field(key::SCORE))?;
+                                if key != key::SCORE {
+                                    return Err(Error::unknown_field(&key, &[key::SCORE]));
                                 }
                                 value
                             },
                         },
                     ))),
-                    "any" => Ok(PrerequisiteTree::Operator(Operator::Any, map.next_value()?)),
-                    "all" => Ok(PrerequisiteTree::Operator(Operator::All, map.next_value()?)),
-                    _ => Err(Error::missing_field(missing_field)),
+                    key::SEMESTER_LEVEL => Ok(PrerequisiteTree::Qualification(
+                        Qualification::SemesterLevel(map.next_value()?),
+                    )),
+                    key::PROGRAM => Ok(PrerequisiteTree::Qualification(Qualification::Program(
+                        map.next_value()?,
+                    ))),
+                    key::CONCENTRATION => Ok(PrerequisiteTree::Qualification(
+                        Qualification::Concentration(map.next_value()?),
+                    )),
+                    key::INSTRUCTOR_PERMISSION => match map.next_value()? {
+                        true => Ok(PrerequisiteTree::Qualification(
+                            Qualification::InstructorPermission,
+                        )),
+                        false => Err(Error::invalid_value(de::Unexpected::Bool(false), &"true")),
+                    },
+                    key::ANY => Ok(PrerequisiteTree::Operator(Operator::Any, map.next_value()?)),
+                    key::ALL => Ok(PrerequisiteTree::Operator(Operator::All, map.next_value()?)),
+                    key::NOT => {
+                        let children: Vec<PrerequisiteTree> = map.next_value()?;
+                        if children.len() != 1 {
+                            return Err(Error::invalid_length(children.len(), &"one child"));
+                        }
+                        Ok(PrerequisiteTree::Operator(Operator::Not, children))
+                    }
+                    _ => Err(Error::unknown_field(&found, expected)),
                 }
             }
         }
@@ -210,3 +848,333 @@ impl<'de> Deserialize<'de> for PrerequisiteTree {
         deserializer.deserialize_map(PrerequisiteTreeVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic;
+    use crate::logic::Minimization;
+
+    #[test]
+    fn traversal() {
+        let tree = PrerequisiteTree::try_from(
+            "(CSCI 0150 or CSCI 0170*) and (MATH 0100 or (MATH 0090 and CSCI 0150))",
+        )
+        .unwrap();
+        let leaves: Vec<_> = tree
+            .qualifications()
+            .map(Qualification::to_string)
+            .collect();
+        assert_eq!(
+            leaves,
+            [
+                "CSCI 0150",
+                "CSCI 0170*",
+                "MATH 0100",
+                "MATH 0090",
+                "CSCI 0150"
+            ]
+        );
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.qualifications_set().len(), 4);
+        assert_eq!(tree.depth(), 4);
+        assert!(!tree.is_empty());
+        assert!(PrerequisiteTree::Operator(Operator::All, Vec::new()).is_empty());
+
+        let taken = tree.map_qualifications(|qualification| match qualification.course() {
+            Some(code) => Qualification::Course(code.clone()),
+            None => qualification.clone(),
+        });
+        assert_eq!(
+            taken.to_string(),
+            "(CSCI 0150 or CSCI 0170) and (MATH 0100 or (MATH 0090 and CSCI 0150))"
+        );
+
+        let operators = tree.fold(&mut |_| 0, &mut |_, counts: Vec<usize>| {
+            1 + counts.into_iter().sum::<usize>()
+        });
+        assert_eq!(operators, 4);
+        assert_eq!(tree.children().len(), 2);
+
+        struct Outline(Vec<String>);
+        impl Visitor for Outline {
+            fn qualification(&mut self, qualification: &Qualification, depth: usize) {
+                self.0.push(format!("{depth} {qualification}"));
+            }
+            fn enter(&mut self, operator: Operator, depth: usize) {
+                self.0.push(format!("{depth} {operator}"));
+            }
+            fn exit(&mut self, operator: Operator, depth: usize) {
+                self.0.push(format!("{depth} /{operator}"));
+            }
+        }
+        let mut outline = Outline(Vec::new());
+        PrerequisiteTree::try_from("CSCI 0150 or (MATH 0090 and MATH 0100)")
+            .unwrap()
+            .visit(&mut outline);
+        assert_eq!(
+            outline.0,
+            [
+                "0 any",
+                "1 CSCI 0150",
+                "1 all",
+                "2 MATH 0090",
+                "2 MATH 0100",
+                "1 /all",
+                "0 /any"
+            ]
+        );
+    }
+
+    fn round_trip(tree: &PrerequisiteTree) {
+        let json = serde_json::to_string(tree).unwrap();
+        let back: PrerequisiteTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(&back, tree, "{json}");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let corrections = include_str!("../resources/prerequisite_corrections.txt");
+        for line in corrections.lines().filter(|line| !line.is_empty()) {
+            let (_, tree) = line.split_once(';').unwrap();
+            round_trip(&PrerequisiteTree::try_from(tree).unwrap());
+        }
+        round_trip(
+            &PrerequisiteTree::try_from("CSCI 0150 and semester level 05 or above").unwrap(),
+        );
+        round_trip(&PrerequisiteTree::try_from("MATH 0090* or MATH 0100").unwrap());
+    }
+
+    #[test]
+    fn minimization_audit() {
+        let corrections = include_str!("../resources/prerequisite_corrections.txt");
+        let trees: Vec<_> = corrections
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (code, tree) = line.split_once(';').unwrap();
+                let code = CourseCode::try_from(code).unwrap();
+                (
+                    Qualification::Course(code),
+                    PrerequisiteTree::try_from(tree).unwrap(),
+                )
+            })
+            .collect();
+        let original = || trees.iter().map(|(symbol, tree)| (symbol.clone(), tree));
+        let minimized = logic::minimize(original(), Minimization::Full);
+        assert!(logic::audit(original(), &minimized.trees).is_empty());
+    }
+
+    #[test]
+    fn json_keys() {
+        let tree = PrerequisiteTree::try_from("CSCI 0150 or MATH 0100").unwrap();
+        let json = serde_json::to_value(&tree).unwrap();
+        let course = |number| serde_json::json!({"course": {"subject": "CSCI", "number": number}});
+        let any = json.get(key::ANY).unwrap().as_array().unwrap();
+        assert_eq!(any[0], course("0150"));
+
+        let legacy = r#"{"or": [{"code": {"subject": "CSCI", "number": "0150"}},
+            {"code": {"subject": "MATH", "number": "0100"}}]}"#;
+        assert_eq!(
+            serde_json::from_str::<PrerequisiteTree>(legacy).unwrap(),
+            tree
+        );
+
+        let error = serde_json::from_str::<PrerequisiteTree>(r#"{"xor": []}"#).unwrap_err();
+        assert!(error.to_string().contains("`any`"), "{error}");
+    }
+
+    #[test]
+    fn concurrent_ok() {
+        let tree = PrerequisiteTree::try_from("CSCI 0150* and MATH 0100").unwrap();
+        assert_eq!(tree.to_string(), "CSCI 0150* and MATH 0100");
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json[key::ALL][0][key::CONCURRENT_OK], true);
+        assert!(json[key::ALL][1].get(key::CONCURRENT_OK).is_none());
+
+        let code = CourseCode::try_from("CSCI 0150").unwrap();
+        let taken = Qualification::Course(code.clone());
+        let concurrent = Qualification::Concurrent(code);
+        assert!(Symbol::ge(&taken, &concurrent));
+        assert!(!Symbol::ge(&concurrent, &taken));
+    }
+
+    #[test]
+    fn negation() {
+        let course = |code| Qualification::Course(CourseCode::try_from(code).unwrap());
+        let leaf = |code| PrerequisiteTree::Qualification(course(code));
+        let tree = PrerequisiteTree::Operator(
+            Operator::All,
+            vec![
+                leaf("CSCI 0150"),
+                PrerequisiteTree::Operator(Operator::Not, vec![leaf("CSCI 0170")]),
+            ],
+        );
+        assert_eq!(tree.to_string(), "CSCI 0150 and (not CSCI 0170)");
+        round_trip(&tree);
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(
+            json[key::ALL][1][key::NOT][0][key::COURSE]["number"],
+            "0170"
+        );
+
+        let mut profile = StudentProfile {
+            qualifications: HashSet::from([course("CSCI 0150")]),
+            semester_level: "03".parse().unwrap(),
+        };
+        assert!(tree.evaluate(&profile));
+        profile.qualifications.insert(course("CSCI 0170"));
+        assert!(!tree.evaluate(&profile));
+
+        let redundant = PrerequisiteTree::try_from("CSCI 0150 and CSCI 0150").unwrap();
+        let trees = [
+            (course("CSCI 0200"), &tree),
+            (course("CSCI 0300"), &redundant),
+        ];
+        let minimized = logic::minimize(trees.clone(), Minimization::Full);
+        assert_eq!((minimized.before, minimized.after), (2, 1));
+        assert!(minimized
+            .trees
+            .contains(&(course("CSCI 0200"), Some(tree.clone()))));
+        assert!(logic::audit(trees, &minimized.trees).is_empty());
+    }
+
+    #[test]
+    fn minimum_grades() {
+        let tree = |s| PrerequisiteTree::try_from(s).unwrap();
+        let both = tree("minimum grade of C in MATH 0100 and minimum grade of B in MATH 0100");
+        let qualification = |s| tree(s).qualifications().next().unwrap().clone();
+        let b = qualification("minimum grade of B in MATH 0100");
+        assert!(Symbol::ge(
+            &b,
+            &qualification("minimum grade of C in MATH 0100")
+        ));
+        assert!(Symbol::ge(&b, &qualification("MATH 0100*")));
+        assert!(!Symbol::ge(&qualification("MATH 0100"), &b));
+        assert!(!Symbol::ge(&b, &qualification("MATH 0090")));
+
+        let code = Qualification::Course(CourseCode::try_from("MATH 0180").unwrap());
+        let minimized = logic::minimize([(code.clone(), &both)], Minimization::Safe);
+        assert_eq!(
+            minimized.trees,
+            [(code, Some(tree("minimum grade of B in MATH 0100")))]
+        );
+
+        round_trip(&both);
+        let json = serde_json::to_value(&both).unwrap();
+        assert_eq!(json[key::ALL][1][key::MIN_GRADE], "B");
+    }
+
+    #[test]
+    fn exam_rankings() {
+        let tree = |s| PrerequisiteTree::try_from(s).unwrap();
+        let code = Qualification::Course(CourseCode::try_from("MATH 0180").unwrap());
+        let minimize = |s| {
+            let original = tree(s);
+            let minimized = logic::minimize([(code.clone(), &original)], Minimization::Safe);
+            assert!(logic::audit([(code.clone(), &original)], &minimized.trees).is_empty());
+            minimized.trees
+        };
+        // a BC score counts as the same AB score, so only the AB alternative is needed
+        assert_eq!(
+            minimize(
+                "minimum score of 4 in 'AP Calculus BC' or minimum score of 4 in 'AP Calculus AB'"
+            ),
+            [(
+                code.clone(),
+                Some(tree("minimum score of 4 in 'AP Calculus AB'"))
+            )]
+        );
+        // but not a higher AB score than it has
+        assert_eq!(
+            minimize(
+                "minimum score of 4 in 'AP Calculus BC' or minimum score of 5 in 'AP Calculus AB'"
+            )[0]
+            .1
+            .as_ref()
+            .map(|tree| tree.qualifications().count()),
+            Some(2)
+        );
+        assert_eq!(
+            minimize(
+                "minimum score of 4 in 'AP Calculus BC' and minimum score of 4 in 'AP Calculus AB'"
+            ),
+            [(code, Some(tree("minimum score of 4 in 'AP Calculus BC'")))]
+        );
+    }
+
+    #[test]
+    fn overlapping_levels() {
+        let tree = PrerequisiteTree::try_from("junior standing and senior standing").unwrap();
+        let code = Qualification::Course(CourseCode::try_from("CSCI 1950").unwrap());
+        let minimized = logic::minimize([(code.clone(), &tree)], Minimization::Safe);
+        let senior = PrerequisiteTree::try_from("senior standing").unwrap();
+        assert_eq!(minimized.trees, [(code, Some(senior))]);
+    }
+
+    #[test]
+    fn satisfied_by() {
+        let tree = PrerequisiteTree::try_from(
+            "(CSCI 0150 or CSCI 0170) and (minimum score of 4 in 'AP Calculus BC' or MATH 0100) and not CSCI 0200",
+        )
+        .unwrap();
+        let leaf = |s| {
+            PrerequisiteTree::try_from(s)
+                .unwrap()
+                .qualifications()
+                .next()
+                .unwrap()
+                .clone()
+        };
+        let mut have = HashSet::from([
+            leaf("CSCI 0170"),
+            leaf("minimum score of 5 in 'AP Calculus BC'"),
+        ]);
+        assert!(tree.satisfied_by(&have));
+        assert!(tree.unmet(&have).is_empty());
+
+        have.insert(leaf("CSCI 0200"));
+        have.remove(&leaf("minimum score of 5 in 'AP Calculus BC'"));
+        have.insert(leaf("minimum score of 3 in 'AP Calculus BC'"));
+        assert!(!tree.satisfied_by(&have));
+        let unmet: Vec<_> = tree.unmet(&have).iter().map(|q| q.to_string()).collect();
+        assert_eq!(
+            unmet,
+            ["minimum score of 4 in 'AP Calculus BC'", "CSCI 0200"]
+        );
+    }
+
+    #[test]
+    fn course_codes() {
+        let code = |s| CourseCode::try_from(s).unwrap();
+        assert_eq!(code("CSCI 0160"), code("CSCI 160"));
+        assert_eq!(code("CSCI 160").to_string(), "CSCI 160");
+        assert_eq!(code("ENGL 0900A").suffix(), "A");
+        assert_eq!(code("ENGL 0900A").numeric(), 900);
+        assert!(code("CSCI 0170") < code("CSCI 1010"));
+        assert!(code("CSCI 900") < code("CSCI 1010"));
+        assert!(code("ENGL 0900") < code("ENGL 0900A"));
+        for invalid in [
+            "CSCI",
+            "csci 0150",
+            "CSCI A150",
+            "CSCI 01-5",
+            "CSCI 0150 A",
+            " 0150",
+        ] {
+            assert!(CourseCode::try_from(invalid).is_err(), "{invalid}");
+        }
+
+        let json = serde_json::to_value(code("ENGL 0900A")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"subject": "ENGL", "number": "0900A"})
+        );
+        assert_eq!(
+            serde_json::from_value::<CourseCode>(json).unwrap().number(),
+            "0900A"
+        );
+        let invalid = serde_json::json!({"subject": "ENGL", "number": "A"});
+        assert!(serde_json::from_value::<CourseCode>(invalid).is_err());
+    }
+}
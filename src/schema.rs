@@ -0,0 +1,125 @@
+#[cfg(feature = "binary")]
+use crate::binary;
+use crate::process::Course;
+use serde::{Deserialize, Serialize};
+use serde_json::de::IoRead;
+use serde_json::{StreamDeserializer, Value};
+use std::io::{self, Write};
+
+/// The shape of the courses `process` writes, counted up whenever a change to
+/// `Course` would keep an older file from reading the same
+pub const VERSION: u32 = 2;
+
+/// The first line of a file of courses. Files from before there was one are version 1.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Header {
+    pub schema_version: u32,
+}
+
+/// Each upgrades a course from the version one past its index to the next
+const MIGRATIONS: [fn(&mut Value); (VERSION - 1) as usize] = [offering_terms];
+
+/// Version 2 gave each offering a term, which a bare srcdb reads as
+fn offering_terms(course: &mut Value) {
+    let Some(offerings) = course.get_mut("offerings").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for offering in offerings {
+        if let (Some(date), None) = (offering.get("date").cloned(), offering.get("term")) {
+            offering["term"] = date;
+        }
+    }
+}
+
+pub fn write_header(output: &mut dyn Write) -> io::Result<()> {
+    serde_json::to_writer(
+        &mut *output,
+        &Header {
+            schema_version: VERSION,
+        },
+    )?;
+    output.write_all(b"\n")?;
+    Ok(())
+}
+
+/// The courses in `input`, JSONL or, with the `binary` feature, binary, upgraded from
+/// whatever version wrote them, and that version
+pub fn read<R: io::Read>(input: R) -> io::Result<(Vec<Course>, u32)> {
+    #[cfg(feature = "binary")]
+    let input = {
+        let mut input = io::BufReader::new(input);
+        if binary::is_binary(&mut input)? {
+            return read_values(binary::Reader::new(input)?);
+        }
+        input
+    };
+    read_values(
+        StreamDeserializer::<_, Value>::new(IoRead::new(input))
+            .map(|value| value.map_err(io::Error::from)),
+    )
+}
+
+fn read_values<I: Iterator<Item = io::Result<Value>>>(values: I) -> io::Result<(Vec<Course>, u32)> {
+    let mut values = values.peekable();
+    let version = match values.peek() {
+        Some(Ok(first)) => match Header::deserialize(first) {
+            Ok(header) => {
+                values.next();
+                header.schema_version
+            }
+            Err(_) => 1,
+        },
+        _ => 1,
+    };
+    if version == 0 || version > VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "schema version {version} is not one this cab reads, which is at most {VERSION}"
+            ),
+        ));
+    }
+    let mut courses = Vec::new();
+    for value in values {
+        let mut value = value?;
+        for migration in &MIGRATIONS[version as usize - 1..] {
+            migration(&mut value);
+        }
+        let mut course: Course = serde_json::from_value(value)?;
+        if version < VERSION {
+            course.refresh();
+        }
+        courses.push(course);
+    }
+    Ok((courses, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write_header, VERSION};
+    use crate::process::Course;
+
+    #[test]
+    fn versions() {
+        let course = Course::fixture(
+            "CSCI 0150",
+            serde_json::json!([{"demographics": {"FY": 30}}]),
+        );
+        let (courses, version) = read(format!("{course}\n").as_bytes()).unwrap();
+        assert_eq!(version, 1);
+        let upgraded = serde_json::to_value(&courses[0]).unwrap();
+        assert_eq!(upgraded["offerings"][0]["term"]["name"], "Fall 2022");
+        assert_eq!(courses[0].demographics().total().classes()[0], 30);
+
+        let mut current = Vec::new();
+        write_header(&mut current).unwrap();
+        serde_json::to_writer(&mut current, &courses[0]).unwrap();
+        let (courses, version) = read(current.as_slice()).unwrap();
+        assert_eq!((courses.len(), version), (1, VERSION));
+
+        let newer = format!("{{\"schema_version\": {}}}\n", VERSION + 1);
+        assert!(read(newer.as_bytes()).is_err());
+        assert_eq!(read(&b""[..]).unwrap().0.len(), 0);
+    }
+}
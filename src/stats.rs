@@ -0,0 +1,124 @@
+use crate::logic;
+use crate::logic::Minimization;
+use crate::process::Course;
+use crate::restrictions::{MinimizationScope, Qualification};
+use crate::term::Term;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Figures about a file of processed courses, to check a scrape at a glance
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub courses: usize,
+    pub subjects: BTreeMap<String, usize>,
+    /// Courses with a prerequisite tree
+    pub with_prerequisites: usize,
+    /// Symbols in the product forms of the trees as read
+    pub before: usize,
+    /// And after minimizing them by subject, which only shrinks trees that weren't
+    /// minimized when processed
+    pub after: usize,
+    /// Students enrolled over every section of each term
+    pub enrollment: BTreeMap<Term, u64>,
+}
+
+pub fn stats(courses: &[Course]) -> Stats {
+    let mut stats = Stats {
+        courses: courses.len(),
+        ..Stats::default()
+    };
+    for course in courses {
+        *stats
+            .subjects
+            .entry(course.code().subject().to_string())
+            .or_default() += 1;
+        for offering in course.offerings() {
+            if let (Some(term), Some(enrollment)) = (offering.term(), offering.enrollment()) {
+                *stats.enrollment.entry(term).or_default() += u64::from(enrollment);
+            }
+        }
+    }
+    let trees = courses.iter().filter_map(|course| {
+        Some((
+            Qualification::Course(course.code().clone()),
+            course.prerequisites()?,
+        ))
+    });
+    let minimized = logic::minimize_grouped(trees, Minimization::Full, |qualification| {
+        MinimizationScope::Subject
+            .group(qualification)
+            .map(String::from)
+    });
+    stats.with_prerequisites = minimized.trees.len();
+    stats.before = minimized.before;
+    stats.after = minimized.after;
+    stats
+}
+
+impl Stats {
+    pub fn report(&self) -> String {
+        let per_tree = |symbols: usize| symbols as f64 / self.with_prerequisites.max(1) as f64;
+        let mut ret = String::new();
+        writeln!(ret, "{} courses", self.courses).unwrap();
+        writeln!(
+            ret,
+            "{} with prerequisites ({:.1}%)",
+            self.with_prerequisites,
+            100.0 * self.with_prerequisites as f64 / self.courses.max(1) as f64
+        )
+        .unwrap();
+        writeln!(
+            ret,
+            "{:.2} symbols per tree, {:.2} minimized",
+            per_tree(self.before),
+            per_tree(self.after)
+        )
+        .unwrap();
+        writeln!(ret, "\n{:<8} {:>7}", "subject", "courses").unwrap();
+        for (subject, courses) in self.subjects.iter() {
+            writeln!(ret, "{subject:<8} {courses:>7}").unwrap();
+        }
+        writeln!(ret, "\n{:<12} {:>10}", "term", "enrollment").unwrap();
+        for (term, enrollment) in self.enrollment.iter() {
+            writeln!(ret, "{:<12} {enrollment:>10}", term.to_string()).unwrap();
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stats;
+    use crate::process::Course;
+
+    #[test]
+    fn counts() {
+        let course = |code: &str, prerequisites, enrollment: Option<u16>| {
+            let mut course = Course::fixture(code, serde_json::json!([{"enrollment": enrollment}]));
+            course["prerequisites"] = prerequisites;
+            serde_json::from_value::<Course>(course).unwrap()
+        };
+        let csci_0150 = serde_json::json!({"course": {"subject": "CSCI", "number": "0150"}});
+        let courses = [
+            course("CSCI 0150", serde_json::Value::Null, Some(200)),
+            course(
+                "CSCI 0200",
+                serde_json::json!({"all": [csci_0150.clone(), csci_0150]}),
+                Some(100),
+            ),
+            course("MATH 0100", serde_json::Value::Null, None),
+        ];
+        let stats = stats(&courses);
+        assert_eq!(stats.courses, 3);
+        assert_eq!(stats.subjects["CSCI"], 2);
+        assert_eq!(stats.with_prerequisites, 1);
+        assert_eq!((stats.before, stats.after), (2, 1));
+        let enrollment: Vec<_> = stats
+            .enrollment
+            .iter()
+            .map(|(term, enrollment)| (term.to_string(), *enrollment))
+            .collect();
+        assert_eq!(enrollment, [(String::from("Fall 2022"), 300)]);
+        assert!(stats.report().contains("1 with prerequisites (33.3%)"));
+    }
+}
@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A subject's line in `resources/subjects.txt`:
+/// `CODE;Name;category;color[;Display name]`
+#[derive(Debug, Clone)]
+pub struct Subject {
+    name: String,
+    category: String,
+    color: String,
+    /// What to call the subject in reports instead of its code
+    display: Option<String>,
+}
+
+impl Subject {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+}
+
+/// The subjects config, keyed by subject code
+#[derive(Debug, Clone)]
+pub struct Subjects {
+    subjects: HashMap<String, Subject>,
+}
+
+impl Default for Subjects {
+    fn default() -> Subjects {
+        Subjects::parse(include_str!("../resources/subjects.txt"))
+    }
+}
+
+impl Subjects {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Subjects> {
+        Ok(Subjects::parse(&fs::read_to_string(path)?))
+    }
+
+    fn parse(string: &str) -> Subjects {
+        let subjects = string
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(';').map(str::trim);
+                let code = fields.next().filter(|code| !code.is_empty())?;
+                let subject = Subject {
+                    name: fields.next().unwrap_or_default().to_string(),
+                    category: fields.next().unwrap_or_default().to_string(),
+                    color: fields.next().unwrap_or_default().to_string(),
+                    display: fields.next().map(String::from),
+                };
+                Some((code.to_string(), subject))
+            })
+            .collect();
+        Subjects { subjects }
+    }
+
+    pub fn get(&self, code: &str) -> Option<&Subject> {
+        self.subjects.get(code)
+    }
+
+    /// The display name override for `code` if it has one, otherwise the code itself
+    pub fn display<'a>(&'a self, code: &'a str) -> &'a str {
+        self.get(code)
+            .and_then(|subject| subject.display.as_deref())
+            .unwrap_or(code)
+    }
+
+    /// Subject codes in the natural order of their display names
+    pub fn sort<S: AsRef<str>>(&self, codes: &mut [S]) {
+        codes.sort_by(|a, b| {
+            natural_cmp(self.display(a.as_ref()), self.display(b.as_ref()))
+                .then_with(|| a.as_ref().cmp(b.as_ref()))
+        });
+    }
+}
+
+/// Compares case-insensitively, and runs of digits by their value, so that
+/// "Engineering 2" comes before "engineering 10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(digit);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{natural_cmp, Subjects};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn natural() {
+        assert_eq!(
+            natural_cmp("Engineering 2", "engineering 10"),
+            Ordering::Less
+        );
+        assert_eq!(natural_cmp("apma", "APMA"), Ordering::Equal);
+        assert_eq!(natural_cmp("MATH", "MCM"), Ordering::Less);
+        assert_eq!(natural_cmp("A02", "A2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn display_names() {
+        let subjects = Subjects::parse(
+            "APMA;Applied Mathematics;other;ffe119;Zoology\nCSCI;Computer Science;other;ffe119\n",
+        );
+        assert_eq!(subjects.display("APMA"), "Zoology");
+        assert_eq!(subjects.display("CSCI"), "CSCI");
+        assert_eq!(subjects.display("XYZW"), "XYZW");
+        let mut codes = ["CSCI", "APMA", "BIOL"];
+        subjects.sort(&mut codes);
+        assert_eq!(codes, ["BIOL", "CSCI", "APMA"]);
+        assert_eq!(Subjects::default().get("GREK").unwrap().color(), "dcbeff");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::compression;
+use crate::hash::Sha256;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// What the subcommands have reported about the current run, for `--summary-json`
+#[derive(Debug, Default)]
+struct Recorder {
+    inputs: Vec<PathBuf>,
+    outputs: Vec<PathBuf>,
+    counts: BTreeMap<String, u64>,
+    warnings: Vec<String>,
+}
+
+static RECORDER: Mutex<Recorder> = Mutex::new(Recorder {
+    inputs: Vec::new(),
+    outputs: Vec::new(),
+    counts: BTreeMap::new(),
+    warnings: Vec::new(),
+});
+
+fn record(f: impl FnOnce(&mut Recorder)) {
+    f(&mut RECORDER.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// A file or directory the run read
+pub fn input<P: AsRef<Path>>(path: P) {
+    let path = path.as_ref().to_path_buf();
+    record(|recorder| recorder.inputs.push(path));
+}
+
+/// A file or directory the run wrote, hashed once the run is over
+pub fn output<P: AsRef<Path>>(path: P) {
+    let path = path.as_ref().to_path_buf();
+    record(|recorder| recorder.outputs.push(path));
+}
+
+/// Sets a count, like the number of courses written
+pub fn count(name: &str, value: usize) {
+    record(|recorder| {
+        recorder.counts.insert(name.to_string(), value as u64);
+    });
+}
+
+/// Prints `message` as a warning, and keeps it for the summary
+pub fn warn(message: String) {
+    eprintln!("warning: {message}");
+    record(|recorder| recorder.warnings.push(message));
+}
+
+/// A line of the summary about one input or output
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct File {
+    pub path: PathBuf,
+    /// `None` if it doesn't exist
+    pub bytes: Option<u64>,
+    /// Of the contents as stored, or for a directory, of the relative path and
+    /// contents of each file in it, in order
+    pub sha256: Option<String>,
+}
+
+impl File {
+    fn new(path: PathBuf) -> File {
+        let mut hash = Sha256::new();
+        match digest(&path, &path, &mut hash) {
+            Ok(bytes) => File {
+                path,
+                bytes: Some(bytes),
+                sha256: Some(hash.hex()),
+            },
+            Err(_) => File {
+                path,
+                bytes: None,
+                sha256: None,
+            },
+        }
+    }
+}
+
+/// The number of bytes hashed
+fn digest(root: &Path, path: &Path, hash: &mut Sha256) -> io::Result<u64> {
+    if !path.is_dir() {
+        let contents = fs::read(path)?;
+        hash.update(&contents);
+        return Ok(contents.len() as u64);
+    }
+    let mut entries: Vec<_> = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    let mut bytes = 0;
+    for entry in entries {
+        let relative = entry.strip_prefix(root).unwrap_or(&entry);
+        hash.update(relative.to_string_lossy().as_bytes());
+        bytes += digest(root, &entry, hash)?;
+    }
+    Ok(bytes)
+}
+
+/// The machine-readable account of a run, so that orchestrators can track a
+/// pipeline without reading its logs
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    pub subcommand: String,
+    pub arguments: Vec<String>,
+    /// Seconds since the Unix epoch
+    pub started: u64,
+    pub duration_seconds: f64,
+    /// `ok`, or `error` with the `error` that ended the run
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub inputs: Vec<File>,
+    pub outputs: Vec<File>,
+    pub counts: BTreeMap<String, u64>,
+    pub warnings: Vec<String>,
+}
+
+impl Summary {
+    /// Of everything recorded since the run started at `started`
+    pub fn new(subcommand: &str, started: SystemTime, result: &io::Result<()>) -> Summary {
+        let recorder = std::mem::take(&mut *RECORDER.lock().unwrap_or_else(|e| e.into_inner()));
+        let duration = started.elapsed().unwrap_or(Duration::ZERO);
+        let started = started
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Summary {
+            subcommand: subcommand.to_string(),
+            arguments: std::env::args().skip(1).collect(),
+            started,
+            duration_seconds: duration.as_secs_f64(),
+            status: match result {
+                Ok(()) => "ok",
+                Err(_) => "error",
+            },
+            error: result.as_ref().err().map(io::Error::to_string),
+            inputs: recorder.inputs.into_iter().map(File::new).collect(),
+            outputs: recorder.outputs.into_iter().map(File::new).collect(),
+            counts: recorder.counts,
+            warnings: recorder.warnings,
+        }
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        compression::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::File;
+    use std::fs;
+
+    #[test]
+    fn files() {
+        let directory = std::env::temp_dir().join(format!("cab-summary-{}", std::process::id()));
+        fs::create_dir_all(directory.join("shards")).unwrap();
+        fs::write(directory.join("cab.jsonl"), "").unwrap();
+        fs::write(directory.join("shards/202210.jsonl"), "abc").unwrap();
+        let empty = File::new(directory.join("cab.jsonl"));
+        assert_eq!(empty.bytes, Some(0));
+        assert_eq!(
+            empty.sha256.as_deref(),
+            Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        let shards = File::new(directory.join("shards"));
+        assert_eq!(shards.bytes, Some(3));
+        fs::write(directory.join("shards/202220.jsonl"), "").unwrap();
+        assert_ne!(File::new(directory.join("shards")).sha256, shards.sha256);
+        let missing = File::new(directory.join("missing.jsonl"));
+        assert_eq!((missing.bytes, missing.sha256), (None, None));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use crate::config;
+use crate::process::{Attribute, Course};
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// When a course gets a tag: every predicate given has to match, and a predicate
+/// with several values matches if any of them does
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Rule {
+    tag: String,
+    subjects: Vec<String>,
+    numbers: Option<RangeInclusive<u16>>,
+    attributes: Vec<Attribute>,
+    /// Lowercase, matched against the lowercase description
+    keywords: Vec<String>,
+}
+
+impl Rule {
+    fn matches(&self, course: &Course) -> bool {
+        let code = course.code();
+        let description = course.description().to_lowercase();
+        (self.subjects.is_empty() || self.subjects.iter().any(|s| s == code.subject()))
+            && self
+                .numbers
+                .as_ref()
+                .is_none_or(|numbers| numbers.contains(&code.numeric()))
+            && (self.attributes.is_empty()
+                || self
+                    .attributes
+                    .iter()
+                    .any(|attribute| course.attributes().contains(attribute)))
+            && (self.keywords.is_empty()
+                || self
+                    .keywords
+                    .iter()
+                    .any(|keyword| description.contains(keyword.as_str())))
+    }
+}
+
+/// `tags.toml`, one table per tag, applied to every course when processing:
+///
+/// ```toml
+/// [intro]
+/// subject = ["CSCI", "MATH"]
+/// number = "0000..0999"
+///
+/// [theory]
+/// subject = "CSCI"
+/// keyword = ["algorithm", "complexity"]
+/// ```
+#[derive(Debug, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Empty if there is no file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Rules> {
+        let path = path.as_ref();
+        let tables = match config::tables(path) {
+            Ok(tables) => tables,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Rules::default()),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: String| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{path:?}: {message}"))
+        };
+        let mut rules = Vec::new();
+        for (tag, table) in tables {
+            let mut rule = Rule {
+                tag,
+                ..Rule::default()
+            };
+            for (key, value) in table {
+                let values = value.split(',').map(str::trim);
+                match key.as_str() {
+                    "subject" => rule.subjects = values.map(String::from).collect(),
+                    "number" => {
+                        rule.numbers = Some(numbers(&value).ok_or_else(|| {
+                            invalid(format!(
+                                "invalid number range {value} for {}, expected like 1000..1999",
+                                rule.tag
+                            ))
+                        })?)
+                    }
+                    "attribute" => {
+                        rule.attributes = values.map(|a| Attribute::from(a.to_string())).collect()
+                    }
+                    "keyword" => rule.keywords = values.map(str::to_lowercase).collect(),
+                    _ => {
+                        return Err(invalid(format!(
+                        "unknown key {key} for {}, expected subject, number, attribute, or keyword",
+                        rule.tag
+                    )))
+                    }
+                }
+            }
+            rules.push(rule);
+        }
+        Ok(Rules { rules })
+    }
+
+    /// The tags of the rules that `course` matches, in alphabetical order
+    pub fn tags(&self, course: &Course) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(course))
+            .map(|rule| rule.tag.clone())
+            .collect()
+    }
+
+    /// Replaces each course's tags with the ones its rules give
+    pub fn apply(&self, courses: &mut [Course]) {
+        for course in courses.iter_mut() {
+            course.set_tags(self.tags(course));
+        }
+    }
+}
+
+/// Like `1000..1999`, including both ends, or a single number
+pub fn numbers(range: &str) -> Option<RangeInclusive<u16>> {
+    match range.split_once("..") {
+        Some((start, end)) => Some(start.trim().parse().ok()?..=end.trim().parse().ok()?),
+        None => {
+            let number = range.trim().parse().ok()?;
+            Some(number..=number)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rules;
+    use crate::process::Course;
+    use std::fs;
+
+    fn course(code: &str, description: &str, attributes: &[&str]) -> Course {
+        let mut course = Course::fixture(code, serde_json::json!([]));
+        course["description"] = description.into();
+        course["attributes"] = attributes.into();
+        serde_json::from_value(course).unwrap()
+    }
+
+    #[test]
+    fn rules() {
+        let path = std::env::temp_dir().join(format!("cab-tags-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            r#"
+            [intro]
+            subject = ["CSCI", "MATH"]
+            number = "0000..0999"
+
+            [systems]
+            subject = "CSCI"
+            keyword = ["Operating Systems", "networks"]
+
+            [writing]
+            attribute = "WRIT"
+            "#,
+        )
+        .unwrap();
+        let rules = Rules::load(&path).unwrap();
+        let mut courses = vec![
+            course("CSCI 0150", "An introduction to programming", &["WRIT"]),
+            course("CSCI 1670", "Covers operating systems in depth", &[]),
+            course("MATH 1530", "Abstract algebra", &[]),
+            course("ENGN 0300", "Computer networks", &[]),
+        ];
+        rules.apply(&mut courses);
+        let tags: Vec<_> = courses
+            .iter()
+            .map(|course| course.tags().join(","))
+            .collect();
+        assert_eq!(tags, ["intro,writing", "systems", "", ""]);
+
+        fs::write(&path, "[intro]\nnumber = \"low\"").unwrap();
+        assert!(Rules::load(&path).is_err());
+        fs::write(&path, "[intro]\nlevel = \"undergrad\"").unwrap();
+        assert!(Rules::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(Rules::load(&path).unwrap().rules.is_empty());
+    }
+}
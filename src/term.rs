@@ -0,0 +1,158 @@
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// In the order they come in a calendar year
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Fall,
+}
+
+impl Season {
+    /// The last two digits of a srcdb
+    fn suffix(self) -> &'static str {
+        match self {
+            Season::Winter => "15",
+            Season::Spring => "20",
+            Season::Summer => "00",
+            Season::Fall => "10",
+        }
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Season::Winter => "Winter",
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Fall => "Fall",
+        })
+    }
+}
+
+/// A term, as the API's srcdb like `202210` names it: the academic year it starts
+/// (2022), then `00` for summer, `10` for fall, `15` for winter, or `20` for spring.
+/// Winter and spring fall in the calendar year after, so `202220` is Spring 2023.
+///
+/// Serialized as both forms, `{"srcdb": "202210", "year": 2022, "season": "fall",
+/// "name": "Fall 2022"}`, and read from that or a bare srcdb.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Term {
+    /// In the calendar
+    year: u16,
+    season: Season,
+}
+
+impl Term {
+    pub fn new(year: u16, season: Season) -> Term {
+        Term { year, season }
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn season(&self) -> Season {
+        self.season
+    }
+
+    pub fn srcdb(&self) -> String {
+        let academic = match self.season {
+            Season::Summer | Season::Fall => self.year,
+            Season::Winter | Season::Spring => self.year - 1,
+        };
+        format!("{academic}{}", self.season.suffix())
+    }
+}
+
+impl FromStr for Term {
+    type Err = ();
+    fn from_str(srcdb: &str) -> Result<Self, Self::Err> {
+        if srcdb.len() != 6 || !srcdb.is_ascii() {
+            return Err(());
+        }
+        let (academic, suffix) = srcdb.split_at(4);
+        let academic: u16 = academic.parse().map_err(|_| ())?;
+        let (season, year) = match suffix {
+            "00" => (Season::Summer, academic),
+            "10" => (Season::Fall, academic),
+            "15" => (Season::Winter, academic + 1),
+            "20" => (Season::Spring, academic + 1),
+            _ => return Err(()),
+        };
+        Ok(Term { year, season })
+    }
+}
+
+/// Like "Fall 2022"
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.season, self.year)
+    }
+}
+
+impl Serialize for Term {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Term", 4)?;
+        state.serialize_field("srcdb", &self.srcdb())?;
+        state.serialize_field("year", &self.year)?;
+        state.serialize_field("season", &self.season)?;
+        state.serialize_field("name", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Term {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Form {
+            Srcdb(String),
+            Both { srcdb: String },
+        }
+
+        let (Form::Srcdb(srcdb) | Form::Both { srcdb }) = Form::deserialize(deserializer)?;
+        srcdb
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid srcdb: {srcdb:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Season, Term};
+
+    #[test]
+    fn srcdb() {
+        let terms: Vec<_> = ["202200", "202210", "202215", "202220"]
+            .into_iter()
+            .map(|srcdb| srcdb.parse::<Term>().unwrap())
+            .collect();
+        let names: Vec<_> = terms.iter().map(Term::to_string).collect();
+        assert_eq!(
+            names,
+            ["Summer 2022", "Fall 2022", "Winter 2023", "Spring 2023"]
+        );
+        assert!(terms.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(Term::new(2023, Season::Spring).srcdb(), "202220");
+        assert!("202230".parse::<Term>().is_err());
+        assert!("2022".parse::<Term>().is_err());
+
+        let json = serde_json::to_value(terms[1]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"srcdb": "202210", "year": 2022, "season": "fall", "name": "Fall 2022"})
+        );
+        assert_eq!(serde_json::from_value::<Term>(json).unwrap(), terms[1]);
+        assert_eq!(
+            serde_json::from_str::<Term>("\"202220\"").unwrap(),
+            terms[3]
+        );
+    }
+}
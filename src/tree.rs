@@ -0,0 +1,126 @@
+use crate::restrictions::{CourseCode, Operator, PrerequisiteTree, Qualification, Visitor};
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// An indented outline
+    Text,
+    /// A `forest` environment, for embedding in documents
+    Latex,
+}
+
+impl FromStr for Format {
+    type Err = ();
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "text" => Ok(Format::Text),
+            "latex" => Ok(Format::Latex),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A course's prerequisite tree, rooted at the course
+pub fn render(
+    code: &CourseCode,
+    prerequisites: Option<&PrerequisiteTree>,
+    format: Format,
+) -> String {
+    let mut ret = String::new();
+    match format {
+        Format::Text => {
+            writeln!(ret, "{code}").unwrap();
+            if let Some(tree) = prerequisites {
+                tree.visit(&mut Text(&mut ret));
+            }
+        }
+        Format::Latex => {
+            ret.push_str("\\begin{forest}\n");
+            write!(ret, "[{{{}}}", escape(&code.to_string())).unwrap();
+            if let Some(tree) = prerequisites {
+                tree.visit(&mut Latex(&mut ret));
+            }
+            ret.push_str("\n]\n\\end{forest}\n");
+        }
+    }
+    ret
+}
+
+fn label(operator: Operator) -> &'static str {
+    match operator {
+        Operator::All => "and",
+        Operator::Any => "or",
+        Operator::Not => "not",
+    }
+}
+
+/// An indented outline, below a root at depth 0
+struct Text<'a>(&'a mut String);
+
+impl Visitor for Text<'_> {
+    fn qualification(&mut self, qualification: &Qualification, depth: usize) {
+        writeln!(self.0, "{}{qualification}", "  ".repeat(depth + 1)).unwrap();
+    }
+
+    fn enter(&mut self, operator: Operator, depth: usize) {
+        writeln!(self.0, "{}{}", "  ".repeat(depth + 1), label(operator)).unwrap();
+    }
+}
+
+/// Nested `forest` brackets, below a root at depth 0
+struct Latex<'a>(&'a mut String);
+
+impl Visitor for Latex<'_> {
+    fn qualification(&mut self, qualification: &Qualification, depth: usize) {
+        let label = escape(&qualification.to_string());
+        write!(self.0, "\n{}[{{{label}}}]", "  ".repeat(depth + 1)).unwrap();
+    }
+
+    fn enter(&mut self, operator: Operator, depth: usize) {
+        let label = label(operator);
+        write!(self.0, "\n{}[{{{label}}}", "  ".repeat(depth + 1)).unwrap();
+    }
+
+    fn exit(&mut self, _operator: Operator, _depth: usize) {
+        self.0.push(']');
+    }
+}
+
+/// Escapes the characters LaTeX treats specially
+fn escape(string: &str) -> String {
+    let mut ret = String::new();
+    for c in string.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                ret.push('\\');
+                ret.push(c);
+            }
+            '~' => ret.push_str("\\textasciitilde{}"),
+            '^' => ret.push_str("\\textasciicircum{}"),
+            '\\' => ret.push_str("\\textbackslash{}"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Format};
+    use crate::restrictions::{CourseCode, PrerequisiteTree};
+
+    #[test]
+    fn latex() {
+        let code = CourseCode::try_from("CSCI 0200").unwrap();
+        let tree = PrerequisiteTree::try_from("CSCI 0150 and (MATH 0090 or MATH 0100)").unwrap();
+        assert_eq!(
+            render(&code, Some(&tree), Format::Latex),
+            "\\begin{forest}\n[{CSCI 0200}\n  [{and}\n    [{CSCI 0150}]\n    [{or}\n      [{MATH 0090}]\n      [{MATH 0100}]]]\n]\n\\end{forest}\n"
+        );
+        assert_eq!(
+            render(&code, Some(&tree), Format::Text),
+            "CSCI 0200\n  and\n    CSCI 0150\n    or\n      MATH 0090\n      MATH 0100\n"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::memory;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::de::IoRead;
+use serde_json::StreamDeserializer;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// What one `process` run produced, appended to the trends database so the
+/// catalog and parser coverage can be followed over many scrapes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Summary {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub hash: String,
+    pub courses: usize,
+    pub with_prerequisites: usize,
+    pub minimization_before: usize,
+    pub minimization_after: usize,
+    pub subjects: BTreeMap<String, usize>,
+    /// Of each stage of processing
+    #[serde(default)]
+    pub memory: Vec<memory::Usage>,
+    /// Records `process` skipped as malformed
+    #[serde(default)]
+    pub rejected: usize,
+}
+
+impl Summary {
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// Subjects whose course count changed since `previous`
+    fn highlights(&self, previous: &Summary) -> String {
+        let mut ret = String::new();
+        let subjects: BTreeSet<_> = self
+            .subjects
+            .keys()
+            .chain(previous.subjects.keys())
+            .collect();
+        for subject in subjects {
+            let old = previous.subjects.get(subject).copied().unwrap_or(0);
+            let new = self.subjects.get(subject).copied().unwrap_or(0);
+            if old != new {
+                write!(ret, " {subject}{:+}", new as i64 - old as i64).unwrap();
+            }
+        }
+        ret
+    }
+}
+
+pub fn record<P: AsRef<Path>>(database: P, summary: &Summary) -> io::Result<()> {
+    let mut database = File::options().create(true).append(true).open(database)?;
+    serde_json::to_writer(&mut database, summary)?;
+    database.write_all(b"\n")
+}
+
+pub fn load<P: AsRef<Path>>(database: P) -> io::Result<Vec<Summary>> {
+    let database = File::open(database)?;
+    let summaries =
+        StreamDeserializer::new(IoRead::new(database)).collect::<serde_json::Result<_>>()?;
+    Ok(summaries)
+}
+
+/// One line per run, with a bar for the number of courses
+pub fn chart(summaries: &[Summary]) -> String {
+    const WIDTH: usize = 40;
+    let max = summaries
+        .iter()
+        .map(|s| s.courses)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let mut ret = String::new();
+    writeln!(
+        ret,
+        "{:<10} {:<16} {:>7} {:>7} {:>15}  courses",
+        "date", "hash", "courses", "prereq", "minimized"
+    )
+    .unwrap();
+    for (i, summary) in summaries.iter().enumerate() {
+        let coverage = 100 * summary.with_prerequisites / summary.courses.max(1);
+        let bar = "#".repeat(WIDTH * summary.courses / max);
+        let highlights = match i.checked_sub(1) {
+            Some(previous) => summary.highlights(&summaries[previous]),
+            None => String::new(),
+        };
+        writeln!(
+            ret,
+            "{} {} {:>7} {:>6}% {:>7}/{:<7}  {bar:<WIDTH$}{highlights}",
+            date(summary.time),
+            summary.hash,
+            summary.courses,
+            coverage,
+            summary.minimization_after,
+            summary.minimization_before,
+        )
+        .unwrap();
+    }
+    ret
+}
+
+/// `YYYY-MM-DD` of a unix timestamp
+pub fn date(time: u64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (time / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::date;
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951782400), "2000-02-29");
+        assert_eq!(date(1663632000), "2022-09-20");
+    }
+}